@@ -0,0 +1,421 @@
+// Copyright (c) 2026, Ben Boeckel
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of this project nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Effective permission calculations
+//!
+//! The kernel decides whether an operation on a key is allowed by selecting one of the user,
+//! group, or other permission masks of the key based on the caller's credentials and then adding
+//! the possessor mask if the caller possesses the key. The types in this module reproduce that
+//! logic so that the outcome of an operation can be predicted (and explained) before attempting
+//! it.
+
+use std::fmt;
+
+use crate::{Description, KeyPermissions, Permission, Result};
+
+/// The bits which make up a single permission class.
+const CLASS_MASK: KeyPermissions = 0x3f;
+
+/// Collapse all permission classes into the `OTHER_*` bits.
+fn normalize(perms: Permission) -> Permission {
+    let bits = perms.bits();
+    Permission::from_bits_truncate((bits | (bits >> 8) | (bits >> 16) | (bits >> 24)) & CLASS_MASK)
+}
+
+/// Extract the permission class at `shift` into the `OTHER_*` bits.
+fn class_bits(perms: Permission, shift: u32) -> Permission {
+    Permission::from_bits_truncate((perms.bits() >> shift) & CLASS_MASK)
+}
+
+/// The credentials of a process accessing a key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Caller {
+    /// The (filesystem) user ID of the caller.
+    pub uid: libc::uid_t,
+    /// The (filesystem) group ID of the caller.
+    pub gid: libc::gid_t,
+    /// The supplementary groups of the caller.
+    pub groups: Vec<libc::gid_t>,
+    /// Whether the caller possesses the key.
+    ///
+    /// A key is possessed if it is reachable from the caller's thread, process, or session
+    /// keyrings through keyrings which grant the `search` permission. See `keyrings(7)`.
+    pub possessor: bool,
+}
+
+impl Caller {
+    /// The credentials of the current process.
+    pub fn current(possessor: bool) -> Result<Self> {
+        Ok(Caller {
+            uid: unsafe { libc::geteuid() },
+            gid: unsafe { libc::getegid() },
+            groups: current_groups()?,
+            possessor,
+        })
+    }
+
+    fn in_group(&self, gid: libc::gid_t) -> Option<GroupMatch> {
+        if self.gid == gid {
+            Some(GroupMatch::Primary)
+        } else if self.groups.contains(&gid) {
+            Some(GroupMatch::Supplementary)
+        } else {
+            None
+        }
+    }
+}
+
+fn current_groups() -> Result<Vec<libc::gid_t>> {
+    loop {
+        let count = unsafe { libc::getgroups(0, std::ptr::null_mut()) };
+        if count < 0 {
+            return Err(errno::errno());
+        }
+
+        let mut groups = vec![0; count as usize];
+        let res = unsafe { libc::getgroups(count, groups.as_mut_ptr()) };
+        if res >= 0 {
+            groups.truncate(res as usize);
+            return Ok(groups);
+        }
+
+        // The group list changed between the calls; try again.
+        let err = errno::errno();
+        if err != errno::Errno(libc::EINVAL) {
+            return Err(err);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GroupMatch {
+    Primary,
+    Supplementary,
+}
+
+/// The permission mask selected for the caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermissionClass {
+    /// The caller owns the key.
+    User,
+    /// The caller's primary group owns the key.
+    Group,
+    /// One of the caller's supplementary groups owns the key.
+    SupplementaryGroup,
+    /// Neither the user nor the group of the key matched the caller.
+    Other,
+}
+
+impl PermissionClass {
+    fn shift(self) -> u32 {
+        match self {
+            PermissionClass::User => 16,
+            PermissionClass::Group | PermissionClass::SupplementaryGroup => 8,
+            PermissionClass::Other => 0,
+        }
+    }
+
+    fn mask_name(self) -> &'static str {
+        match self {
+            PermissionClass::User => "user",
+            PermissionClass::Group | PermissionClass::SupplementaryGroup => "group",
+            PermissionClass::Other => "other",
+        }
+    }
+}
+
+/// The access the kernel grants a caller to a key.
+///
+/// All permissions here are normalized into the `OTHER_*` bits since that is how the kernel
+/// compares them against the permission an operation requires.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Access {
+    /// The permission mask selected for the caller.
+    pub class: PermissionClass,
+    /// The permissions granted by the selected mask.
+    pub class_permissions: Permission,
+    /// The permissions granted by possession, if the caller possesses the key.
+    pub possessor_permissions: Option<Permission>,
+    /// The effective permissions of the caller.
+    pub effective: Permission,
+    uid: libc::uid_t,
+    gid: libc::gid_t,
+    group_mask_ignored: bool,
+}
+
+impl Access {
+    /// Compute the access a caller has to the key described by `description`.
+    ///
+    /// The owning user's mask is used if the caller's user ID matches. Otherwise, the group mask
+    /// is used if the key's group is the caller's group or one of its supplementary groups, but
+    /// only if the group mask grants any permissions at all. Failing that, the other mask is used.
+    /// The possessor mask is added on top of the selected mask for possessors.
+    ///
+    /// Note that the kernel does not grant any implicit access to privileged users.
+    pub fn compute(description: &Description, caller: &Caller) -> Self {
+        let perms = description.perms;
+        let group_mask = class_bits(perms, PermissionClass::Group.shift());
+        // The kernel uses `(gid_t)-1` to indicate that the key has no group.
+        let group_valid = description.gid != !0;
+        let group_match = if group_valid {
+            caller.in_group(description.gid)
+        } else {
+            None
+        };
+
+        let (class, group_mask_ignored) = if caller.uid == description.uid {
+            (PermissionClass::User, false)
+        } else {
+            match group_match {
+                Some(_) if group_mask.is_empty() => (PermissionClass::Other, true),
+                Some(GroupMatch::Primary) => (PermissionClass::Group, false),
+                Some(GroupMatch::Supplementary) => (PermissionClass::SupplementaryGroup, false),
+                None => (PermissionClass::Other, false),
+            }
+        };
+
+        let class_permissions = class_bits(perms, class.shift());
+        let possessor_permissions = if caller.possessor {
+            Some(class_bits(perms, 24))
+        } else {
+            None
+        };
+        let effective = class_permissions | possessor_permissions.unwrap_or_else(Permission::empty);
+
+        Access {
+            class,
+            class_permissions,
+            possessor_permissions,
+            effective,
+            uid: description.uid,
+            gid: description.gid,
+            group_mask_ignored,
+        }
+    }
+
+    /// Whether the caller is granted all of `needed`.
+    ///
+    /// The permissions may be given using any class (e.g., `USER_READ` and `OTHER_READ` both mean
+    /// "read").
+    pub fn allows(&self, needed: Permission) -> bool {
+        self.effective.contains(normalize(needed))
+    }
+
+    /// The permissions from `needed` which the caller is not granted.
+    ///
+    /// The result is normalized into the `OTHER_*` bits.
+    pub fn missing(&self, needed: Permission) -> Permission {
+        normalize(needed) - self.effective
+    }
+}
+
+/// Format normalized permissions as a single `alswrv` class.
+struct ClassDisplay(Permission);
+
+impl fmt::Display for ClassDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let full = format!("{}", self.0);
+        // Normalized permissions live in the final class.
+        write!(f, "{}", &full[full.len() - 6..])
+    }
+}
+
+impl fmt::Display for Access {
+    /// Explains how the effective permissions were determined.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.class {
+            PermissionClass::User => write!(f, "caller is the owner (uid {})", self.uid)?,
+            PermissionClass::Group => write!(f, "caller's group owns the key (gid {})", self.gid)?,
+            PermissionClass::SupplementaryGroup => {
+                write!(
+                    f,
+                    "caller is a member of the owning group (gid {})",
+                    self.gid,
+                )?
+            },
+            PermissionClass::Other if self.group_mask_ignored => {
+                write!(
+                    f,
+                    "caller is in the owning group (gid {}), but the group mask is empty",
+                    self.gid,
+                )?
+            },
+            PermissionClass::Other => write!(f, "caller is neither the owner nor in the group")?,
+        }
+        write!(
+            f,
+            ": the {} mask grants `{}`",
+            self.class.mask_name(),
+            ClassDisplay(self.class_permissions),
+        )?;
+        match self.possessor_permissions {
+            Some(possessor) => write!(f, "; possession grants `{}`", ClassDisplay(possessor))?,
+            None => write!(f, "; the caller does not possess the key")?,
+        }
+        write!(f, "; effective: `{}`", ClassDisplay(self.effective))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Description, Permission};
+
+    use super::{Access, Caller, PermissionClass};
+
+    const OWNER: libc::uid_t = 1000;
+    const GROUP: libc::gid_t = 100;
+
+    fn description(perms: Permission) -> Description {
+        Description {
+            type_: "user".into(),
+            uid: OWNER,
+            gid: GROUP,
+            perms,
+            description: "test".into(),
+        }
+    }
+
+    fn caller(
+        uid: libc::uid_t,
+        gid: libc::gid_t,
+        groups: &[libc::gid_t],
+        possessor: bool,
+    ) -> Caller {
+        Caller {
+            uid,
+            gid,
+            groups: groups.into(),
+            possessor,
+        }
+    }
+
+    #[test]
+    fn test_owner() {
+        let desc =
+            description(Permission::USER_VIEW | Permission::USER_READ | Permission::OTHER_ALL);
+        let access = Access::compute(&desc, &caller(OWNER, 1, &[], false));
+
+        assert_eq!(access.class, PermissionClass::User);
+        assert_eq!(
+            access.effective,
+            Permission::OTHER_VIEW | Permission::OTHER_READ
+        );
+        assert_eq!(access.possessor_permissions, None);
+        assert!(access.allows(Permission::USER_READ));
+        assert!(access.allows(Permission::POSSESSOR_VIEW));
+        // The other mask is not consulted for the owner.
+        assert!(!access.allows(Permission::OTHER_WRITE));
+        assert_eq!(
+            access.missing(Permission::USER_READ | Permission::USER_WRITE),
+            Permission::OTHER_WRITE,
+        );
+    }
+
+    #[test]
+    fn test_possessor() {
+        let access = Access::compute(
+            &description(Permission::PRIVATE),
+            &caller(OWNER, 1, &[], true),
+        );
+
+        assert_eq!(access.class, PermissionClass::User);
+        assert_eq!(access.class_permissions, Permission::OTHER_VIEW);
+        assert_eq!(access.possessor_permissions, Some(Permission::OTHER_ALL));
+        assert_eq!(access.effective, Permission::OTHER_ALL);
+        assert!(access.allows(Permission::USER_ALL));
+    }
+
+    #[test]
+    fn test_group() {
+        let desc = description(Permission::GROUP_SHARED);
+
+        let access = Access::compute(&desc, &caller(2000, GROUP, &[], false));
+        assert_eq!(access.class, PermissionClass::Group);
+        assert!(access.allows(Permission::GROUP_READ));
+        assert!(!access.allows(Permission::GROUP_WRITE));
+
+        let access = Access::compute(&desc, &caller(2000, 1, &[5, GROUP], false));
+        assert_eq!(access.class, PermissionClass::SupplementaryGroup);
+        assert!(access.allows(Permission::GROUP_READ));
+    }
+
+    #[test]
+    fn test_empty_group_mask() {
+        let desc = description(Permission::PRIVATE | Permission::OTHER_VIEW);
+        let access = Access::compute(&desc, &caller(2000, GROUP, &[], false));
+
+        assert_eq!(access.class, PermissionClass::Other);
+        assert_eq!(access.effective, Permission::OTHER_VIEW);
+        assert!(format!("{}", access).contains("the group mask is empty"));
+    }
+
+    #[test]
+    fn test_invalid_group() {
+        let mut desc = description(Permission::GROUP_ALL);
+        desc.gid = !0;
+        let access = Access::compute(&desc, &caller(2000, !0, &[], false));
+
+        assert_eq!(access.class, PermissionClass::Other);
+        assert_eq!(access.effective, Permission::empty());
+    }
+
+    #[test]
+    fn test_other() {
+        let desc = description(Permission::USER_READABLE | Permission::OTHER_SEARCH);
+        let access = Access::compute(&desc, &caller(2000, 1, &[2, 3], false));
+
+        assert_eq!(access.class, PermissionClass::Other);
+        assert_eq!(access.effective, Permission::OTHER_SEARCH);
+        assert!(!access.allows(Permission::OTHER_READ));
+    }
+
+    #[test]
+    fn test_explanation() {
+        let desc = description(Permission::PRIVATE);
+
+        let access = Access::compute(&desc, &caller(OWNER, GROUP, &[], false));
+        assert_eq!(
+            format!("{}", access),
+            "caller is the owner (uid 1000): the user mask grants `-----v`; the caller does not \
+             possess the key; effective: `-----v`",
+        );
+
+        let access = Access::compute(&desc, &caller(2000, 1, &[], true));
+        assert_eq!(
+            format!("{}", access),
+            "caller is neither the owner nor in the group: the other mask grants `------`; \
+             possession grants `alswrv`; effective: `alswrv`",
+        );
+    }
+
+    #[test]
+    fn test_current_caller() {
+        let caller = Caller::current(true).unwrap();
+        assert_eq!(caller.uid, unsafe { libc::geteuid() });
+        assert_eq!(caller.gid, unsafe { libc::getegid() });
+        assert!(caller.possessor);
+    }
+}
@@ -35,9 +35,12 @@ use keyutils_raw::*;
 use log::error;
 use uninit::extension_traits::VecCapacity;
 
-use crate::constants::{KeyctlSupportFlags, Permission, SpecialKeyring};
+use crate::builder::KeyBuilder;
+use crate::constants::{KeyctlSupportFlag, KeyctlSupportFlags, Permission, SpecialKeyring};
 use crate::keytype::*;
 use crate::keytypes;
+use crate::pkey::OptionsError;
+use crate::rotation::{KeyRotation, RotatableKeyType};
 
 /// Reexport of `Errno` as `Error`.
 pub type Error = errno::Errno;
@@ -256,10 +259,10 @@ impl Keyring {
         .map(Self::new_impl)
     }
 
-    /// Return all immediate children of the keyring.
+    /// Return the immediate children of the keyring without inspecting them.
     ///
     /// Requires `read` permission on the keyring.
-    pub fn read(&self) -> Result<(Vec<Key>, Vec<Keyring>)> {
+    pub(crate) fn children(&self) -> Result<Vec<Key>> {
         // The `description` check below hides this error code from the kernel.
         if self.id.get() == 0 {
             return Err(errno::Errno(libc::ENOKEY));
@@ -273,10 +276,10 @@ impl Keyring {
         }
 
         let buffer = read_impl(self.id)?;
-        let keyring_children = {
-            let chunk_size = mem::size_of::<KeyringSerial>();
-            let chunks = buffer.chunks(chunk_size);
-            chunks.map(|chunk| {
+        let chunk_size = mem::size_of::<KeyringSerial>();
+        buffer
+            .chunks(chunk_size)
+            .map(|chunk| {
                 let bytes = chunk.try_into().map_err(|err| {
                     error!(
                         "A keyring did not have the right number of bytes for a child key or \
@@ -292,12 +295,16 @@ impl Keyring {
                 })?;
                 Ok(Key::new_impl(serial))
             })
-        };
+            .collect()
+    }
 
+    /// Return all immediate children of the keyring.
+    ///
+    /// Requires `read` permission on the keyring.
+    pub fn read(&self) -> Result<(Vec<Key>, Vec<Keyring>)> {
         let mut keys = Vec::new();
         let mut keyrings = Vec::new();
-        for key in keyring_children {
-            let key = key?;
+        for key in self.children()? {
             match key.description() {
                 Ok(description) => {
                     if description.type_ == keytypes::Keyring::name() {
@@ -354,6 +361,33 @@ impl Keyring {
         )
     }
 
+    /// Prepare a key of a specific type to be added to the keyring.
+    ///
+    /// The key is created and configured in a private keyring and only linked into this keyring
+    /// once all of the settings on the builder have been applied. Unlike `add_key`, a key with the
+    /// same description which already exists in the keyring is replaced rather than updated. See
+    /// `KeyBuilder`.
+    pub fn build_key<K, D, P>(&mut self, description: D, payload: P) -> KeyBuilder<'_, K, D, P>
+    where
+        K: KeyType,
+        D: Borrow<K::Description>,
+        P: Borrow<K::Payload>,
+    {
+        KeyBuilder::new(self, description, payload)
+    }
+
+    /// Manage versioned generations of a key in the keyring.
+    ///
+    /// Superseded generations remain usable for `grace` after a new generation is added. See
+    /// `KeyRotation`.
+    pub fn rotation<K, D>(&mut self, name: D, grace: Duration) -> KeyRotation<'_, K, D>
+    where
+        K: RotatableKeyType,
+        D: Borrow<K::Description>,
+    {
+        KeyRotation::new(self, name, grace)
+    }
+
     /// Adds a keyring to the current keyring.
     ///
     /// If a keyring with the same description already, the link to the old keyring will be
@@ -539,10 +573,15 @@ impl KeySupportInfo {
             max_dec_size: c_info.max_dec_size,
         }
     }
+
+    /// The operations supported by the key.
+    pub fn supported(&self) -> KeyctlSupportFlag {
+        KeyctlSupportFlag::from_bits_truncate(self.supported_ops)
+    }
 }
 
 /// Encodings supported by the kernel.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 // #[non_exhaustive]
 pub enum KeyctlEncoding {
     /// The RSASSA-PKCS1-v1.5 encoding.
@@ -570,7 +609,7 @@ impl KeyctlEncoding {
 }
 
 /// Hashes supported by the kernel.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 // #[non_exhaustive]
 pub enum KeyctlHash {
     /// The MD4 hash.
@@ -649,7 +688,7 @@ pub struct PublicKeyOptions {
 }
 
 impl PublicKeyOptions {
-    fn info(&self) -> String {
+    pub(crate) fn info(&self) -> String {
         let options = [
             ("enc", self.encoding.as_ref().map(KeyctlEncoding::encoding)),
             ("hash", self.hash.as_ref().map(KeyctlHash::hash)),
@@ -659,6 +698,24 @@ impl PublicKeyOptions {
         .collect::<Vec<_>>();
         options.join(" ").trim().to_owned()
     }
+
+    /// Check that the encoding and hash may be used together.
+    ///
+    /// RSASSA-PKCS1-v1.5 may be used without a hash to sign an already-encoded digest.
+    /// Unrecognized encodings are assumed to be valid with or without a hash.
+    pub fn validate(&self) -> result::Result<(), OptionsError> {
+        match (self.encoding.as_ref(), self.hash.as_ref()) {
+            (None, Some(_)) => Err(OptionsError::HashWithoutEncoding),
+            (Some(encoding @ KeyctlEncoding::RsaesPkcs1V15), Some(_)) => {
+                Err(OptionsError::UnusedHash(encoding.clone()))
+            },
+            (Some(encoding @ KeyctlEncoding::RsassaPss), None)
+            | (Some(encoding @ KeyctlEncoding::RsaesOaep), None) => {
+                Err(OptionsError::MissingHash(encoding.clone()))
+            },
+            _ => Ok(()),
+        }
+    }
 }
 
 impl Key {
@@ -835,7 +892,7 @@ impl Key {
     /// Compute a key from a Diffie-Hellman shared secret.
     ///
     /// The `base` key contains the remote public key to create a share secret which is then
-    /// processed using `hash`.
+    /// processed using `hash`. The `other` information may be built using `dh::OtherInfo`.
     ///
     /// See [SP800-56A][] for details.
     ///
@@ -910,7 +967,7 @@ impl Key {
     pub fn encrypt(&self, options: &PublicKeyOptions, data: &[u8]) -> Result<Vec<u8>> {
         let info = options.info();
         let support = self.pkey_query_support_impl(&info)?;
-        let mut buffer = Vec::with_capacity(support.max_enc_size as usize);
+        let mut buffer = vec![0; support.max_enc_size as usize];
         let write_buffer = buffer.get_backing_buffer();
         let sz = keyctl_pkey_encrypt(self.id, &info, data, write_buffer)?;
         buffer.truncate(sz);
@@ -921,7 +978,7 @@ impl Key {
     pub fn decrypt(&self, options: &PublicKeyOptions, data: &[u8]) -> Result<Vec<u8>> {
         let info = options.info();
         let support = self.pkey_query_support_impl(&info)?;
-        let mut buffer = Vec::with_capacity(support.max_dec_size as usize);
+        let mut buffer = vec![0; support.max_dec_size as usize];
         let write_buffer = buffer.get_backing_buffer();
         let sz = keyctl_pkey_decrypt(self.id, &info, data, write_buffer)?;
         buffer.truncate(sz);
@@ -932,7 +989,7 @@ impl Key {
     pub fn sign(&self, options: &PublicKeyOptions, data: &[u8]) -> Result<Vec<u8>> {
         let info = options.info();
         let support = self.pkey_query_support_impl(&info)?;
-        let mut buffer = Vec::with_capacity(support.max_sig_size as usize);
+        let mut buffer = vec![0; support.max_sig_size as usize];
         let write_buffer = buffer.get_backing_buffer();
         let sz = keyctl_pkey_sign(self.id, &info, data, write_buffer)?;
         buffer.truncate(sz);
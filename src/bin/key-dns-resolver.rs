@@ -0,0 +1,104 @@
+// Copyright (c) 2026, Ben Boeckel
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of this project nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! A `request-key` handler for `dns_resolver` keys
+//!
+//! Configure it in `/etc/request-key.conf` using:
+//!
+//! ```text
+//! create dns_resolver * * /usr/sbin/key-dns-resolver %k
+//! ```
+//!
+//! Lookups are performed using a hosts file (`/etc/hosts` by default; use `--hosts PATH` to
+//! override it). To test a lookup without the kernel, use `-D DESCRIPTION CALLOUT_INFO`.
+
+use std::env;
+use std::error::Error;
+use std::path::PathBuf;
+use std::process;
+
+use keyutils::dns_upcall::{CalloutOptions, Handler, HostsFile};
+use keyutils::keytypes::dns_resolver::{Description, Resolved};
+use keyutils::{Key, KeyringSerial};
+
+const USAGE: &str = "usage: key-dns-resolver [--hosts PATH] <key-id>
+       key-dns-resolver [--hosts PATH] -D <description> <callout-info>";
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let mut hosts = None;
+    let mut debug = false;
+    let mut positional = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "--hosts" => hosts = Some(PathBuf::from(args.next().ok_or(USAGE)?)),
+            "-D" => debug = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            },
+            _ => positional.push(arg),
+        }
+    }
+
+    let resolver = if let Some(hosts) = hosts {
+        HostsFile::open(hosts)?
+    } else {
+        HostsFile::system()?
+    };
+    let handler = Handler::new(resolver);
+
+    if debug {
+        if positional.len() != 2 {
+            return Err(USAGE.into());
+        }
+
+        let desc = Description::parse(&positional[0]).ok_or("invalid description")?;
+        let options = CalloutOptions::parse(&positional[1]);
+        let payload = handler.payload(&desc, &options)?;
+        println!("{:#?}", Resolved::decode(&payload)?);
+    } else {
+        if positional.len() != 1 {
+            return Err(USAGE.into());
+        }
+
+        let id = positional[0].parse().ok().and_then(KeyringSerial::new);
+        let id = id.ok_or("invalid key id")?;
+        // The key ID is given to us by the kernel.
+        let mut key = unsafe { Key::new(id) };
+        handler.handle(&mut key)?;
+    }
+
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("key-dns-resolver: {}", err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,117 @@
+// Copyright (c) 2026, Ben Boeckel
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of this project nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! An ssh-agent using private keys held by the kernel
+//!
+//! Load each RSA private key as an `asymmetric` key described by the comment of its public key:
+//!
+//! ```text
+//! openssl pkcs8 -topk8 -nocrypt -in id_rsa -outform DER | keyctl padd asymmetric "$comment" @u
+//! ```
+//!
+//! Then start the agent with the public keys to serve:
+//!
+//! ```text
+//! key-ssh-agent -a /run/user/$UID/kernel-agent.sock ~/.ssh/id_rsa.pub
+//! ```
+//!
+//! Keys are searched for in the user keyring unless `--keyring ID` is given.
+
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::os::unix::net::UnixListener;
+use std::path::PathBuf;
+use std::process;
+
+use keyutils::ssh_agent::{Agent, PublicKey};
+use keyutils::{Keyring, KeyringSerial, SpecialKeyring};
+
+const USAGE: &str = "usage: key-ssh-agent [--keyring ID] -a <socket> <public-key-file>...";
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let mut keyring = None;
+    let mut socket = None;
+    let mut public_key_files = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "--keyring" => {
+                let id = args.next().ok_or(USAGE)?;
+                let id = id.parse().ok().and_then(KeyringSerial::new);
+                keyring = Some(id.ok_or("invalid keyring id")?);
+            },
+            "-a" => socket = Some(PathBuf::from(args.next().ok_or(USAGE)?)),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            },
+            _ => public_key_files.push(arg),
+        }
+    }
+
+    let socket = socket.ok_or(USAGE)?;
+    if public_key_files.is_empty() {
+        return Err(USAGE.into());
+    }
+
+    let mut public_keys = Vec::new();
+    for path in public_key_files {
+        let contents = fs::read_to_string(&path)?;
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            public_keys.push(PublicKey::parse(line).map_err(|err| format!("{}: {}", path, err))?);
+        }
+    }
+
+    let keyring = if let Some(id) = keyring {
+        // The keyring is checked when it is searched.
+        unsafe { Keyring::new(id) }
+    } else {
+        Keyring::attach_or_create(SpecialKeyring::User)?
+    };
+    let agent = Agent::from_keyring(&keyring, public_keys)?;
+    if agent.identities().is_empty() {
+        return Err("no identities found in the keyring".into());
+    }
+
+    let listener = UnixListener::bind(&socket)?;
+    println!("SSH_AUTH_SOCK={}; export SSH_AUTH_SOCK;", socket.display());
+    agent.listen(&listener)?;
+
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("key-ssh-agent: {}", err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,193 @@
+// Copyright (c) 2026, Ben Boeckel
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of this project nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use std::borrow::Borrow;
+use std::marker::PhantomData;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use log::warn;
+
+use crate::keytype::*;
+use crate::{Key, Keyring, Permission, Result, SpecialKeyring};
+
+/// A private keyring which holds keys while they are being configured.
+///
+/// The keyring is linked into the thread keyring so that keys within it are possessed by the
+/// creating thread. It is invalidated when dropped which also drops its links to staged keys.
+struct StagingKeyring {
+    keyring: Keyring,
+}
+
+impl StagingKeyring {
+    fn new() -> Result<Self> {
+        static STAGING_COUNT: AtomicUsize = AtomicUsize::new(0);
+        let num = STAGING_COUNT.fetch_add(1, Ordering::SeqCst);
+
+        let mut thread_keyring = Keyring::attach_or_create(SpecialKeyring::Thread)?;
+        let keyring =
+            thread_keyring.add_keyring(format!("keyutils:staging:{}:{}", process::id(), num))?;
+
+        Ok(StagingKeyring {
+            keyring,
+        })
+    }
+}
+
+impl Drop for StagingKeyring {
+    fn drop(&mut self) {
+        if let Err(err) = self.keyring.clone().invalidate() {
+            warn!("failed to invalidate the staging keyring: {}", err);
+        }
+    }
+}
+
+/// A builder for keys which are fully configured before they become visible.
+///
+/// Adding a key and then changing its permissions, timeout, or ownership requires separate
+/// syscalls, any of which may fail and leave a half-configured key in the keyring. Instead, the
+/// builder creates the key in a private staging keyring, applies all of the requested settings,
+/// and only then links it into the target keyring. If any step fails, the staged key is
+/// invalidated.
+///
+/// See `Keyring::build_key`.
+#[derive(Debug)]
+pub struct KeyBuilder<'a, K, D, P> {
+    keyring: &'a mut Keyring,
+    description: D,
+    payload: P,
+    permissions: Option<Permission>,
+    timeout: Option<Duration>,
+    uid: Option<libc::uid_t>,
+    gid: Option<libc::gid_t>,
+    _keytype: PhantomData<K>,
+}
+
+impl<'a, K, D, P> KeyBuilder<'a, K, D, P>
+where
+    K: KeyType,
+    D: Borrow<K::Description>,
+    P: Borrow<K::Payload>,
+{
+    pub(crate) fn new(keyring: &'a mut Keyring, description: D, payload: P) -> Self {
+        KeyBuilder {
+            keyring,
+            description,
+            payload,
+            permissions: None,
+            timeout: None,
+            uid: None,
+            gid: None,
+            _keytype: PhantomData,
+        }
+    }
+
+    /// Set the permissions of the key.
+    ///
+    /// If the permissions do not include `POSSESSOR_LINK`, the key is linked into the target
+    /// keyring before the permissions are applied since the link would otherwise be denied.
+    pub fn permissions(mut self, permissions: Permission) -> Self {
+        self.permissions = Some(permissions);
+        self
+    }
+
+    /// Set an expiration timer on the key.
+    ///
+    /// Any partial seconds are ignored. Since a timeout of 0 means "no expiration" to the kernel, a
+    /// timeout of less than a second is rounded up to one second.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout.max(Duration::from_secs(1)));
+        self
+    }
+
+    /// Set the user which owns the key.
+    ///
+    /// Requires the SysAdmin capability to change it to anything other than the current user.
+    pub fn owner(mut self, uid: libc::uid_t) -> Self {
+        self.uid = Some(uid);
+        self
+    }
+
+    /// Set the group which owns the key.
+    ///
+    /// Requires the SysAdmin capability to change it to anything other than a group of which the
+    /// current user is a member.
+    pub fn group(mut self, gid: libc::gid_t) -> Self {
+        self.gid = Some(gid);
+        self
+    }
+
+    /// Create the key and link it into the target keyring.
+    ///
+    /// Requires `write` permission on the target keyring.
+    pub fn create(mut self) -> Result<Key> {
+        let mut staging = StagingKeyring::new()?;
+        let key = staging
+            .keyring
+            .add_key::<K, _, _>(self.description.borrow(), self.payload.borrow())?;
+
+        let mut staged = key.clone();
+        match self.configure(&mut staged) {
+            Ok(()) => Ok(key),
+            Err(err) => {
+                if let Err(inval_err) = staged.invalidate() {
+                    warn!("failed to invalidate a staged key: {}", inval_err);
+                }
+                Err(err)
+            },
+        }
+    }
+
+    fn configure(&mut self, key: &mut Key) -> Result<()> {
+        if let Some(uid) = self.uid {
+            key.chown(uid)?;
+        }
+        if let Some(gid) = self.gid {
+            key.chgrp(gid)?;
+        }
+        if let Some(timeout) = self.timeout {
+            key.set_timeout(timeout)?;
+        }
+
+        match self.permissions {
+            Some(permissions) if permissions.contains(Permission::POSSESSOR_LINK) => {
+                key.set_permissions(permissions)?;
+                self.keyring.link_key(key)
+            },
+            Some(permissions) => {
+                self.keyring.link_key(key)?;
+                let res = key.set_permissions(permissions);
+                if res.is_err() {
+                    // The key is already visible; remove it again before rolling back.
+                    let _ = self.keyring.unlink_key(key);
+                }
+                res
+            },
+            None => self.keyring.link_key(key),
+        }
+    }
+}
@@ -0,0 +1,359 @@
+// Copyright (c) 2026, Ben Boeckel
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of this project nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Keyring-backed secret caching
+//!
+//! Values such as access tokens may be cached in a keyring (e.g., the session or user keyring) so
+//! that they may be shared between processes. Cached values expire after a time-to-live and are
+//! refreshed on demand.
+
+use std::error;
+use std::fmt;
+use std::process;
+use std::result;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use log::warn;
+
+use crate::keytypes::{BigKey, User};
+use crate::{Error, Key, Keyring, Result};
+
+/// The key type used to store cached values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// #[non_exhaustive]
+pub enum Storage {
+    /// Store values in `user` keys.
+    ///
+    /// User keys are limited to 32767 bytes.
+    User,
+    /// Store values in `big_key` keys.
+    ///
+    /// Large values are stored in an encrypted shmem file rather than kernel memory.
+    BigKey,
+}
+
+/// Errors which may occur when fetching a value from the cache.
+#[derive(Debug)]
+pub enum CacheError<E> {
+    /// An error from the keyring.
+    Keyring(Error),
+    /// An error from the refresh function.
+    Refresh(E),
+}
+
+impl<E> From<Error> for CacheError<E> {
+    fn from(err: Error) -> Self {
+        CacheError::Keyring(err)
+    }
+}
+
+impl<E> fmt::Display for CacheError<E>
+where
+    E: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CacheError::Keyring(err) => write!(f, "keyring error: {}", err),
+            CacheError::Refresh(err) => write!(f, "failed to refresh the value: {}", err),
+        }
+    }
+}
+
+impl<E> error::Error for CacheError<E>
+where
+    E: error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            CacheError::Keyring(err) => Some(err),
+            CacheError::Refresh(err) => Some(err),
+        }
+    }
+}
+
+/// The shortest timeout the kernel supports.
+///
+/// Timeouts are in whole seconds and a timeout of zero means "no expiration", so shorter durations
+/// are rounded up to this.
+const MIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Whether an error from the kernel means that the value must be refreshed.
+fn is_stale(err: Error) -> bool {
+    err == errno::Errno(libc::ENOKEY)
+        || err == errno::Errno(libc::EKEYEXPIRED)
+        || err == errno::Errno(libc::EKEYREVOKED)
+}
+
+/// Map a stale value to `None`.
+fn fresh<T>(res: Result<T>) -> Result<Option<T>> {
+    match res {
+        Ok(value) => Ok(Some(value)),
+        Err(err) if is_stale(err) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// A held refresh lock.
+struct RefreshLock {
+    key: Key,
+}
+
+impl RefreshLock {
+    fn release(self) {
+        if let Err(err) = self.key.invalidate() {
+            if !is_stale(err) {
+                warn!("failed to release a cache refresh lock: {}", err);
+            }
+        }
+    }
+}
+
+/// A cache of values stored in a keyring.
+///
+/// Values are stored under the description `<namespace>:<name>` with a timeout of the cache's
+/// time-to-live. When a value is missing, has expired, or has been revoked, a refresh function is
+/// called to compute a new value which is then stored in the keyring.
+///
+/// To avoid every process sharing the cache refreshing a value at the same time, a lock key
+/// (`<namespace>.lock:<name>`) is created by the refreshing process. Other processes wait for the
+/// new value to appear while the lock exists. The lock is advisory: the kernel does not provide an
+/// atomic "create if missing" operation, so two processes which race to create the lock may both
+/// refresh the value. The lock expires on its own if its holder dies.
+#[derive(Debug, Clone)]
+pub struct SecretCache {
+    keyring: Keyring,
+    namespace: String,
+    ttl: Duration,
+    storage: Storage,
+    lock_timeout: Duration,
+    poll_interval: Duration,
+}
+
+impl SecretCache {
+    /// Create a cache which stores values in `keyring` for `ttl`.
+    ///
+    /// A time-to-live of less than a second is rounded up to one second.
+    pub fn new<N>(keyring: Keyring, namespace: N, ttl: Duration) -> Self
+    where
+        N: Into<String>,
+    {
+        SecretCache {
+            keyring,
+            namespace: namespace.into(),
+            ttl: ttl.max(MIN_TIMEOUT),
+            storage: Storage::User,
+            lock_timeout: Duration::from_secs(30),
+            poll_interval: Duration::from_millis(50),
+        }
+    }
+
+    /// Set the key type to store values in.
+    ///
+    /// Defaults to `Storage::User`.
+    pub fn storage(mut self, storage: Storage) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    /// Set how long to wait for another process to refresh a value.
+    ///
+    /// This is also the lifetime of the lock key so that the lock disappears if the refreshing
+    /// process dies. Defaults to 30 seconds; a timeout of less than a second is rounded up to one
+    /// second.
+    pub fn lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = timeout.max(MIN_TIMEOUT);
+        self
+    }
+
+    /// Set how often to check for a new value while another process is refreshing it.
+    ///
+    /// Defaults to 50 milliseconds.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    fn value_description(&self, name: &str) -> String {
+        format!("{}:{}", self.namespace, name)
+    }
+
+    fn lock_description(&self, name: &str) -> String {
+        format!("{}.lock:{}", self.namespace, name)
+    }
+
+    fn find(&self, description: &str) -> Result<Option<Key>> {
+        let res = match self.storage {
+            Storage::User => self.keyring.search_for_key::<User, _, _>(description, None),
+            Storage::BigKey => {
+                self.keyring
+                    .search_for_key::<BigKey, _, _>(description, None)
+            },
+        };
+        fresh(res)
+    }
+
+    fn read(&self, description: &str) -> Result<Option<Vec<u8>>> {
+        if let Some(key) = self.find(description)? {
+            fresh(key.read())
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Get a cached value.
+    ///
+    /// Returns `None` if the value is missing, has expired, or has been revoked.
+    pub fn get(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        self.read(&self.value_description(name))
+    }
+
+    /// Store a value in the cache.
+    ///
+    /// Any existing value is replaced.
+    pub fn insert(&mut self, name: &str, value: &[u8]) -> Result<Key> {
+        let description = self.value_description(name);
+        let ttl = self.ttl;
+        match self.storage {
+            Storage::User => {
+                self.keyring
+                    .build_key::<User, _, _>(description.as_str(), value)
+                    .timeout(ttl)
+                    .create()
+            },
+            Storage::BigKey => {
+                self.keyring
+                    .build_key::<BigKey, _, _>(description.as_str(), value)
+                    .timeout(ttl)
+                    .create()
+            },
+        }
+    }
+
+    /// Remove a value from the cache.
+    pub fn invalidate(&mut self, name: &str) -> Result<()> {
+        match self.find(&self.value_description(name))? {
+            Some(key) => fresh(key.invalidate()).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    fn lock_token() -> Vec<u8> {
+        static LOCK_COUNT: AtomicUsize = AtomicUsize::new(0);
+        let num = LOCK_COUNT.fetch_add(1, Ordering::SeqCst);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        format!("{}:{}:{}", process::id(), num, now.as_nanos()).into_bytes()
+    }
+
+    fn try_lock(&mut self, name: &str) -> Result<Option<RefreshLock>> {
+        let description = self.lock_description(name);
+        if self.read(&description)?.is_some() {
+            return Ok(None);
+        }
+
+        let token = Self::lock_token();
+        let lock_timeout = self.lock_timeout;
+        let key = self
+            .keyring
+            .build_key::<User, _, _>(description.as_str(), token.as_slice())
+            .timeout(lock_timeout)
+            .create()?;
+
+        // Another process may have replaced the lock in the meantime.
+        let holder = fresh(self.keyring.search_for_key::<User, _, _>(description, None))?;
+        if holder.as_ref() == Some(&key) {
+            Ok(Some(RefreshLock {
+                key,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn refresh_and_store<F, E>(
+        &mut self,
+        name: &str,
+        refresh: F,
+    ) -> result::Result<Vec<u8>, CacheError<E>>
+    where
+        F: FnOnce() -> result::Result<Vec<u8>, E>,
+    {
+        let value = refresh().map_err(CacheError::Refresh)?;
+        self.insert(name, &value)?;
+        Ok(value)
+    }
+
+    /// Get a cached value, refreshing it if necessary.
+    ///
+    /// If the value is missing, has expired, or has been revoked, `refresh` is called to compute
+    /// a new value which is stored in the cache. If another process is already refreshing the
+    /// value, this waits for up to the lock timeout for the new value to appear before refreshing
+    /// it anyways.
+    pub fn get_or_refresh<F, E>(
+        &mut self,
+        name: &str,
+        refresh: F,
+    ) -> result::Result<Vec<u8>, CacheError<E>>
+    where
+        F: FnOnce() -> result::Result<Vec<u8>, E>,
+    {
+        if let Some(value) = self.get(name)? {
+            return Ok(value);
+        }
+
+        let deadline = Instant::now() + self.lock_timeout;
+        loop {
+            if let Some(lock) = self.try_lock(name)? {
+                // The previous lock holder may have stored the value just before we took the lock.
+                let res = match self.get(name) {
+                    Ok(Some(value)) => Ok(value),
+                    Ok(None) => self.refresh_and_store(name, refresh),
+                    Err(err) => Err(err.into()),
+                };
+                lock.release();
+                return res;
+            }
+
+            if Instant::now() >= deadline {
+                break;
+            }
+            thread::sleep(self.poll_interval);
+
+            if let Some(value) = self.get(name)? {
+                return Ok(value);
+            }
+        }
+
+        warn!(
+            "timed out waiting for another process to refresh `{}`",
+            self.value_description(name),
+        );
+        self.refresh_and_store(name, refresh)
+    }
+}
@@ -24,6 +24,11 @@
 // (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
 // SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
+use std::error;
+use std::fmt;
+use std::num::ParseIntError;
+use std::str::FromStr;
+
 use bitflags::bitflags;
 use keyutils_raw::*;
 
@@ -146,6 +151,193 @@ bitflags! {
     }
 }
 
+/// The permission classes in the order they appear in the textual representation.
+const PERMISSION_CLASS_SHIFTS: [u32; 4] = [24, 16, 8, 0];
+/// The permission bits within a class in the order they appear in the textual representation.
+const PERMISSION_CLASS_CHARS: [(KeyPermissions, char); 6] = [
+    (0x20, 'a'),
+    (0x10, 'l'),
+    (0x08, 's'),
+    (0x04, 'w'),
+    (0x02, 'r'),
+    (0x01, 'v'),
+];
+/// The length of the textual representation of a set of permissions.
+const PERMISSION_STRING_LEN: usize = PERMISSION_CLASS_SHIFTS.len() * PERMISSION_CLASS_CHARS.len();
+
+impl Permission {
+    /// Possessors have full access and the owning user may view the attributes.
+    ///
+    /// These are the permissions the kernel gives to new `user` keys.
+    pub const PRIVATE: Permission = Permission {
+        bits: KEY_POS_ALL | KEY_USR_VIEW,
+    };
+    /// Possessors have full access and the owning user may find and read the key.
+    pub const USER_READABLE: Permission = Permission {
+        bits: KEY_POS_ALL | KEY_USR_VIEW | KEY_USR_READ | KEY_USR_SEARCH,
+    };
+    /// As `USER_READABLE`, but members of the owning group may also find and read the key.
+    pub const GROUP_SHARED: Permission = Permission {
+        bits: KEY_POS_ALL
+            | KEY_USR_VIEW
+            | KEY_USR_READ
+            | KEY_USR_SEARCH
+            | KEY_GRP_VIEW
+            | KEY_GRP_READ
+            | KEY_GRP_SEARCH,
+    };
+
+    /// Look up a named set of permissions.
+    ///
+    /// The supported names are `private`, `user-readable`, and `group-shared`.
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "private" => Some(Self::PRIVATE),
+            "user-readable" => Some(Self::USER_READABLE),
+            "group-shared" => Some(Self::GROUP_SHARED),
+            _ => None,
+        }
+    }
+
+    fn parse_hex(hex: &str) -> Result<Self, ParsePermissionError> {
+        let bits =
+            KeyPermissions::from_str_radix(hex, 16).map_err(ParsePermissionError::InvalidHex)?;
+        Self::from_bits(bits).ok_or(ParsePermissionError::UnknownBits(bits))
+    }
+
+    fn parse_symbolic(symbolic: &str) -> Result<Self, ParsePermissionError> {
+        let len = symbolic.chars().count();
+        if len != PERMISSION_STRING_LEN {
+            return Err(ParsePermissionError::InvalidLength(len));
+        }
+
+        let expected = PERMISSION_CLASS_SHIFTS.iter().flat_map(|&shift| {
+            PERMISSION_CLASS_CHARS
+                .iter()
+                .map(move |&(bit, c)| (bit << shift, c))
+        });
+        let bits = symbolic.chars().zip(expected).enumerate().try_fold(
+            0,
+            |bits, (index, (found, (bit, c)))| {
+                if found == c {
+                    Ok(bits | bit)
+                } else if found == '-' {
+                    Ok(bits)
+                } else {
+                    Err(ParsePermissionError::InvalidCharacter {
+                        index,
+                        found,
+                    })
+                }
+            },
+        )?;
+
+        Ok(Self::from_bits_truncate(bits))
+    }
+}
+
+impl fmt::Display for Permission {
+    /// Formats the permissions in the symbolic `alswrv` notation used by `keyctl`.
+    ///
+    /// There are four classes of six characters each: possessor, user, group, and other. The
+    /// alternate flag (`{:#}`) instead formats the permissions as the hexadecimal mask accepted
+    /// by `keyctl setperm`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            return write!(f, "{:#010x}", self.bits);
+        }
+
+        for shift in PERMISSION_CLASS_SHIFTS.iter() {
+            for &(bit, c) in PERMISSION_CLASS_CHARS.iter() {
+                let c = if self.bits & (bit << shift) == 0 {
+                    '-'
+                } else {
+                    c
+                };
+                write!(f, "{}", c)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Permission {
+    type Err = ParsePermissionError;
+
+    /// Parses permissions from one of the supported notations.
+    ///
+    ///   - a hexadecimal mask with a `0x` prefix (e.g., `0x3f010000`)
+    ///   - the symbolic notation (e.g., `alswrv-----v------------`)
+    ///   - a preset name (see `Permission::preset`)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with("0x") || s.starts_with("0X") {
+            Self::parse_hex(&s[2..])
+        } else if let Some(preset) = Self::preset(s) {
+            Ok(preset)
+        } else {
+            Self::parse_symbolic(s)
+        }
+    }
+}
+
+/// Errors which may occur when parsing a `Permission` from a string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+// #[non_exhaustive]
+pub enum ParsePermissionError {
+    /// The hexadecimal mask could not be parsed.
+    InvalidHex(ParseIntError),
+    /// The mask contains bits which are not known permissions.
+    UnknownBits(KeyPermissions),
+    /// The symbolic notation has the wrong number of characters.
+    InvalidLength(usize),
+    /// The symbolic notation has an unexpected character.
+    InvalidCharacter {
+        /// The index of the character.
+        index: usize,
+        /// The character which was found.
+        found: char,
+    },
+}
+
+impl fmt::Display for ParsePermissionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParsePermissionError::InvalidHex(err) => {
+                write!(f, "invalid hexadecimal permission mask: {}", err)
+            },
+            ParsePermissionError::UnknownBits(bits) => {
+                write!(f, "unknown permission bits in mask: {:#010x}", bits)
+            },
+            ParsePermissionError::InvalidLength(len) => {
+                write!(
+                    f,
+                    "symbolic permissions must be {} characters long, not {}",
+                    PERMISSION_STRING_LEN, len,
+                )
+            },
+            ParsePermissionError::InvalidCharacter {
+                index,
+                found,
+            } => {
+                write!(
+                    f,
+                    "unexpected character `{}` at index {} of symbolic permissions",
+                    found, index,
+                )
+            },
+        }
+    }
+}
+
+impl error::Error for ParsePermissionError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ParsePermissionError::InvalidHex(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
 /// They kernel type for representing support for optional features.
 ///
 /// Asymmetric keys might only support a limited set of operations. These flags indicate which
@@ -153,11 +345,16 @@ bitflags! {
 pub type KeyctlSupportFlags = u32;
 
 bitflags! {
-    struct KeyctlSupportFlag: KeyctlSupportFlags {
-        const SUPPORTS_ENCRYPT  = 0x01;
-        const SUPPORTS_DECRYPT  = 0x02;
-        const SUPPORTS_SIGN     = 0x04;
-        const SUPPORTS_VERIFY   = 0x08;
+    /// Operations an asymmetric key supports.
+    pub struct KeyctlSupportFlag: KeyctlSupportFlags {
+        /// The key may be used to encrypt data.
+        const SUPPORTS_ENCRYPT  = KEYCTL_SUPPORTS_ENCRYPT;
+        /// The key may be used to decrypt data.
+        const SUPPORTS_DECRYPT  = KEYCTL_SUPPORTS_DECRYPT;
+        /// The key may be used to sign data.
+        const SUPPORTS_SIGN     = KEYCTL_SUPPORTS_SIGN;
+        /// The key may be used to verify signatures.
+        const SUPPORTS_VERIFY   = KEYCTL_SUPPORTS_VERIFY;
     }
 }
 
@@ -234,3 +431,97 @@ fn test_support_flags() {
         KEYCTL_SUPPORTS_VERIFY,
     );
 }
+
+#[test]
+fn test_permission_display() {
+    assert_eq!(
+        format!("{}", Permission::empty()),
+        "------------------------"
+    );
+    assert_eq!(format!("{}", Permission::all()), "alswrvalswrvalswrvalswrv");
+    assert_eq!(
+        format!("{}", Permission::PRIVATE),
+        "alswrv-----v------------",
+    );
+    assert_eq!(
+        format!("{}", Permission::GROUP_SHARED),
+        "alswrv--s-rv--s-rv------",
+    );
+    assert_eq!(
+        format!("{}", Permission::USER_WRITE | Permission::OTHER_LINK),
+        "---------w---------l----",
+    );
+}
+
+#[test]
+fn test_permission_display_hex() {
+    assert_eq!(format!("{:#}", Permission::empty()), "0x00000000");
+    assert_eq!(format!("{:#}", Permission::PRIVATE), "0x3f010000");
+    assert_eq!(format!("{:#}", Permission::OTHER_VIEW), "0x00000001");
+}
+
+#[test]
+fn test_permission_parse_hex() {
+    assert_eq!("0x3f010000".parse(), Ok(Permission::PRIVATE));
+    assert_eq!("0X3F010000".parse(), Ok(Permission::PRIVATE));
+    assert_eq!("0x0".parse(), Ok(Permission::empty()));
+    assert_eq!(
+        "0x40000000".parse::<Permission>(),
+        Err(ParsePermissionError::UnknownBits(0x4000_0000)),
+    );
+    match "0xnope".parse::<Permission>() {
+        Err(ParsePermissionError::InvalidHex(_)) => (),
+        res => panic!("unexpected parse result: {:?}", res),
+    }
+}
+
+#[test]
+fn test_permission_parse_symbolic() {
+    assert_eq!("alswrv-----v------------".parse(), Ok(Permission::PRIVATE));
+    assert_eq!("------------------------".parse(), Ok(Permission::empty()));
+    assert_eq!("alswrvalswrvalswrvalswrv".parse(), Ok(Permission::all()));
+    assert_eq!(
+        "alswrv".parse::<Permission>(),
+        Err(ParsePermissionError::InvalidLength(6)),
+    );
+    assert_eq!(
+        "alswrv-----v-----------x".parse::<Permission>(),
+        Err(ParsePermissionError::InvalidCharacter {
+            index: 23,
+            found: 'x',
+        }),
+    );
+    // Characters must be in their class position.
+    assert_eq!(
+        "valswr-----v------------".parse::<Permission>(),
+        Err(ParsePermissionError::InvalidCharacter {
+            index: 0,
+            found: 'v',
+        }),
+    );
+}
+
+#[test]
+fn test_permission_parse_preset() {
+    assert_eq!("private".parse(), Ok(Permission::PRIVATE));
+    assert_eq!("user-readable".parse(), Ok(Permission::USER_READABLE));
+    assert_eq!("group-shared".parse(), Ok(Permission::GROUP_SHARED));
+    assert_eq!(Permission::preset("public"), None);
+}
+
+#[test]
+fn test_permission_round_trip() {
+    let perms = [
+        Permission::empty(),
+        Permission::all(),
+        Permission::PRIVATE,
+        Permission::USER_READABLE,
+        Permission::GROUP_SHARED,
+        Permission::POSSESSOR_VIEW | Permission::GROUP_SET_ATTRIBUTE | Permission::OTHER_READ,
+    ];
+
+    for perm in perms.iter() {
+        assert_eq!(format!("{}", perm).parse(), Ok(*perm));
+        assert_eq!(format!("{:#}", perm).parse(), Ok(*perm));
+    }
+}
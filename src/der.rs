@@ -0,0 +1,155 @@
+// Copyright (c) 2026, Ben Boeckel
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of this project nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Minimal DER parsing
+//!
+//! Only enough of DER is supported to find the structures the kernel inspects in keys and
+//! certificates.
+
+/// The tag of an ASN.1 `BOOLEAN`.
+pub(crate) const TAG_BOOLEAN: u8 = 0x01;
+/// The tag of an ASN.1 `INTEGER`.
+pub(crate) const TAG_INTEGER: u8 = 0x02;
+/// The tag of an ASN.1 `BIT STRING`.
+pub(crate) const TAG_BIT_STRING: u8 = 0x03;
+/// The tag of an ASN.1 `OCTET STRING`.
+pub(crate) const TAG_OCTET_STRING: u8 = 0x04;
+/// The tag of an ASN.1 `OBJECT IDENTIFIER`.
+pub(crate) const TAG_OID: u8 = 0x06;
+/// The tag of an ASN.1 `SEQUENCE`.
+pub(crate) const TAG_SEQUENCE: u8 = 0x30;
+/// The tag of an ASN.1 `SET`.
+pub(crate) const TAG_SET: u8 = 0x31;
+
+/// The tag of a constructed, context-specific value with the given number.
+pub(crate) const fn tag_context(number: u8) -> u8 {
+    0xa0 | number
+}
+
+/// A DER-encoded value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Tlv<'a> {
+    /// The tag of the value.
+    pub tag: u8,
+    /// The full encoding of the value (including the tag and length).
+    pub raw: &'a [u8],
+    /// The contents of the value.
+    pub contents: &'a [u8],
+}
+
+/// Parse a single DER value from the start of `input`.
+///
+/// Returns the value and the remaining input. Only single-byte tags and definite lengths are
+/// supported.
+pub(crate) fn parse(input: &[u8]) -> Option<(Tlv<'_>, &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    // Multi-byte tags are not used by the structures we need.
+    if tag & 0x1f == 0x1f {
+        return None;
+    }
+
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = if first & 0x80 == 0 {
+        (first as usize, rest)
+    } else {
+        let count = (first & 0x7f) as usize;
+        // Indefinite lengths are not allowed in DER and lengths beyond `usize` are not possible.
+        if count == 0 || count > std::mem::size_of::<usize>() || rest.len() < count {
+            return None;
+        }
+        let (len_bytes, rest) = rest.split_at(count);
+        let len = len_bytes
+            .iter()
+            .fold(0usize, |len, &byte| (len << 8) | byte as usize);
+        (len, rest)
+    };
+
+    if rest.len() < len {
+        return None;
+    }
+    let header_len = input.len() - rest.len();
+    let (contents, rest) = rest.split_at(len);
+
+    Some((
+        Tlv {
+            tag,
+            raw: &input[..header_len + len],
+            contents,
+        },
+        rest,
+    ))
+}
+
+/// Parse a single DER value with the given tag from the start of `input`.
+pub(crate) fn expect(input: &[u8], tag: u8) -> Option<(Tlv<'_>, &[u8])> {
+    parse(input).filter(|(tlv, _)| tlv.tag == tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expect, parse, Tlv, TAG_SEQUENCE};
+
+    #[test]
+    fn test_parse_short() {
+        let input = [0x04, 0x02, 0xaa, 0xbb, 0xcc];
+        let (tlv, rest) = parse(&input).unwrap();
+        assert_eq!(
+            tlv,
+            Tlv {
+                tag: 0x04,
+                raw: &input[..4],
+                contents: &[0xaa, 0xbb],
+            },
+        );
+        assert_eq!(rest, [0xcc]);
+    }
+
+    #[test]
+    fn test_parse_long() {
+        let mut input = vec![0x30, 0x82, 0x01, 0x00];
+        input.resize(260, 0);
+        let (tlv, rest) = expect(&input, TAG_SEQUENCE).unwrap();
+        assert_eq!(tlv.contents.len(), 256);
+        assert_eq!(tlv.raw.len(), 260);
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        // Empty input.
+        assert_eq!(parse(&[]), None);
+        // Missing length.
+        assert_eq!(parse(&[0x30]), None);
+        // Truncated contents.
+        assert_eq!(parse(&[0x30, 0x03, 0x00]), None);
+        // Indefinite length.
+        assert_eq!(parse(&[0x30, 0x80, 0x00, 0x00]), None);
+        // Multi-byte tag.
+        assert_eq!(parse(&[0x1f, 0x81, 0x01, 0x00]), None);
+        // Unexpected tag.
+        assert_eq!(expect(&[0x04, 0x00], TAG_SEQUENCE), None);
+    }
+}
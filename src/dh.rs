@@ -0,0 +1,870 @@
+// Copyright (c) 2026, Ben Boeckel
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of this project nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Diffie-Hellman key exchange using the kernel
+//!
+//! `Key::compute_dh` and `Key::compute_dh_kdf` operate on `user` keys holding big-endian numbers.
+//! This module provides well-known groups which may be loaded into a keyring, private keys with
+//! randomly generated exponents, and typed results for the public value and shared secret. The
+//! `OtherInfo` input to the kernel's KDF may be built with `OtherInfo` and the kernel's output
+//! checked against the userspace `kdf` implementation.
+
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::process;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+use sha1::Sha1;
+use sha2::{Digest, Sha224, Sha256, Sha384, Sha512};
+
+use crate::keytypes::{from_hex, User};
+use crate::{Key, KeyctlHash, Keyring, Result};
+
+/// A finite field Diffie-Hellman group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Group {
+    name: &'static str,
+    bits: usize,
+    prime: &'static str,
+    generator: u8,
+}
+
+/// The 2048-bit MODP group from RFC 3526 (group 14).
+pub const MODP_2048: Group = Group {
+    name: "modp2048",
+    bits: 2048,
+    prime: concat!(
+        "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74",
+        "020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437",
+        "4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED",
+        "EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05",
+        "98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB",
+        "9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B",
+        "E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718",
+        "3995497CEA956AE515D2261898FA051015728E5A8AACAA68FFFFFFFFFFFFFFFF",
+    ),
+    generator: 2,
+};
+
+/// The 3072-bit MODP group from RFC 3526 (group 15).
+pub const MODP_3072: Group = Group {
+    name: "modp3072",
+    bits: 3072,
+    prime: concat!(
+        "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74",
+        "020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437",
+        "4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED",
+        "EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05",
+        "98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB",
+        "9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B",
+        "E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718",
+        "3995497CEA956AE515D2261898FA051015728E5A8AAAC42DAD33170D04507A33",
+        "A85521ABDF1CBA64ECFB850458DBEF0A8AEA71575D060C7DB3970F85A6E1E4C7",
+        "ABF5AE8CDB0933D71E8C94E04A25619DCEE3D2261AD2EE6BF12FFA06D98A0864",
+        "D87602733EC86A64521F2B18177B200CBBE117577A615D6C770988C0BAD946E2",
+        "08E24FA074E5AB3143DB5BFCE0FD108E4B82D120A93AD2CAFFFFFFFFFFFFFFFF",
+    ),
+    generator: 2,
+};
+
+/// The 4096-bit MODP group from RFC 3526 (group 16).
+pub const MODP_4096: Group = Group {
+    name: "modp4096",
+    bits: 4096,
+    prime: concat!(
+        "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74",
+        "020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437",
+        "4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED",
+        "EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05",
+        "98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB",
+        "9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B",
+        "E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718",
+        "3995497CEA956AE515D2261898FA051015728E5A8AAAC42DAD33170D04507A33",
+        "A85521ABDF1CBA64ECFB850458DBEF0A8AEA71575D060C7DB3970F85A6E1E4C7",
+        "ABF5AE8CDB0933D71E8C94E04A25619DCEE3D2261AD2EE6BF12FFA06D98A0864",
+        "D87602733EC86A64521F2B18177B200CBBE117577A615D6C770988C0BAD946E2",
+        "08E24FA074E5AB3143DB5BFCE0FD108E4B82D120A92108011A723C12A787E6D7",
+        "88719A10BDBA5B2699C327186AF4E23C1A946834B6150BDA2583E9CA2AD44CE8",
+        "DBBBC2DB04DE8EF92E8EFC141FBECAA6287C59474E6BC05D99B2964FA090C3A2",
+        "233BA186515BE7ED1F612970CEE2D7AFB81BDD762170481CD0069127D5B05AA9",
+        "93B4EA988D8FDDC186FFB7DC90A6C08F4DF435C934063199FFFFFFFFFFFFFFFF",
+    ),
+    generator: 2,
+};
+
+/// The 6144-bit MODP group from RFC 3526 (group 17).
+pub const MODP_6144: Group = Group {
+    name: "modp6144",
+    bits: 6144,
+    prime: concat!(
+        "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74",
+        "020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437",
+        "4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED",
+        "EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05",
+        "98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB",
+        "9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B",
+        "E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718",
+        "3995497CEA956AE515D2261898FA051015728E5A8AAAC42DAD33170D04507A33",
+        "A85521ABDF1CBA64ECFB850458DBEF0A8AEA71575D060C7DB3970F85A6E1E4C7",
+        "ABF5AE8CDB0933D71E8C94E04A25619DCEE3D2261AD2EE6BF12FFA06D98A0864",
+        "D87602733EC86A64521F2B18177B200CBBE117577A615D6C770988C0BAD946E2",
+        "08E24FA074E5AB3143DB5BFCE0FD108E4B82D120A92108011A723C12A787E6D7",
+        "88719A10BDBA5B2699C327186AF4E23C1A946834B6150BDA2583E9CA2AD44CE8",
+        "DBBBC2DB04DE8EF92E8EFC141FBECAA6287C59474E6BC05D99B2964FA090C3A2",
+        "233BA186515BE7ED1F612970CEE2D7AFB81BDD762170481CD0069127D5B05AA9",
+        "93B4EA988D8FDDC186FFB7DC90A6C08F4DF435C93402849236C3FAB4D27C7026",
+        "C1D4DCB2602646DEC9751E763DBA37BDF8FF9406AD9E530EE5DB382F413001AE",
+        "B06A53ED9027D831179727B0865A8918DA3EDBEBCF9B14ED44CE6CBACED4BB1B",
+        "DB7F1447E6CC254B332051512BD7AF426FB8F401378CD2BF5983CA01C64B92EC",
+        "F032EA15D1721D03F482D7CE6E74FEF6D55E702F46980C82B5A84031900B1C9E",
+        "59E7C97FBEC7E8F323A97A7E36CC88BE0F1D45B7FF585AC54BD407B22B4154AA",
+        "CC8F6D7EBF48E1D814CC5ED20F8037E0A79715EEF29BE32806A1D58BB7C5DA76",
+        "F550AA3D8A1FBFF0EB19CCB1A313D55CDA56C9EC2EF29632387FE8D76E3C0468",
+        "043E8F663F4860EE12BF2D5B0B7474D6E694F91E6DCC4024FFFFFFFFFFFFFFFF",
+    ),
+    generator: 2,
+};
+
+/// The 8192-bit MODP group from RFC 3526 (group 18).
+pub const MODP_8192: Group = Group {
+    name: "modp8192",
+    bits: 8192,
+    prime: concat!(
+        "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74",
+        "020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437",
+        "4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED",
+        "EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05",
+        "98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB",
+        "9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B",
+        "E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718",
+        "3995497CEA956AE515D2261898FA051015728E5A8AAAC42DAD33170D04507A33",
+        "A85521ABDF1CBA64ECFB850458DBEF0A8AEA71575D060C7DB3970F85A6E1E4C7",
+        "ABF5AE8CDB0933D71E8C94E04A25619DCEE3D2261AD2EE6BF12FFA06D98A0864",
+        "D87602733EC86A64521F2B18177B200CBBE117577A615D6C770988C0BAD946E2",
+        "08E24FA074E5AB3143DB5BFCE0FD108E4B82D120A92108011A723C12A787E6D7",
+        "88719A10BDBA5B2699C327186AF4E23C1A946834B6150BDA2583E9CA2AD44CE8",
+        "DBBBC2DB04DE8EF92E8EFC141FBECAA6287C59474E6BC05D99B2964FA090C3A2",
+        "233BA186515BE7ED1F612970CEE2D7AFB81BDD762170481CD0069127D5B05AA9",
+        "93B4EA988D8FDDC186FFB7DC90A6C08F4DF435C93402849236C3FAB4D27C7026",
+        "C1D4DCB2602646DEC9751E763DBA37BDF8FF9406AD9E530EE5DB382F413001AE",
+        "B06A53ED9027D831179727B0865A8918DA3EDBEBCF9B14ED44CE6CBACED4BB1B",
+        "DB7F1447E6CC254B332051512BD7AF426FB8F401378CD2BF5983CA01C64B92EC",
+        "F032EA15D1721D03F482D7CE6E74FEF6D55E702F46980C82B5A84031900B1C9E",
+        "59E7C97FBEC7E8F323A97A7E36CC88BE0F1D45B7FF585AC54BD407B22B4154AA",
+        "CC8F6D7EBF48E1D814CC5ED20F8037E0A79715EEF29BE32806A1D58BB7C5DA76",
+        "F550AA3D8A1FBFF0EB19CCB1A313D55CDA56C9EC2EF29632387FE8D76E3C0468",
+        "043E8F663F4860EE12BF2D5B0B7474D6E694F91E6DBE115974A3926F12FEE5E4",
+        "38777CB6A932DF8CD8BEC4D073B931BA3BC832B68D9DD300741FA7BF8AFC47ED",
+        "2576F6936BA424663AAB639C5AE4F5683423B4742BF1C978238F16CBE39D652D",
+        "E3FDB8BEFC848AD922222E04A4037C0713EB57A81A23F0C73473FC646CEA306B",
+        "4BCBC8862F8385DDFA9D4B7FA2C087E879683303ED5BDD3A062B3CF5B3A278A6",
+        "6D2A13F83F44F82DDF310EE074AB6A364597E899A0255DC164F31CC50846851D",
+        "F9AB48195DED7EA1B1D510BD7EE74D73FAF36BC31ECFA268359046F4EB879F92",
+        "4009438B481C6CD7889A002ED5EE382BC9190DA6FC026E479558E4475677E9AA",
+        "9E3050E2765694DFC81F56E880B96E7160C980DD98EDD3DFFFFFFFFFFFFFFFFF",
+    ),
+    generator: 2,
+};
+
+/// The 2048-bit finite field group from RFC 7919.
+pub const FFDHE_2048: Group = Group {
+    name: "ffdhe2048",
+    bits: 2048,
+    prime: concat!(
+        "FFFFFFFFFFFFFFFFADF85458A2BB4A9AAFDC5620273D3CF1D8B9C583CE2D3695",
+        "A9E13641146433FBCC939DCE249B3EF97D2FE363630C75D8F681B202AEC4617A",
+        "D3DF1ED5D5FD65612433F51F5F066ED0856365553DED1AF3B557135E7F57C935",
+        "984F0C70E0E68B77E2A689DAF3EFE8721DF158A136ADE73530ACCA4F483A797A",
+        "BC0AB182B324FB61D108A94BB2C8E3FBB96ADAB760D7F4681D4F42A3DE394DF4",
+        "AE56EDE76372BB190B07A7C8EE0A6D709E02FCE1CDF7E2ECC03404CD28342F61",
+        "9172FE9CE98583FF8E4F1232EEF28183C3FE3B1B4C6FAD733BB5FCBC2EC22005",
+        "C58EF1837D1683B2C6F34A26C1B2EFFA886B423861285C97FFFFFFFFFFFFFFFF",
+    ),
+    generator: 2,
+};
+
+/// The 3072-bit finite field group from RFC 7919.
+pub const FFDHE_3072: Group = Group {
+    name: "ffdhe3072",
+    bits: 3072,
+    prime: concat!(
+        "FFFFFFFFFFFFFFFFADF85458A2BB4A9AAFDC5620273D3CF1D8B9C583CE2D3695",
+        "A9E13641146433FBCC939DCE249B3EF97D2FE363630C75D8F681B202AEC4617A",
+        "D3DF1ED5D5FD65612433F51F5F066ED0856365553DED1AF3B557135E7F57C935",
+        "984F0C70E0E68B77E2A689DAF3EFE8721DF158A136ADE73530ACCA4F483A797A",
+        "BC0AB182B324FB61D108A94BB2C8E3FBB96ADAB760D7F4681D4F42A3DE394DF4",
+        "AE56EDE76372BB190B07A7C8EE0A6D709E02FCE1CDF7E2ECC03404CD28342F61",
+        "9172FE9CE98583FF8E4F1232EEF28183C3FE3B1B4C6FAD733BB5FCBC2EC22005",
+        "C58EF1837D1683B2C6F34A26C1B2EFFA886B4238611FCFDCDE355B3B6519035B",
+        "BC34F4DEF99C023861B46FC9D6E6C9077AD91D2691F7F7EE598CB0FAC186D91C",
+        "AEFE130985139270B4130C93BC437944F4FD4452E2D74DD364F2E21E71F54BFF",
+        "5CAE82AB9C9DF69EE86D2BC522363A0DABC521979B0DEADA1DBF9A42D5C4484E",
+        "0ABCD06BFA53DDEF3C1B20EE3FD59D7C25E41D2B66C62E37FFFFFFFFFFFFFFFF",
+    ),
+    generator: 2,
+};
+
+/// The 4096-bit finite field group from RFC 7919.
+pub const FFDHE_4096: Group = Group {
+    name: "ffdhe4096",
+    bits: 4096,
+    prime: concat!(
+        "FFFFFFFFFFFFFFFFADF85458A2BB4A9AAFDC5620273D3CF1D8B9C583CE2D3695",
+        "A9E13641146433FBCC939DCE249B3EF97D2FE363630C75D8F681B202AEC4617A",
+        "D3DF1ED5D5FD65612433F51F5F066ED0856365553DED1AF3B557135E7F57C935",
+        "984F0C70E0E68B77E2A689DAF3EFE8721DF158A136ADE73530ACCA4F483A797A",
+        "BC0AB182B324FB61D108A94BB2C8E3FBB96ADAB760D7F4681D4F42A3DE394DF4",
+        "AE56EDE76372BB190B07A7C8EE0A6D709E02FCE1CDF7E2ECC03404CD28342F61",
+        "9172FE9CE98583FF8E4F1232EEF28183C3FE3B1B4C6FAD733BB5FCBC2EC22005",
+        "C58EF1837D1683B2C6F34A26C1B2EFFA886B4238611FCFDCDE355B3B6519035B",
+        "BC34F4DEF99C023861B46FC9D6E6C9077AD91D2691F7F7EE598CB0FAC186D91C",
+        "AEFE130985139270B4130C93BC437944F4FD4452E2D74DD364F2E21E71F54BFF",
+        "5CAE82AB9C9DF69EE86D2BC522363A0DABC521979B0DEADA1DBF9A42D5C4484E",
+        "0ABCD06BFA53DDEF3C1B20EE3FD59D7C25E41D2B669E1EF16E6F52C3164DF4FB",
+        "7930E9E4E58857B6AC7D5F42D69F6D187763CF1D5503400487F55BA57E31CC7A",
+        "7135C886EFB4318AED6A1E012D9E6832A907600A918130C46DC778F971AD0038",
+        "092999A333CB8B7A1A1DB93D7140003C2A4ECEA9F98D0ACC0A8291CDCEC97DCF",
+        "8EC9B55A7F88A46B4DB5A851F44182E1C68A007E5E655F6AFFFFFFFFFFFFFFFF",
+    ),
+    generator: 2,
+};
+
+/// The 6144-bit finite field group from RFC 7919.
+pub const FFDHE_6144: Group = Group {
+    name: "ffdhe6144",
+    bits: 6144,
+    prime: concat!(
+        "FFFFFFFFFFFFFFFFADF85458A2BB4A9AAFDC5620273D3CF1D8B9C583CE2D3695",
+        "A9E13641146433FBCC939DCE249B3EF97D2FE363630C75D8F681B202AEC4617A",
+        "D3DF1ED5D5FD65612433F51F5F066ED0856365553DED1AF3B557135E7F57C935",
+        "984F0C70E0E68B77E2A689DAF3EFE8721DF158A136ADE73530ACCA4F483A797A",
+        "BC0AB182B324FB61D108A94BB2C8E3FBB96ADAB760D7F4681D4F42A3DE394DF4",
+        "AE56EDE76372BB190B07A7C8EE0A6D709E02FCE1CDF7E2ECC03404CD28342F61",
+        "9172FE9CE98583FF8E4F1232EEF28183C3FE3B1B4C6FAD733BB5FCBC2EC22005",
+        "C58EF1837D1683B2C6F34A26C1B2EFFA886B4238611FCFDCDE355B3B6519035B",
+        "BC34F4DEF99C023861B46FC9D6E6C9077AD91D2691F7F7EE598CB0FAC186D91C",
+        "AEFE130985139270B4130C93BC437944F4FD4452E2D74DD364F2E21E71F54BFF",
+        "5CAE82AB9C9DF69EE86D2BC522363A0DABC521979B0DEADA1DBF9A42D5C4484E",
+        "0ABCD06BFA53DDEF3C1B20EE3FD59D7C25E41D2B669E1EF16E6F52C3164DF4FB",
+        "7930E9E4E58857B6AC7D5F42D69F6D187763CF1D5503400487F55BA57E31CC7A",
+        "7135C886EFB4318AED6A1E012D9E6832A907600A918130C46DC778F971AD0038",
+        "092999A333CB8B7A1A1DB93D7140003C2A4ECEA9F98D0ACC0A8291CDCEC97DCF",
+        "8EC9B55A7F88A46B4DB5A851F44182E1C68A007E5E0DD9020BFD64B645036C7A",
+        "4E677D2C38532A3A23BA4442CAF53EA63BB454329B7624C8917BDD64B1C0FD4C",
+        "B38E8C334C701C3ACDAD0657FCCFEC719B1F5C3E4E46041F388147FB4CFDB477",
+        "A52471F7A9A96910B855322EDB6340D8A00EF092350511E30ABEC1FFF9E3A26E",
+        "7FB29F8C183023C3587E38DA0077D9B4763E4E4B94B2BBC194C6651E77CAF992",
+        "EEAAC0232A281BF6B3A739C1226116820AE8DB5847A67CBEF9C9091B462D538C",
+        "D72B03746AE77F5E62292C311562A846505DC82DB854338AE49F5235C95B9117",
+        "8CCF2DD5CACEF403EC9D1810C6272B045B3B71F9DC6B80D63FDD4A8E9ADB1E69",
+        "62A69526D43161C1A41D570D7938DAD4A40E329CD0E40E65FFFFFFFFFFFFFFFF",
+    ),
+    generator: 2,
+};
+
+/// The 8192-bit finite field group from RFC 7919.
+pub const FFDHE_8192: Group = Group {
+    name: "ffdhe8192",
+    bits: 8192,
+    prime: concat!(
+        "FFFFFFFFFFFFFFFFADF85458A2BB4A9AAFDC5620273D3CF1D8B9C583CE2D3695",
+        "A9E13641146433FBCC939DCE249B3EF97D2FE363630C75D8F681B202AEC4617A",
+        "D3DF1ED5D5FD65612433F51F5F066ED0856365553DED1AF3B557135E7F57C935",
+        "984F0C70E0E68B77E2A689DAF3EFE8721DF158A136ADE73530ACCA4F483A797A",
+        "BC0AB182B324FB61D108A94BB2C8E3FBB96ADAB760D7F4681D4F42A3DE394DF4",
+        "AE56EDE76372BB190B07A7C8EE0A6D709E02FCE1CDF7E2ECC03404CD28342F61",
+        "9172FE9CE98583FF8E4F1232EEF28183C3FE3B1B4C6FAD733BB5FCBC2EC22005",
+        "C58EF1837D1683B2C6F34A26C1B2EFFA886B4238611FCFDCDE355B3B6519035B",
+        "BC34F4DEF99C023861B46FC9D6E6C9077AD91D2691F7F7EE598CB0FAC186D91C",
+        "AEFE130985139270B4130C93BC437944F4FD4452E2D74DD364F2E21E71F54BFF",
+        "5CAE82AB9C9DF69EE86D2BC522363A0DABC521979B0DEADA1DBF9A42D5C4484E",
+        "0ABCD06BFA53DDEF3C1B20EE3FD59D7C25E41D2B669E1EF16E6F52C3164DF4FB",
+        "7930E9E4E58857B6AC7D5F42D69F6D187763CF1D5503400487F55BA57E31CC7A",
+        "7135C886EFB4318AED6A1E012D9E6832A907600A918130C46DC778F971AD0038",
+        "092999A333CB8B7A1A1DB93D7140003C2A4ECEA9F98D0ACC0A8291CDCEC97DCF",
+        "8EC9B55A7F88A46B4DB5A851F44182E1C68A007E5E0DD9020BFD64B645036C7A",
+        "4E677D2C38532A3A23BA4442CAF53EA63BB454329B7624C8917BDD64B1C0FD4C",
+        "B38E8C334C701C3ACDAD0657FCCFEC719B1F5C3E4E46041F388147FB4CFDB477",
+        "A52471F7A9A96910B855322EDB6340D8A00EF092350511E30ABEC1FFF9E3A26E",
+        "7FB29F8C183023C3587E38DA0077D9B4763E4E4B94B2BBC194C6651E77CAF992",
+        "EEAAC0232A281BF6B3A739C1226116820AE8DB5847A67CBEF9C9091B462D538C",
+        "D72B03746AE77F5E62292C311562A846505DC82DB854338AE49F5235C95B9117",
+        "8CCF2DD5CACEF403EC9D1810C6272B045B3B71F9DC6B80D63FDD4A8E9ADB1E69",
+        "62A69526D43161C1A41D570D7938DAD4A40E329CCFF46AAA36AD004CF600C838",
+        "1E425A31D951AE64FDB23FCEC9509D43687FEB69EDD1CC5E0B8CC3BDF64B10EF",
+        "86B63142A3AB8829555B2F747C932665CB2C0F1CC01BD70229388839D2AF05E4",
+        "54504AC78B7582822846C0BA35C35F5C59160CC046FD8251541FC68C9C86B022",
+        "BB7099876A460E7451A8A93109703FEE1C217E6C3826E52C51AA691E0E423CFC",
+        "99E9E31650C1217B624816CDAD9A95F9D5B8019488D9C0A0A1FE3075A577E231",
+        "83F81D4A3F2FA4571EFC8CE0BA8A4FE8B6855DFE72B0A66EDED2FBABFBE58A30",
+        "FAFABE1C5D71A87E2F741EF8C1FE86FEA6BBFDE530677F0D97D11D49F7A8443D",
+        "0822E506A9F4614E011E2A94838FF88CD68C8BB7C5C6424CFFFFFFFFFFFFFFFF",
+    ),
+    generator: 2,
+};
+
+/// Compare big-endian unsigned integers.
+fn cmp_be(lhs: &[u8], rhs: &[u8]) -> Ordering {
+    fn strip(value: &[u8]) -> &[u8] {
+        let start = value
+            .iter()
+            .position(|&byte| byte != 0)
+            .unwrap_or(value.len());
+        &value[start..]
+    }
+
+    let lhs = strip(lhs);
+    let rhs = strip(rhs);
+    lhs.len().cmp(&rhs.len()).then_with(|| lhs.cmp(rhs))
+}
+
+impl Group {
+    /// The name of the group.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The size of the prime in bits.
+    pub fn bits(&self) -> usize {
+        self.bits
+    }
+
+    /// The prime of the group as a big-endian number.
+    pub fn prime(&self) -> Vec<u8> {
+        from_hex(self.prime).expect("group primes are valid hex")
+    }
+
+    /// The generator of the group as a big-endian number.
+    pub fn generator(&self) -> Vec<u8> {
+        vec![self.generator]
+    }
+
+    /// Whether `value` is in the range `[2, p - 2]`.
+    fn in_range(&self, value: &[u8]) -> bool {
+        let mut max = self.prime();
+        // The prime is odd, so this cannot underflow.
+        *max.last_mut().expect("group primes are not empty") -= 1;
+
+        cmp_be(value, &[1]) == Ordering::Greater && cmp_be(value, &max) == Ordering::Less
+    }
+
+    /// Add the prime and generator of the group to a keyring.
+    ///
+    /// The keys are `user` keys described as `dh:<name>:prime` and `dh:<name>:base`.
+    pub fn load(&self, keyring: &mut Keyring) -> Result<GroupKeys> {
+        let prime =
+            keyring.add_key::<User, _, _>(format!("dh:{}:prime", self.name), self.prime())?;
+        let base =
+            keyring.add_key::<User, _, _>(format!("dh:{}:base", self.name), self.generator())?;
+
+        Ok(GroupKeys {
+            group: *self,
+            prime,
+            base,
+        })
+    }
+}
+
+/// The keys holding the parameters of a group.
+#[derive(Debug, Clone)]
+pub struct GroupKeys {
+    group: Group,
+    prime: Key,
+    base: Key,
+}
+
+impl GroupKeys {
+    /// The group the keys hold.
+    pub fn group(&self) -> &Group {
+        &self.group
+    }
+
+    /// The key holding the prime.
+    pub fn prime(&self) -> &Key {
+        &self.prime
+    }
+
+    /// The key holding the generator.
+    pub fn base(&self) -> &Key {
+        &self.base
+    }
+}
+
+/// A public value to send to the peer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicValue {
+    value: Vec<u8>,
+}
+
+impl PublicValue {
+    /// A public value received from a peer.
+    ///
+    /// Returns `None` if the value is not in the range `[2, p - 2]` for the group.
+    pub fn from_bytes(group: &Group, value: &[u8]) -> Option<Self> {
+        if group.in_range(value) {
+            Some(PublicValue {
+                value: value.into(),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// The public value as a big-endian number.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.value
+    }
+}
+
+/// A secret computed by the kernel.
+///
+/// The secret is cleared when it is dropped. It may not be cloned or compared so that copies are
+/// not left behind and comparisons do not leak timing information.
+pub struct SharedSecret {
+    secret: Vec<u8>,
+}
+
+impl SharedSecret {
+    /// The bytes of the secret.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.secret
+    }
+}
+
+impl fmt::Debug for SharedSecret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SharedSecret")
+            .field("len", &self.secret.len())
+            .finish()
+    }
+}
+
+impl Drop for SharedSecret {
+    fn drop(&mut self) {
+        for byte in self.secret.iter_mut() {
+            // Use a volatile write so that the clearing is not optimized out.
+            unsafe { ptr::write_volatile(byte, 0) };
+        }
+    }
+}
+
+fn io_errno(err: io::Error) -> errno::Errno {
+    errno::Errno(err.raw_os_error().unwrap_or(libc::EIO))
+}
+
+/// Generate a random exponent in the range `[2, p - 2]`.
+fn random_exponent(group: &Group) -> io::Result<Vec<u8>> {
+    let mut random = File::open("/dev/urandom")?;
+    let mut exponent = vec![0; group.bits / 8];
+    loop {
+        random.read_exact(&mut exponent)?;
+        if group.in_range(&exponent) {
+            return Ok(exponent);
+        }
+    }
+}
+
+/// A Diffie-Hellman private key held by the kernel.
+#[derive(Debug, Clone)]
+pub struct DhPrivateKey {
+    params: GroupKeys,
+    key: Key,
+}
+
+impl DhPrivateKey {
+    /// Generate a random private exponent and store it in a `user` key in the keyring.
+    ///
+    /// The exponent is always stored in a new key. A key with the same description in the keyring
+    /// is replaced rather than updated, so other private keys never have their exponent changed.
+    /// Note that the replaced key is unlinked from the keyring and may be garbage collected if it
+    /// is not linked anywhere else.
+    pub fn generate(params: &GroupKeys, keyring: &mut Keyring, description: &str) -> Result<Self> {
+        let mut exponent = random_exponent(&params.group).map_err(io_errno)?;
+        let key = keyring
+            .build_key::<User, _, _>(description, &exponent[..])
+            .create();
+        for byte in exponent.iter_mut() {
+            unsafe { ptr::write_volatile(byte, 0) };
+        }
+
+        Ok(DhPrivateKey {
+            params: params.clone(),
+            key: key?,
+        })
+    }
+
+    /// The group parameters of the key.
+    pub fn params(&self) -> &GroupKeys {
+        &self.params
+    }
+
+    /// The key holding the private exponent.
+    pub fn key(&self) -> &Key {
+        &self.key
+    }
+
+    /// Compute the public value to send to the peer.
+    pub fn public_value(&self) -> Result<PublicValue> {
+        Key::compute_dh(&self.key, &self.params.prime, &self.params.base).map(|value| {
+            PublicValue {
+                value,
+            }
+        })
+    }
+
+    /// Run `compute` with the peer's public value loaded into a temporary key.
+    fn with_peer<F>(&self, keyring: &mut Keyring, peer: &PublicValue, compute: F) -> Result<Vec<u8>>
+    where
+        F: FnOnce(&Key) -> Result<Vec<u8>>,
+    {
+        static PEER_COUNT: AtomicUsize = AtomicUsize::new(0);
+        let num = PEER_COUNT.fetch_add(1, AtomicOrdering::SeqCst);
+
+        // Use a unique description so that concurrent exchanges do not update each other's keys.
+        let description = format!(
+            "dh:{}:peer:{}:{}",
+            self.params.group.name,
+            process::id(),
+            num,
+        );
+        let peer = keyring.add_key::<User, _, _>(description, peer.as_bytes())?;
+        let res = compute(&peer);
+        // Failing to clean up the public value is not a problem.
+        let _ = peer.invalidate();
+        res
+    }
+
+    /// Compute the shared secret with a peer.
+    ///
+    /// The peer's public value is temporarily added to the keyring as a `user` key described as
+    /// `dh:<group>:peer:<pid>:<n>`.
+    pub fn shared_secret(&self, keyring: &mut Keyring, peer: &PublicValue) -> Result<SharedSecret> {
+        self.with_peer(keyring, peer, |peer| {
+            Key::compute_dh(&self.key, &self.params.prime, peer)
+        })
+        .map(|secret| {
+            SharedSecret {
+                secret,
+            }
+        })
+    }
+
+    /// Derive a key from the shared secret with a peer.
+    ///
+    /// The shared secret is processed by the kernel using the SP800-56A KDF with `hash` and the
+    /// `other` information, usually an encoded `OtherInfo`. The peer's public value is added to
+    /// the keyring as in `shared_secret`.
+    pub fn derive(
+        &self,
+        keyring: &mut Keyring,
+        peer: &PublicValue,
+        hash: KeyctlHash,
+        other: Option<&[u8]>,
+    ) -> Result<SharedSecret> {
+        self.with_peer(keyring, peer, |peer| {
+            Key::compute_dh_kdf(&self.key, &self.params.prime, peer, hash, other)
+        })
+        .map(|secret| {
+            SharedSecret {
+                secret,
+            }
+        })
+    }
+}
+
+/// The `OtherInfo` input to the SP800-56A KDF.
+///
+/// The fields are encoded as `AlgorithmID || PartyUInfo || PartyVInfo {|| SuppPubInfo}
+/// {|| SuppPrivInfo}`. Note that the kernel limits the encoded information to 64 bytes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OtherInfo {
+    algorithm_id: Vec<u8>,
+    party_u_info: Vec<u8>,
+    party_v_info: Vec<u8>,
+    supp_pub_info: Option<Vec<u8>>,
+    supp_priv_info: Option<Vec<u8>>,
+}
+
+impl OtherInfo {
+    /// Information for the algorithm the derived keying material will be used for.
+    pub fn new<A>(algorithm_id: A) -> Self
+    where
+        A: Into<Vec<u8>>,
+    {
+        OtherInfo {
+            algorithm_id: algorithm_id.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Set the public information about the initiator.
+    pub fn party_u_info<I>(mut self, info: I) -> Self
+    where
+        I: Into<Vec<u8>>,
+    {
+        self.party_u_info = info.into();
+        self
+    }
+
+    /// Set the public information about the responder.
+    pub fn party_v_info<I>(mut self, info: I) -> Self
+    where
+        I: Into<Vec<u8>>,
+    {
+        self.party_v_info = info.into();
+        self
+    }
+
+    /// Set additional public information known to both parties.
+    pub fn supp_pub_info<I>(mut self, info: I) -> Self
+    where
+        I: Into<Vec<u8>>,
+    {
+        self.supp_pub_info = Some(info.into());
+        self
+    }
+
+    /// Set additional private information known to both parties.
+    pub fn supp_priv_info<I>(mut self, info: I) -> Self
+    where
+        I: Into<Vec<u8>>,
+    {
+        self.supp_priv_info = Some(info.into());
+        self
+    }
+
+    fn fields(&self) -> Vec<&[u8]> {
+        let mut fields = vec![
+            &self.algorithm_id[..],
+            &self.party_u_info[..],
+            &self.party_v_info[..],
+        ];
+        fields.extend(self.supp_pub_info.iter().map(Vec::as_slice));
+        fields.extend(self.supp_priv_info.iter().map(Vec::as_slice));
+        fields
+    }
+
+    /// Encode the fields as-is.
+    ///
+    /// Each field must have a length agreed upon by both parties.
+    pub fn fixed_length(&self) -> Vec<u8> {
+        self.fields().concat()
+    }
+
+    /// Encode each field prefixed by its length in bytes as a 32-bit big-endian integer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a field is longer than `u32::MAX` bytes.
+    pub fn length_prefixed(&self) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        for field in self.fields() {
+            let len = u32::try_from(field.len()).expect("field lengths fit in 32 bits");
+            encoded.extend_from_slice(&len.to_be_bytes());
+            encoded.extend_from_slice(field);
+        }
+        encoded
+    }
+}
+
+/// Derive keying material from a shared secret in userspace.
+///
+/// This is the SP800-56A single-step KDF as computed by `Key::compute_dh_kdf`: the concatenation
+/// of `H(counter || Z || OtherInfo)` for a 32-bit big-endian counter starting at 1, truncated to
+/// `len` bytes. The kernel pads the shared secret with leading zeros to the size of the prime, so
+/// `secret` must be padded in the same way to reproduce its output.
+///
+/// Returns `None` if `hash` is not one of the SHA-1 or SHA-2 hashes.
+pub fn kdf(hash: &KeyctlHash, secret: &[u8], other_info: &[u8], len: usize) -> Option<Vec<u8>> {
+    fn kdf_with<D: Digest>(secret: &[u8], other_info: &[u8], len: usize) -> Vec<u8> {
+        let mut output = Vec::with_capacity(len);
+        let mut counter: u32 = 1;
+        while output.len() < len {
+            let mut hasher = D::new();
+            hasher.update(counter.to_be_bytes());
+            hasher.update(secret);
+            hasher.update(other_info);
+            output.extend_from_slice(&hasher.finalize());
+            counter += 1;
+        }
+        output.truncate(len);
+        output
+    }
+
+    Some(match *hash {
+        KeyctlHash::Sha1 => kdf_with::<Sha1>(secret, other_info, len),
+        KeyctlHash::Sha224 => kdf_with::<Sha224>(secret, other_info, len),
+        KeyctlHash::Sha256 => kdf_with::<Sha256>(secret, other_info, len),
+        KeyctlHash::Sha384 => kdf_with::<Sha384>(secret, other_info, len),
+        KeyctlHash::Sha512 => kdf_with::<Sha512>(secret, other_info, len),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use super::*;
+
+    const GROUPS: &[Group] = &[
+        MODP_2048, MODP_3072, MODP_4096, MODP_6144, MODP_8192, FFDHE_2048, FFDHE_3072, FFDHE_4096,
+        FFDHE_6144, FFDHE_8192,
+    ];
+
+    #[test]
+    fn test_groups() {
+        for group in GROUPS {
+            let prime = group.prime();
+            assert_eq!(prime.len() * 8, group.bits(), "{}", group.name());
+            // All of the groups have the top and bottom 64 bits set.
+            assert!(prime[..8].iter().all(|&byte| byte == 0xff));
+            assert!(prime[prime.len() - 8..].iter().all(|&byte| byte == 0xff));
+            assert_eq!(group.generator(), [2]);
+        }
+
+        // The first digits of pi and e after the leading ones.
+        assert_eq!(MODP_2048.prime()[8..12], [0xc9, 0x0f, 0xda, 0xa2]);
+        assert_eq!(FFDHE_2048.prime()[8..12], [0xad, 0xf8, 0x54, 0x58]);
+    }
+
+    #[test]
+    fn test_cmp_be() {
+        assert_eq!(cmp_be(&[], &[0]), Ordering::Equal);
+        assert_eq!(cmp_be(&[0, 0, 1], &[1]), Ordering::Equal);
+        assert_eq!(cmp_be(&[1, 0], &[0xff]), Ordering::Greater);
+        assert_eq!(cmp_be(&[0, 0xff], &[1, 0]), Ordering::Less);
+        assert_eq!(cmp_be(&[1, 2], &[1, 3]), Ordering::Less);
+    }
+
+    #[test]
+    fn test_public_value_range() {
+        let group = MODP_2048;
+        let prime = group.prime();
+        let mut max = prime.clone();
+        *max.last_mut().unwrap() -= 1;
+
+        assert_eq!(PublicValue::from_bytes(&group, &[]), None);
+        assert_eq!(PublicValue::from_bytes(&group, &[1]), None);
+        assert_eq!(PublicValue::from_bytes(&group, &[0, 1]), None);
+        assert_eq!(PublicValue::from_bytes(&group, &max), None);
+        assert_eq!(PublicValue::from_bytes(&group, &prime), None);
+        assert_eq!(
+            PublicValue::from_bytes(&group, &[2]).unwrap().as_bytes(),
+            [2],
+        );
+        *max.last_mut().unwrap() -= 1;
+        assert!(PublicValue::from_bytes(&group, &max).is_some());
+    }
+
+    #[test]
+    fn test_random_exponent() {
+        let exponent = random_exponent(&FFDHE_2048).unwrap();
+        assert_eq!(exponent.len(), 256);
+        assert!(FFDHE_2048.in_range(&exponent));
+        assert_ne!(exponent, random_exponent(&FFDHE_2048).unwrap());
+    }
+
+    #[test]
+    fn test_shared_secret_debug() {
+        let secret = SharedSecret {
+            secret: vec![1, 2, 3],
+        };
+        assert_eq!(format!("{:?}", secret), "SharedSecret { len: 3 }");
+    }
+
+    #[test]
+    fn test_other_info() {
+        let info = OtherInfo::new("AES-256")
+            .party_u_info("alice")
+            .party_v_info("bob");
+        assert_eq!(info.fixed_length(), b"AES-256alicebob");
+        assert_eq!(
+            info.length_prefixed(),
+            b"\0\0\0\x07AES-256\0\0\0\x05alice\0\0\0\x03bob",
+        );
+
+        let info = info.supp_pub_info(vec![0, 0, 1, 0]).supp_priv_info("");
+        assert_eq!(info.fixed_length(), b"AES-256alicebob\0\0\x01\0");
+        assert_eq!(
+            info.length_prefixed(),
+            &b"\0\0\0\x07AES-256\0\0\0\x05alice\0\0\0\x03bob\0\0\0\x04\0\0\x01\0\0\0\0\0"[..],
+        );
+
+        assert_eq!(OtherInfo::new("").fixed_length(), b"");
+        assert_eq!(OtherInfo::new("").length_prefixed(), [0; 12]);
+    }
+
+    #[test]
+    fn test_kdf() {
+        let secret = (0..32).collect::<Vec<u8>>();
+        let info = OtherInfo::new("AES-256")
+            .party_u_info("alice")
+            .party_v_info("bob")
+            .length_prefixed();
+        let vectors = [
+            (
+                KeyctlHash::Sha1,
+                "2abd4ff14a6410b29852149590996abc40f229942bbbda99f1cd1a4a2fc170aef835eb8d4fe14aac",
+            ),
+            (
+                KeyctlHash::Sha224,
+                "fd49a240c8872260b4cebdd5ef1520d04b1a37aec318e99412f60c7e79d63c2b53eeb35b91a9cc05",
+            ),
+            (
+                KeyctlHash::Sha256,
+                "161e4298fc4faf8e438b9f2be7acad5a7fa9e1cbe3a0b43716c8e22d4a02fc73d480e72697c4bb95",
+            ),
+            (
+                KeyctlHash::Sha384,
+                "fb7117f148f61f3160e3dcc7d65c0062a93337b25b5ce6fe556658b9103e7e3e64b9b4030a40a05a",
+            ),
+            (
+                KeyctlHash::Sha512,
+                "f0aba04cd6ae77df4d3f466c3bc62421cf9f9f6c652ad44a03ba648a097a2e8c789201dd6e4b065d",
+            ),
+        ];
+
+        for (hash, expected) in vectors.iter() {
+            let expected = from_hex(expected).unwrap();
+            assert_eq!(kdf(hash, &secret, &info, 40).unwrap(), expected, "{:?}", hash);
+            // Shorter outputs are prefixes of longer ones.
+            assert_eq!(
+                kdf(hash, &secret, &info, 7).unwrap(),
+                expected[..7],
+                "{:?}",
+                hash,
+            );
+            assert_eq!(kdf(hash, &secret, &info, 0).unwrap(), []);
+        }
+
+        assert_eq!(kdf(&KeyctlHash::Md5, &secret, &info, 16), None);
+    }
+}
@@ -0,0 +1,665 @@
+// Copyright (c) 2026, Ben Boeckel
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of this project nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! A handler for `dns_resolver` key requests
+//!
+//! The kernel requests `dns_resolver` keys through `request-key`, which traditionally runs the
+//! `key.dns_resolver` program. The `Handler` here performs the same task using a pluggable
+//! `Resolver` and instantiates the key with the payload format the kernel expects.
+
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::IpAddr;
+use std::path::Path;
+use std::result;
+use std::time::Duration;
+
+use log::warn;
+
+use crate::keytypes::dns_resolver::{
+    self, Description, LookupStatus, Protocol, QueryType, RecordSource, Server, ServerList,
+};
+use crate::{Key, KeyManager, Result};
+
+/// Options passed to the upcall in the callout information of the request.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CalloutOptions {
+    /// Return IPv4 addresses.
+    ///
+    /// If only one of `ipv4` and `ipv6` is set, addresses of the other family are ignored.
+    pub ipv4: bool,
+    /// Return IPv6 addresses.
+    pub ipv6: bool,
+    /// Return results in the binary server list format.
+    pub list: bool,
+    /// Report lookup failures through the payload rather than rejecting the key.
+    pub dnserror: bool,
+}
+
+impl CalloutOptions {
+    /// Parse the callout information of a request.
+    ///
+    /// Options may be separated by spaces or commas. Unknown options are ignored.
+    pub fn parse(info: &str) -> Self {
+        let mut options = CalloutOptions::default();
+
+        let words = info
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|word| !word.is_empty());
+        for word in words {
+            match word {
+                "ipv4" => options.ipv4 = true,
+                "ipv6" => options.ipv6 = true,
+                "list" => options.list = true,
+                "dnserror" => options.dnserror = true,
+                // The AFS client asks for server lists using `srv=1`.
+                word if word.starts_with("srv=") => options.list = true,
+                word => warn!("ignoring unknown dns_resolver callout option: {}", word),
+            }
+        }
+
+        options
+    }
+
+    fn accepts(&self, address: &IpAddr) -> bool {
+        match (self.ipv4, self.ipv6) {
+            (true, false) => address.is_ipv4(),
+            (false, true) => address.is_ipv6(),
+            _ => true,
+        }
+    }
+}
+
+/// Errors which may occur when performing a lookup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+// #[non_exhaustive]
+pub enum LookupError {
+    /// The name does not exist or has no records of the requested type.
+    NotFound,
+    /// A temporary failure occurred; the lookup may succeed later.
+    TemporaryFailure,
+    /// The nameserver failed.
+    NameserverFailure,
+    /// A local failure occurred.
+    LocalFailure(String),
+    /// The query type is not supported.
+    UnsupportedQuery(String),
+}
+
+impl LookupError {
+    /// The status to report in a server list.
+    pub fn status(&self) -> LookupStatus {
+        match self {
+            LookupError::NotFound => LookupStatus::NotFound,
+            LookupError::TemporaryFailure => LookupStatus::TemporaryFailure,
+            LookupError::NameserverFailure => LookupStatus::NameserverFailure,
+            LookupError::LocalFailure(_) | LookupError::UnsupportedQuery(_) => {
+                LookupStatus::LocalFailure
+            },
+        }
+    }
+
+    /// The error code to report to the kernel.
+    pub fn errno(&self) -> i32 {
+        match self {
+            LookupError::NotFound => libc::ENODATA,
+            LookupError::TemporaryFailure => libc::EAGAIN,
+            LookupError::NameserverFailure | LookupError::LocalFailure(_) => libc::ECONNREFUSED,
+            LookupError::UnsupportedQuery(_) => libc::EOPNOTSUPP,
+        }
+    }
+}
+
+impl fmt::Display for LookupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LookupError::NotFound => write!(f, "no records found"),
+            LookupError::TemporaryFailure => write!(f, "temporary lookup failure"),
+            LookupError::NameserverFailure => write!(f, "nameserver failure"),
+            LookupError::LocalFailure(msg) => write!(f, "local lookup failure: {}", msg),
+            LookupError::UnsupportedQuery(query) => {
+                write!(f, "unsupported query type: {}", query)
+            },
+        }
+    }
+}
+
+impl error::Error for LookupError {}
+
+/// A service record found by an `AFSDB` or `SRV` lookup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceRecord {
+    /// The name of the server.
+    pub name: String,
+    /// The priority of the server.
+    pub priority: u16,
+    /// The weight of the server.
+    pub weight: u16,
+    /// The port of the service.
+    pub port: u16,
+    /// The protocol of the service.
+    pub protocol: Protocol,
+}
+
+/// A backend which performs lookups for the handler.
+pub trait Resolver {
+    /// Look up the addresses of a host.
+    fn lookup_addresses(&self, name: &str) -> result::Result<Vec<IpAddr>, LookupError>;
+
+    /// Where the addresses found by `lookup_addresses` come from.
+    ///
+    /// By default, addresses are assumed to come from DNS `A` or `AAAA` records.
+    fn address_source(&self) -> RecordSource {
+        RecordSource::DnsA
+    }
+
+    /// Look up service records for a name.
+    ///
+    /// This is called for `AFSDB` and `SRV` queries. By default, no records are found.
+    fn lookup_services(
+        &self,
+        query_type: &QueryType,
+        name: &str,
+    ) -> result::Result<Vec<ServiceRecord>, LookupError> {
+        let _ = (query_type, name);
+        Err(LookupError::NotFound)
+    }
+}
+
+impl<R> Resolver for &R
+where
+    R: Resolver + ?Sized,
+{
+    fn lookup_addresses(&self, name: &str) -> result::Result<Vec<IpAddr>, LookupError> {
+        (*self).lookup_addresses(name)
+    }
+
+    fn address_source(&self) -> RecordSource {
+        (*self).address_source()
+    }
+
+    fn lookup_services(
+        &self,
+        query_type: &QueryType,
+        name: &str,
+    ) -> result::Result<Vec<ServiceRecord>, LookupError> {
+        (*self).lookup_services(query_type, name)
+    }
+}
+
+/// A resolver using a hosts file (as in `/etc/hosts`).
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct HostsFile {
+    entries: Vec<(IpAddr, Vec<String>)>,
+}
+
+impl HostsFile {
+    /// Read the system's hosts file.
+    pub fn system() -> io::Result<Self> {
+        Self::open("/etc/hosts")
+    }
+
+    /// Read a hosts file.
+    pub fn open<P>(path: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        fs::read_to_string(path).map(|contents| Self::parse(&contents))
+    }
+
+    /// Parse the contents of a hosts file.
+    ///
+    /// Lines with invalid addresses are ignored.
+    pub fn parse(contents: &str) -> Self {
+        let entries = contents
+            .lines()
+            .filter_map(|line| {
+                let line = line.split('#').next().unwrap_or("");
+                let mut fields = line.split_whitespace();
+                let address = fields.next()?.parse().ok()?;
+                let names = fields.map(str::to_ascii_lowercase).collect::<Vec<_>>();
+
+                if names.is_empty() {
+                    None
+                } else {
+                    Some((address, names))
+                }
+            })
+            .collect();
+
+        HostsFile {
+            entries,
+        }
+    }
+}
+
+impl Resolver for HostsFile {
+    fn lookup_addresses(&self, name: &str) -> result::Result<Vec<IpAddr>, LookupError> {
+        let name = name.trim_end_matches('.').to_ascii_lowercase();
+        let mut addresses = Vec::new();
+        for (address, names) in &self.entries {
+            if names.contains(&name) && !addresses.contains(address) {
+                addresses.push(*address);
+            }
+        }
+
+        if addresses.is_empty() {
+            Err(LookupError::NotFound)
+        } else {
+            Ok(addresses)
+        }
+    }
+
+    fn address_source(&self) -> RecordSource {
+        RecordSource::Nss
+    }
+}
+
+/// A handler for `dns_resolver` key requests.
+#[derive(Debug, Clone)]
+pub struct Handler<R> {
+    resolver: R,
+    negative_timeout: Duration,
+}
+
+impl<R> Handler<R>
+where
+    R: Resolver,
+{
+    /// Create a handler using the given resolver.
+    pub fn new(resolver: R) -> Self {
+        Handler {
+            resolver,
+            negative_timeout: Duration::from_secs(1),
+        }
+    }
+
+    /// How long failed requests are cached by the kernel.
+    ///
+    /// Defaults to one second.
+    pub fn negative_timeout(mut self, timeout: Duration) -> Self {
+        self.negative_timeout = timeout;
+        self
+    }
+
+    /// Compute the payload for a request.
+    pub fn payload(
+        &self,
+        desc: &Description,
+        options: &CalloutOptions,
+    ) -> result::Result<Vec<u8>, LookupError> {
+        match &desc.query_type {
+            Some(query_type @ QueryType::AFSDB) | Some(query_type @ QueryType::SRV) => {
+                self.server_list(query_type, &desc.name, options)
+                    .map(|list| list.encode())
+            },
+            None | Some(QueryType::A) | Some(QueryType::AAAA) => {
+                let options = match desc.query_type {
+                    Some(QueryType::A) => {
+                        CalloutOptions {
+                            ipv4: true,
+                            ipv6: false,
+                            ..*options
+                        }
+                    },
+                    Some(QueryType::AAAA) => {
+                        CalloutOptions {
+                            ipv4: false,
+                            ipv6: true,
+                            ..*options
+                        }
+                    },
+                    _ => *options,
+                };
+                let addresses = self.addresses(&desc.name, &options)?;
+
+                if options.list {
+                    let source = self.resolver.address_source();
+                    let list = ServerList {
+                        source,
+                        status: LookupStatus::Good,
+                        servers: vec![Server {
+                            name: desc.name.to_string(),
+                            priority: 0,
+                            weight: 0,
+                            port: 0,
+                            protocol: Protocol::Unspecified,
+                            source,
+                            status: LookupStatus::Good,
+                            addresses,
+                        }],
+                    };
+                    Ok(list.encode())
+                } else {
+                    Ok(dns_resolver::encode_addresses(&addresses))
+                }
+            },
+            Some(QueryType::Other(name)) => Err(LookupError::UnsupportedQuery(name.to_string())),
+        }
+    }
+
+    fn addresses(
+        &self,
+        name: &str,
+        options: &CalloutOptions,
+    ) -> result::Result<Vec<IpAddr>, LookupError> {
+        let addresses = self
+            .resolver
+            .lookup_addresses(name)?
+            .into_iter()
+            .filter(|address| options.accepts(address))
+            .collect::<Vec<_>>();
+
+        if addresses.is_empty() {
+            Err(LookupError::NotFound)
+        } else {
+            Ok(addresses)
+        }
+    }
+
+    fn server_list(
+        &self,
+        query_type: &QueryType,
+        name: &str,
+        options: &CalloutOptions,
+    ) -> result::Result<ServerList, LookupError> {
+        let source = if *query_type == QueryType::AFSDB {
+            RecordSource::DnsAfsdb
+        } else {
+            RecordSource::DnsSrv
+        };
+
+        let records = self.resolver.lookup_services(query_type, name)?;
+        if records.is_empty() {
+            return Err(LookupError::NotFound);
+        }
+
+        let servers = records
+            .into_iter()
+            .map(|record| {
+                let (status, addresses) = match self.addresses(&record.name, options) {
+                    Ok(addresses) => (LookupStatus::Good, addresses),
+                    Err(err) => (err.status(), Vec::new()),
+                };
+
+                Server {
+                    name: record.name,
+                    priority: record.priority,
+                    weight: record.weight,
+                    port: record.port,
+                    protocol: record.protocol,
+                    source,
+                    status,
+                    addresses,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let good = servers
+            .iter()
+            .filter(|server| server.status == LookupStatus::Good)
+            .count();
+        let status = if good == servers.len() {
+            LookupStatus::Good
+        } else if good > 0 {
+            LookupStatus::GoodWithBad
+        } else {
+            LookupStatus::Bad
+        };
+
+        Ok(ServerList {
+            source,
+            status,
+            servers,
+        })
+    }
+
+    /// Handle a request for the given key.
+    ///
+    /// This assumes authority over the key, looks up the requested records, and instantiates the
+    /// key. Failed lookups either reject the key or, if the `dnserror` option was given,
+    /// instantiate it with the error.
+    pub fn handle(&self, key: &mut Key) -> Result<()> {
+        let manager = key.manage()?;
+        let desc = key.description()?;
+        let callout = KeyManager::request_key_auth_key(false)?.read()?;
+        let options = CalloutOptions::parse(&String::from_utf8_lossy(&callout));
+
+        let request = if let Some(request) = Description::parse(&desc.description) {
+            request
+        } else {
+            return manager.reject(None, self.negative_timeout, errno::Errno(libc::EINVAL));
+        };
+
+        match self.payload(&request, &options) {
+            Ok(payload) => manager.instantiate(None, payload),
+            Err(err) => {
+                if options.dnserror {
+                    manager.instantiate(None, format!("#dnserror={}", err.errno()))
+                } else {
+                    manager.reject(None, self.negative_timeout, errno::Errno(err.errno()))
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    use crate::keytypes::dns_resolver::{
+        Description, LookupStatus, Protocol, QueryType, ServerList,
+    };
+
+    use super::*;
+
+    const HOSTS: &str = "
+# comment
+127.0.0.1   localhost
+192.0.2.1   vl01.example.org vl01 # trailing comment
+2001:db8::1 VL01.example.org
+192.0.2.2   vl02.example.org
+bogus       ignored.example.org
+192.0.2.3
+";
+
+    fn v4(d: u8) -> IpAddr {
+        IpAddr::V4(Ipv4Addr::new(192, 0, 2, d))
+    }
+
+    fn v6() -> IpAddr {
+        IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1))
+    }
+
+    struct ServiceResolver {
+        hosts: HostsFile,
+    }
+
+    impl Resolver for ServiceResolver {
+        fn lookup_addresses(&self, name: &str) -> result::Result<Vec<IpAddr>, LookupError> {
+            self.hosts.lookup_addresses(name)
+        }
+
+        fn lookup_services(
+            &self,
+            query_type: &QueryType,
+            name: &str,
+        ) -> result::Result<Vec<ServiceRecord>, LookupError> {
+            assert_eq!(*query_type, QueryType::SRV);
+            assert_eq!(name, "_afs3-vlserver._udp.example.org");
+
+            Ok(["vl01.example.org", "vl03.example.org"]
+                .iter()
+                .map(|name| {
+                    ServiceRecord {
+                        name: (*name).into(),
+                        priority: 10,
+                        weight: 5,
+                        port: 7003,
+                        protocol: Protocol::Udp,
+                    }
+                })
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_callout_options() {
+        assert_eq!(CalloutOptions::parse(""), CalloutOptions::default());
+        assert_eq!(
+            CalloutOptions::parse("ipv4 list,dnserror unknown"),
+            CalloutOptions {
+                ipv4: true,
+                ipv6: false,
+                list: true,
+                dnserror: true,
+            },
+        );
+        assert!(CalloutOptions::parse("srv=1").list);
+    }
+
+    #[test]
+    fn test_hosts_file() {
+        let hosts = HostsFile::parse(HOSTS);
+
+        assert_eq!(
+            hosts.lookup_addresses("vl01.example.org").unwrap(),
+            [v4(1), v6()],
+        );
+        assert_eq!(hosts.lookup_addresses("VL01").unwrap(), [v4(1)]);
+        assert_eq!(
+            hosts.lookup_addresses("vl02.example.org.").unwrap(),
+            [v4(2)],
+        );
+        assert_eq!(
+            hosts.lookup_addresses("ignored.example.org").unwrap_err(),
+            LookupError::NotFound,
+        );
+    }
+
+    #[test]
+    fn test_payload_addresses() {
+        let handler = Handler::new(HostsFile::parse(HOSTS));
+        let desc = Description::parse("vl01.example.org").unwrap();
+
+        assert_eq!(
+            handler.payload(&desc, &CalloutOptions::default()).unwrap(),
+            b"192.0.2.1,2001:db8::1",
+        );
+        assert_eq!(
+            handler
+                .payload(&desc, &CalloutOptions::parse("ipv6"))
+                .unwrap(),
+            b"2001:db8::1",
+        );
+
+        let desc = Description::parse("a:vl01.example.org").unwrap();
+        assert_eq!(
+            handler.payload(&desc, &CalloutOptions::default()).unwrap(),
+            b"192.0.2.1",
+        );
+
+        let desc = Description::parse("aaaa:vl02.example.org").unwrap();
+        assert_eq!(
+            handler
+                .payload(&desc, &CalloutOptions::default())
+                .unwrap_err(),
+            LookupError::NotFound,
+        );
+
+        let desc = Description::parse("txt:vl02.example.org").unwrap();
+        assert_eq!(
+            handler
+                .payload(&desc, &CalloutOptions::default())
+                .unwrap_err(),
+            LookupError::UnsupportedQuery("txt".into()),
+        );
+    }
+
+    #[test]
+    fn test_payload_address_list() {
+        let handler = Handler::new(HostsFile::parse(HOSTS));
+        let desc = Description::parse("vl02.example.org").unwrap();
+
+        let payload = handler
+            .payload(&desc, &CalloutOptions::parse("list"))
+            .unwrap();
+        // The source follows the header.
+        assert_eq!(payload[3], 5);
+        let list = ServerList::decode(&payload).unwrap();
+        assert_eq!(list.source, RecordSource::Nss);
+        assert_eq!(list.servers[0].source, RecordSource::Nss);
+        assert_eq!(list.servers.len(), 1);
+        assert_eq!(list.servers[0].name, "vl02.example.org");
+        assert_eq!(list.servers[0].addresses, [v4(2)]);
+    }
+
+    #[test]
+    fn test_payload_services() {
+        let handler = Handler::new(ServiceResolver {
+            hosts: HostsFile::parse(HOSTS),
+        });
+        let desc = Description::parse("srv:_afs3-vlserver._udp.example.org").unwrap();
+
+        let payload = handler
+            .payload(&desc, &CalloutOptions::parse("ipv4"))
+            .unwrap();
+        // The source follows the header.
+        assert_eq!(payload[3], 4);
+        let list = ServerList::decode(&payload).unwrap();
+        assert_eq!(list.source, RecordSource::DnsSrv);
+        assert_eq!(list.status, LookupStatus::GoodWithBad);
+        assert_eq!(list.servers.len(), 2);
+
+        let server = &list.servers[0];
+        assert_eq!(server.name, "vl01.example.org");
+        assert_eq!(server.port, 7003);
+        assert_eq!(server.protocol, Protocol::Udp);
+        assert_eq!(server.status, LookupStatus::Good);
+        assert_eq!(server.addresses, [v4(1)]);
+
+        let server = &list.servers[1];
+        assert_eq!(server.name, "vl03.example.org");
+        assert_eq!(server.status, LookupStatus::NotFound);
+        assert!(server.addresses.is_empty());
+    }
+
+    #[test]
+    fn test_payload_no_services() {
+        let handler = Handler::new(HostsFile::parse(HOSTS));
+        let desc = Description::parse("afsdb:example.org").unwrap();
+
+        assert_eq!(
+            handler
+                .payload(&desc, &CalloutOptions::default())
+                .unwrap_err(),
+            LookupError::NotFound,
+        );
+    }
+}
@@ -26,17 +26,24 @@
 
 //! Asymmetric keys
 
-use std::borrow::Cow;
+use std::borrow::{Borrow, Cow};
+use std::error;
+use std::fmt;
 
 use crate::keytype::*;
+use crate::{der, pem};
 use crate::{Key, Keyring, KeyringSerial};
 
+use super::{from_hex, ByteBuf};
+
 /// Asymmetric keys support encrypting, decrypting, signing, and verifying data.
 ///
 /// Note that when searching for an asymmetric key, the following formats may be used:
 ///
 ///   - `ex:<id>`: an exact match of the key ID
 ///   - `id:<id>`: a partial match of the key ID
+///
+/// See `AsymmetricQuery` to build these descriptions.
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct Asymmetric;
 
@@ -50,7 +57,8 @@ impl KeyType for Asymmetric {
     ///   - `tpm`
     ///   - `x509`
     ///
-    /// The kernel will automatically detect the format.
+    /// The kernel will automatically detect the format. See `Payload` to check the format (and
+    /// convert from PEM) beforehand.
     type Payload = [u8];
 
     fn name() -> &'static str {
@@ -58,6 +66,480 @@ impl KeyType for Asymmetric {
     }
 }
 
+/// The kind of object in an asymmetric key payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// #[non_exhaustive]
+pub enum ObjectType {
+    /// An X.509 certificate.
+    Certificate,
+    /// An unencrypted PKCS#8 private key.
+    PrivateKey,
+    /// A TPM 1.2 wrapped key blob.
+    TpmKey,
+}
+
+impl ObjectType {
+    /// The PEM label used for the object type.
+    pub fn pem_label(self) -> &'static str {
+        match self {
+            ObjectType::Certificate => "CERTIFICATE",
+            ObjectType::PrivateKey => "PRIVATE KEY",
+            ObjectType::TpmKey => "TSS KEY BLOB",
+        }
+    }
+
+    fn from_pem_label(label: &str) -> Option<Self> {
+        match label {
+            "CERTIFICATE" | "X509 CERTIFICATE" => Some(ObjectType::Certificate),
+            "PRIVATE KEY" => Some(ObjectType::PrivateKey),
+            "TSS KEY BLOB" => Some(ObjectType::TpmKey),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ObjectType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ObjectType::Certificate => "X.509 certificate",
+            ObjectType::PrivateKey => "PKCS#8 private key",
+            ObjectType::TpmKey => "TPM key blob",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Errors which may occur when reading an asymmetric key payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+// #[non_exhaustive]
+pub enum PayloadError {
+    /// The input looked like PEM, but could not be decoded.
+    InvalidPem,
+    /// The PEM block has a label which the kernel does not support.
+    UnsupportedPemLabel(String),
+    /// The PEM label does not match the contents of the block.
+    MismatchedPemLabel {
+        /// The label of the PEM block.
+        label: String,
+        /// The type of object found in the block.
+        object_type: ObjectType,
+    },
+    /// The private key is encrypted.
+    ///
+    /// The kernel only accepts unencrypted PKCS#8 private keys. The key must be decrypted (e.g.,
+    /// with `openssl pkcs8 -nocrypt`) before being added.
+    EncryptedPrivateKey,
+    /// The data is not a certificate, private key, or TPM key blob.
+    UnrecognizedObject,
+    /// The certificate could not be parsed.
+    InvalidCertificate,
+}
+
+impl fmt::Display for PayloadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PayloadError::InvalidPem => write!(f, "invalid PEM data"),
+            PayloadError::UnsupportedPemLabel(label) => {
+                write!(f, "unsupported PEM label: {}", label)
+            },
+            PayloadError::MismatchedPemLabel {
+                label,
+                object_type,
+            } => {
+                write!(
+                    f,
+                    "the PEM label {} does not match its contents ({})",
+                    label, object_type,
+                )
+            },
+            PayloadError::EncryptedPrivateKey => {
+                write!(
+                    f,
+                    "encrypted private keys are not supported by the kernel; decrypt it first",
+                )
+            },
+            PayloadError::UnrecognizedObject => {
+                write!(
+                    f,
+                    "not an X.509 certificate, PKCS#8 private key, or TPM key blob",
+                )
+            },
+            PayloadError::InvalidCertificate => write!(f, "invalid X.509 certificate"),
+        }
+    }
+}
+
+impl error::Error for PayloadError {}
+
+/// `commonName` (2.5.4.3)
+const OID_COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+/// `organizationName` (2.5.4.10)
+const OID_ORGANIZATION_NAME: &[u8] = &[0x55, 0x04, 0x0a];
+/// `emailAddress` (1.2.840.113549.1.9.1)
+const OID_EMAIL_ADDRESS: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x01];
+/// `subjectKeyIdentifier` (2.5.29.14)
+const OID_SUBJECT_KEY_IDENTIFIER: &[u8] = &[0x55, 0x1d, 0x0e];
+
+/// The information the kernel extracts from a certificate to identify its key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CertificateInfo {
+    subject: String,
+    serial: Vec<u8>,
+    issuer: Vec<u8>,
+    skid: Option<Vec<u8>>,
+}
+
+impl CertificateInfo {
+    fn parse(tbs: &[u8]) -> Option<Self> {
+        let rest = der::expect(tbs, der::tag_context(0)).map_or(tbs, |(_, rest)| rest);
+        let (serial, rest) = der::expect(rest, der::TAG_INTEGER)?;
+        let (_, rest) = der::expect(rest, der::TAG_SEQUENCE)?;
+        let (issuer, rest) = der::expect(rest, der::TAG_SEQUENCE)?;
+        let (_, rest) = der::expect(rest, der::TAG_SEQUENCE)?;
+        let (subject, rest) = der::expect(rest, der::TAG_SEQUENCE)?;
+        let (_, mut rest) = der::expect(rest, der::TAG_SEQUENCE)?;
+
+        let mut skid = None;
+        while !rest.is_empty() {
+            let (tlv, next) = der::parse(rest)?;
+            if tlv.tag == der::tag_context(3) {
+                skid = Self::find_skid(tlv.contents)?;
+            }
+            rest = next;
+        }
+
+        Some(CertificateInfo {
+            subject: Self::fabricate_name(subject.contents)?,
+            serial: serial.contents.into(),
+            issuer: issuer.contents.into(),
+            skid,
+        })
+    }
+
+    fn find_skid(extensions: &[u8]) -> Option<Option<Vec<u8>>> {
+        let (extensions, _) = der::expect(extensions, der::TAG_SEQUENCE)?;
+        let mut rest = extensions.contents;
+        while !rest.is_empty() {
+            let (extension, next) = der::expect(rest, der::TAG_SEQUENCE)?;
+            rest = next;
+
+            let (oid, fields) = der::expect(extension.contents, der::TAG_OID)?;
+            if oid.contents != OID_SUBJECT_KEY_IDENTIFIER {
+                continue;
+            }
+            // Skip the `critical` flag.
+            let fields = der::expect(fields, der::TAG_BOOLEAN).map_or(fields, |(_, rest)| rest);
+            let (value, _) = der::expect(fields, der::TAG_OCTET_STRING)?;
+            let (skid, _) = der::expect(value.contents, der::TAG_OCTET_STRING)?;
+
+            return Some(Some(skid.contents.into()));
+        }
+
+        Some(None)
+    }
+
+    /// Build the subject name the same way the kernel does.
+    ///
+    /// This is `x509_fabricate_name` from the kernel.
+    fn fabricate_name(name: &[u8]) -> Option<String> {
+        let mut cn: Option<&[u8]> = None;
+        let mut o: Option<&[u8]> = None;
+        let mut email: Option<&[u8]> = None;
+
+        let mut rest = name;
+        while !rest.is_empty() {
+            let (rdn, next) = der::expect(rest, der::TAG_SET)?;
+            rest = next;
+
+            let mut attrs = rdn.contents;
+            while !attrs.is_empty() {
+                let (attr, next) = der::expect(attrs, der::TAG_SEQUENCE)?;
+                attrs = next;
+
+                let (oid, value) = der::expect(attr.contents, der::TAG_OID)?;
+                let (value, _) = der::parse(value)?;
+                // Later attributes replace earlier ones.
+                if oid.contents == OID_COMMON_NAME {
+                    cn = Some(value.contents);
+                } else if oid.contents == OID_ORGANIZATION_NAME {
+                    o = Some(value.contents);
+                } else if oid.contents == OID_EMAIL_ADDRESS {
+                    email = Some(value.contents);
+                }
+            }
+        }
+
+        let name = match (cn, o) {
+            (Some(cn), Some(o)) => {
+                // Only use the common name if it is prefixed by the organization (or a
+                // significant portion of it).
+                if cn.starts_with(o) || (cn.len() >= 7 && o.len() >= 7 && cn[..7] == o[..7]) {
+                    cn.into()
+                } else {
+                    let mut name = o.to_vec();
+                    name.extend_from_slice(b": ");
+                    name.extend_from_slice(cn);
+                    name
+                }
+            },
+            (Some(cn), None) => cn.into(),
+            (None, Some(o)) => o.into(),
+            (None, None) => email.unwrap_or_default().into(),
+        };
+
+        Some(String::from_utf8_lossy(&name).into_owned())
+    }
+}
+
+/// A payload for an asymmetric key.
+///
+/// The payload may be given as PEM or DER. The type of object is detected and checked before it
+/// is handed to the kernel. For certificates, the identifiers the kernel will assign to the key
+/// are available so that it may be found again using `ex:` or `id:` searches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Payload {
+    object_type: ObjectType,
+    der: Vec<u8>,
+    certificate: Option<CertificateInfo>,
+}
+
+impl Payload {
+    /// Read a payload from either PEM or DER data.
+    pub fn parse(data: &[u8]) -> Result<Self, PayloadError> {
+        if pem::is_pem(data) {
+            Self::from_pem(data)
+        } else {
+            Self::from_der(data)
+        }
+    }
+
+    /// Read a payload from a PEM block.
+    ///
+    /// Only the first PEM block in the input is used.
+    pub fn from_pem(data: &[u8]) -> Result<Self, PayloadError> {
+        let pem = pem::parse(data).ok_or(PayloadError::InvalidPem)?;
+        if pem.label == "ENCRYPTED PRIVATE KEY" {
+            return Err(PayloadError::EncryptedPrivateKey);
+        }
+        let expected = ObjectType::from_pem_label(&pem.label)
+            .ok_or_else(|| PayloadError::UnsupportedPemLabel(pem.label.clone()))?;
+
+        let payload = Self::from_der(&pem.contents)?;
+        if payload.object_type != expected {
+            return Err(PayloadError::MismatchedPemLabel {
+                label: pem.label,
+                object_type: payload.object_type,
+            });
+        }
+
+        Ok(payload)
+    }
+
+    /// Read a payload from DER data.
+    pub fn from_der(data: &[u8]) -> Result<Self, PayloadError> {
+        let (outer, rest) =
+            der::expect(data, der::TAG_SEQUENCE).ok_or(PayloadError::UnrecognizedObject)?;
+        if !rest.is_empty() {
+            return Err(PayloadError::UnrecognizedObject);
+        }
+
+        let tags = Self::element_tags(outer.contents).ok_or(PayloadError::UnrecognizedObject)?;
+        let object_type = if tags == [der::TAG_SEQUENCE, der::TAG_SEQUENCE, der::TAG_BIT_STRING] {
+            // Certificate ::= SEQUENCE { tbsCertificate, signatureAlgorithm, signatureValue }
+            ObjectType::Certificate
+        } else if tags.len() >= 3
+            && tags[..3] == [der::TAG_INTEGER, der::TAG_SEQUENCE, der::TAG_OCTET_STRING]
+        {
+            // OneAsymmetricKey ::= SEQUENCE { version, privateKeyAlgorithm, privateKey, ... }
+            ObjectType::PrivateKey
+        } else if tags == [der::TAG_SEQUENCE, der::TAG_OCTET_STRING] {
+            // EncryptedPrivateKeyInfo ::= SEQUENCE { encryptionAlgorithm, encryptedData }
+            return Err(PayloadError::EncryptedPrivateKey);
+        } else if tags == [der::TAG_OCTET_STRING] {
+            // TPMKey ::= SEQUENCE { blob }
+            ObjectType::TpmKey
+        } else {
+            return Err(PayloadError::UnrecognizedObject);
+        };
+
+        let certificate = if object_type == ObjectType::Certificate {
+            let (tbs, _) = der::expect(outer.contents, der::TAG_SEQUENCE)
+                .ok_or(PayloadError::InvalidCertificate)?;
+            Some(CertificateInfo::parse(tbs.contents).ok_or(PayloadError::InvalidCertificate)?)
+        } else {
+            None
+        };
+
+        Ok(Payload {
+            object_type,
+            der: data.into(),
+            certificate,
+        })
+    }
+
+    fn element_tags(mut contents: &[u8]) -> Option<Vec<u8>> {
+        let mut tags = Vec::new();
+        while !contents.is_empty() {
+            let (tlv, rest) = der::parse(contents)?;
+            tags.push(tlv.tag);
+            contents = rest;
+        }
+        Some(tags)
+    }
+
+    /// The type of object in the payload.
+    pub fn object_type(&self) -> ObjectType {
+        self.object_type
+    }
+
+    /// The DER encoding of the payload.
+    pub fn der(&self) -> &[u8] {
+        &self.der
+    }
+
+    /// The subject key identifier of a certificate, if present.
+    pub fn subject_key_identifier(&self) -> Option<&[u8]> {
+        self.certificate
+            .as_ref()
+            .and_then(|cert| cert.skid.as_ref())
+            .map(Vec::as_slice)
+    }
+
+    /// The identifiers the kernel will assign to the key.
+    ///
+    /// These are hex-encoded and may be searched for using `ex:<id>` (or a suffix of it using
+    /// `id:<id>`). Certificates are identified by their serial number and issuer and, if present,
+    /// their subject key identifier. The kernel does not assign identifiers to private keys or
+    /// TPM key blobs.
+    pub fn key_ids(&self) -> Vec<String> {
+        self.certificate
+            .as_ref()
+            .map(|cert| {
+                let mut ids = vec![format!(
+                    "{:x}{:x}",
+                    ByteBuf(&cert.serial),
+                    ByteBuf(&cert.issuer),
+                )];
+                if let Some(skid) = cert.skid.as_ref() {
+                    ids.push(format!("{:x}", ByteBuf(skid)));
+                }
+                ids
+            })
+            .unwrap_or_default()
+    }
+
+    /// The fingerprint the kernel will use to describe the key.
+    ///
+    /// This is only available for certificates.
+    pub fn fingerprint(&self) -> Option<KeyFingerprint> {
+        self.certificate.as_ref().map(|cert| {
+            KeyFingerprint {
+                subject: cert.subject.clone(),
+                id: cert.skid.as_ref().unwrap_or(&cert.serial).clone(),
+            }
+        })
+    }
+
+    /// The description the kernel will use if the key is added with an empty description.
+    ///
+    /// This is only available for certificates. It is the subject name followed by the subject
+    /// key identifier (or the serial number if there is none).
+    pub fn description(&self) -> Option<String> {
+        self.fingerprint()
+            .map(|fingerprint| fingerprint.to_string())
+    }
+}
+
+impl Borrow<[u8]> for Payload {
+    fn borrow(&self) -> &[u8] {
+        &self.der
+    }
+}
+
+/// The description the kernel generates for a certificate added without a description.
+///
+/// It has the form `<subject>: <id>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyFingerprint {
+    /// The subject name of the certificate.
+    pub subject: String,
+    /// The subject key identifier of the certificate (or its serial number if it has none).
+    pub id: Vec<u8>,
+}
+
+impl KeyFingerprint {
+    /// Parse a description generated by the kernel.
+    ///
+    /// This is meant for descriptions returned from `Key::description`. Descriptions given when
+    /// the key was added are only recognized if they happen to have the same form.
+    pub fn parse(description: &str) -> Option<Self> {
+        // The subject itself may contain `: ` (e.g., `<organization>: <common name>`).
+        let idx = description.rfind(": ")?;
+        let id = from_hex(&description[idx + 2..])?;
+        if id.is_empty() {
+            return None;
+        }
+
+        Some(KeyFingerprint {
+            subject: description[..idx].into(),
+            id,
+        })
+    }
+}
+
+impl fmt::Display for KeyFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {:x}", self.subject, ByteBuf(&self.id))
+    }
+}
+
+/// A description to search for asymmetric keys with.
+///
+/// Queries may be passed to `Keyring::search_for_key::<Asymmetric, _, _>` by formatting them with
+/// `to_string`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+// #[non_exhaustive]
+pub enum AsymmetricQuery {
+    /// Match a key ID exactly (`ex:<id>`).
+    Exact(Vec<u8>),
+    /// Match the end of a key ID (`id:<id>`).
+    Partial(Vec<u8>),
+    /// Match the description of the key exactly.
+    ///
+    /// Keys added without a description are described by their certificate subject and
+    /// fingerprint; see `Fingerprint` for those. The description must not start with one of the
+    /// prefixes used by the other queries.
+    Subject(String),
+    /// Match the description the kernel generated for a certificate.
+    Fingerprint(KeyFingerprint),
+}
+
+impl From<KeyFingerprint> for AsymmetricQuery {
+    fn from(fingerprint: KeyFingerprint) -> Self {
+        AsymmetricQuery::Fingerprint(fingerprint)
+    }
+}
+
+impl fmt::Display for AsymmetricQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsymmetricQuery::Exact(id) => write!(f, "ex:{:x}", ByteBuf(id)),
+            AsymmetricQuery::Partial(id) => write!(f, "id:{:x}", ByteBuf(id)),
+            AsymmetricQuery::Subject(subject) => write!(f, "{}", subject),
+            AsymmetricQuery::Fingerprint(fingerprint) => write!(f, "{}", fingerprint),
+        }
+    }
+}
+
+impl KeyDescription for AsymmetricQuery {
+    fn description(&self) -> Cow<str> {
+        match self {
+            AsymmetricQuery::Subject(subject) => Cow::Borrowed(subject),
+            _ => Cow::Owned(self.to_string()),
+        }
+    }
+}
+
 /// A restriction that may be placed onto a keyring using an asymmetric key.
 #[derive(Debug, Clone, PartialEq, Eq)]
 // #[non_exhaustive]
@@ -117,3 +599,313 @@ impl KeyRestriction for AsymmetricRestriction {
 impl RestrictableKeyType for Asymmetric {
     type Restriction = AsymmetricRestriction;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A self-signed certificate with `CN=t` and a subject key identifier.
+    const CERTIFICATE_PEM: &str = "\
+-----BEGIN CERTIFICATE-----\n\
+MIIC+TCCAeGgAwIBAgIURv+uJjAHa74f5HomO1owbVb5RzUwDQYJKoZIhvcNAQEL\n\
+BQAwDDEKMAgGA1UEAwwBdDAeFw0yNjEwMTgxNjE0MTZaFw0yNjEwMTkxNjE0MTZa\n\
+MAwxCjAIBgNVBAMMAXQwggEiMA0GCSqGSIb3DQEBAQUAA4IBDwAwggEKAoIBAQC5\n\
+122t9ycSTT4jjfXQpdcStyJPtLzLQZM9q+NTZvm/RGWFpck+/Ibcz6fSozAva/C5\n\
+uA79/Y4P1GFWRmajBIhuZ9I89wXSzl4iXyRHYFjfR4ix8LTNi/eWehAs1s0xv8SO\n\
+8TOfZVkxYc+7lCYjvIc1NyX+k1KtQy9KBnKXBHS5hzRn+BPno3TUNVXTgQ1Kwk04\n\
+ypoCI6IZWP4fcdqAud0velY1jLpn1R7/8yObA0D+OEmxGpFOYiCsqyHBSBMHimGt\n\
+CbzndcZeC47mqkfr3HSBPjvB4louRxaUmat1YmvxE6/7MvYrPEDcVZBJWBdbNk1e\n\
+OPe2QrNuPowwt6T3q7uLAgMBAAGjUzBRMB0GA1UdDgQWBBSN0n585Uxumi6ChDH4\n\
+1i+2UTC3RzAfBgNVHSMEGDAWgBSN0n585Uxumi6ChDH41i+2UTC3RzAPBgNVHRMB\n\
+Af8EBTADAQH/MA0GCSqGSIb3DQEBCwUAA4IBAQAm2HxEfuv76nC2fw1N8N/AeB1X\n\
+YiNi2XrG/I33Q7lNzFcREkWSztcyHRC1aWjObbwd474Zxi+xkvn2hJT1rAiANGsG\n\
+NHyXLPSCnKV7rltrWUM+JdkIY8tI6o2IYqLXOogllUGOfRMkFmfA4GOQDUI+7T2U\n\
+p3jUxVQ5Y9MuSyKc98hwQe2SFRPHiApylJwBOqsmkiYaird0KRhf73T3ZorVAL6r\n\
+O3JosXVFNwUcDEfvsB0GUC/OyqXiyoALqPCg2qsLKbNqofDykwGOwtTzIm9mhEQ1\n\
+g6XxZa5Wy8gSOuY1lekUgRT7bVWHcK0FGgXBMAvzuewl3h5CIr3qBCIaJNK1\n\
+-----END CERTIFICATE-----\n";
+
+    // A minimal certificate with serial `0x0123`, issuer `CN=ca`, subject `O=Example, CN=signer`,
+    // and no extensions.
+    const CERTIFICATE_DER: &[u8] = &[
+        0x30, 0x45, // Certificate
+        0x30, 0x3e, // tbsCertificate
+        0x02, 0x02, 0x01, 0x23, // serialNumber
+        0x30, 0x00, // signature
+        0x30, 0x0d, 0x31, 0x0b, 0x30, 0x09, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x02, 0x63,
+        0x61, // issuer
+        0x30, 0x00, // validity
+        0x30, 0x23, 0x31, 0x10, 0x30, 0x0e, 0x06, 0x03, 0x55, 0x04, 0x0a, 0x0c, 0x07, 0x45, 0x78,
+        0x61, 0x6d, 0x70, 0x6c, 0x65, 0x31, 0x0f, 0x30, 0x0d, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0c,
+        0x06, 0x73, 0x69, 0x67, 0x6e, 0x65, 0x72, // subject
+        0x30, 0x00, // subjectPublicKeyInfo
+        0x30, 0x00, // signatureAlgorithm
+        0x03, 0x01, 0x00, // signatureValue
+    ];
+
+    const PRIVATE_KEY_DER: &[u8] = &[
+        0x30, 0x09, // OneAsymmetricKey
+        0x02, 0x01, 0x00, // version
+        0x30, 0x00, // privateKeyAlgorithm
+        0x04, 0x02, 0xaa, 0xbb, // privateKey
+    ];
+
+    const ENCRYPTED_PRIVATE_KEY_DER: &[u8] = &[
+        0x30, 0x06, // EncryptedPrivateKeyInfo
+        0x30, 0x00, // encryptionAlgorithm
+        0x04, 0x02, 0xaa, 0xbb, // encryptedData
+    ];
+
+    const TPM_KEY_DER: &[u8] = &[
+        0x30, 0x04, // TPMKey
+        0x04, 0x02, 0xaa, 0xbb, // blob
+    ];
+
+
+    fn name(attrs: &[(&[u8], &str)]) -> Vec<u8> {
+        attrs
+            .iter()
+            .flat_map(|(oid, value)| {
+                let mut attr = vec![0x06, oid.len() as u8];
+                attr.extend_from_slice(oid);
+                attr.extend_from_slice(&[0x0c, value.len() as u8]);
+                attr.extend_from_slice(value.as_bytes());
+
+                let mut rdn = vec![0x31, attr.len() as u8 + 2, 0x30, attr.len() as u8];
+                rdn.extend(attr);
+                rdn
+            })
+            .collect()
+    }
+
+    fn fingerprint(subject: &str, id: &[u8]) -> KeyFingerprint {
+        KeyFingerprint {
+            subject: subject.into(),
+            id: id.into(),
+        }
+    }
+
+    fn fabricate_name(attrs: &[(&[u8], &str)]) -> String {
+        CertificateInfo::fabricate_name(&name(attrs)).unwrap()
+    }
+
+    #[test]
+    fn test_certificate_pem() {
+        let payload = Payload::parse(CERTIFICATE_PEM.as_bytes()).unwrap();
+        assert_eq!(payload.object_type(), ObjectType::Certificate);
+        assert_eq!(payload.der().len(), 765);
+
+        let skid = [
+            0x8d, 0xd2, 0x7e, 0x7c, 0xe5, 0x4c, 0x6e, 0x9a, 0x2e, 0x82, 0x84, 0x31, 0xf8, 0xd6,
+            0x2f, 0xb6, 0x51, 0x30, 0xb7, 0x47,
+        ];
+        assert_eq!(payload.subject_key_identifier(), Some(&skid[..]));
+        assert_eq!(
+            payload.key_ids(),
+            [
+                "46ffae2630076bbe1fe47a263b5a306d56f94735310a300806035504030c0174",
+                "8dd27e7ce54c6e9a2e828431f8d62fb65130b747",
+            ],
+        );
+        assert_eq!(
+            payload.description().unwrap(),
+            "t: 8dd27e7ce54c6e9a2e828431f8d62fb65130b747",
+        );
+
+        let der = Payload::parse(payload.der()).unwrap();
+        assert_eq!(der, payload);
+    }
+
+    #[test]
+    fn test_certificate_without_skid() {
+        let payload = Payload::from_der(CERTIFICATE_DER).unwrap();
+        assert_eq!(payload.object_type(), ObjectType::Certificate);
+        assert_eq!(payload.subject_key_identifier(), None);
+        assert_eq!(payload.key_ids(), ["0123310b300906035504030c026361"]);
+        assert_eq!(payload.description().unwrap(), "Example: signer: 0123");
+    }
+
+    #[test]
+    fn test_private_key() {
+        let payload = Payload::parse(PRIVATE_KEY_DER).unwrap();
+        assert_eq!(payload.object_type(), ObjectType::PrivateKey);
+        assert!(payload.key_ids().is_empty());
+        assert_eq!(payload.description(), None);
+
+        let pem = pem::encode("PRIVATE KEY", PRIVATE_KEY_DER);
+        assert_eq!(Payload::parse(pem.as_bytes()).unwrap(), payload);
+    }
+
+    #[test]
+    fn test_tpm_key() {
+        let payload = Payload::parse(TPM_KEY_DER).unwrap();
+        assert_eq!(payload.object_type(), ObjectType::TpmKey);
+        assert!(payload.key_ids().is_empty());
+
+        let pem = pem::encode("TSS KEY BLOB", TPM_KEY_DER);
+        assert_eq!(Payload::parse(pem.as_bytes()).unwrap(), payload);
+    }
+
+    #[test]
+    fn test_encrypted_private_key() {
+        assert_eq!(
+            Payload::parse(ENCRYPTED_PRIVATE_KEY_DER),
+            Err(PayloadError::EncryptedPrivateKey),
+        );
+
+        let pem = pem::encode("ENCRYPTED PRIVATE KEY", ENCRYPTED_PRIVATE_KEY_DER);
+        assert_eq!(
+            Payload::parse(pem.as_bytes()),
+            Err(PayloadError::EncryptedPrivateKey),
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(
+            Payload::parse(b"-----BEGIN CERTIFICATE-----\n"),
+            Err(PayloadError::InvalidPem),
+        );
+        assert_eq!(
+            Payload::parse(pem::encode("RSA PRIVATE KEY", PRIVATE_KEY_DER).as_bytes()),
+            Err(PayloadError::UnsupportedPemLabel("RSA PRIVATE KEY".into())),
+        );
+        assert_eq!(
+            Payload::parse(pem::encode("CERTIFICATE", PRIVATE_KEY_DER).as_bytes()),
+            Err(PayloadError::MismatchedPemLabel {
+                label: "CERTIFICATE".into(),
+                object_type: ObjectType::PrivateKey,
+            }),
+        );
+        assert_eq!(Payload::parse(&[]), Err(PayloadError::UnrecognizedObject));
+        assert_eq!(
+            Payload::parse(&[0x30, 0x00]),
+            Err(PayloadError::UnrecognizedObject),
+        );
+        // Trailing data.
+        assert_eq!(
+            Payload::parse(&[0x30, 0x02, 0x04, 0x00, 0x00]),
+            Err(PayloadError::UnrecognizedObject),
+        );
+        // A certificate without a subject.
+        assert_eq!(
+            Payload::parse(&[
+                0x30, 0x0b, 0x30, 0x04, 0x02, 0x02, 0x01, 0x23, 0x30, 0x00, 0x03, 0x01, 0x00
+            ]),
+            Err(PayloadError::InvalidCertificate),
+        );
+    }
+
+    #[test]
+    fn test_fabricate_name() {
+        assert_eq!(fabricate_name(&[]), "");
+        assert_eq!(fabricate_name(&[(OID_COMMON_NAME, "cn")]), "cn");
+        assert_eq!(fabricate_name(&[(OID_ORGANIZATION_NAME, "org")]), "org");
+        assert_eq!(
+            fabricate_name(&[(OID_EMAIL_ADDRESS, "a@example.com")]),
+            "a@example.com",
+        );
+        assert_eq!(
+            fabricate_name(&[
+                (OID_EMAIL_ADDRESS, "a@example.com"),
+                (OID_COMMON_NAME, "cn"),
+            ]),
+            "cn",
+        );
+        // The last attribute of each type is used.
+        assert_eq!(
+            fabricate_name(&[(OID_COMMON_NAME, "first"), (OID_COMMON_NAME, "second")]),
+            "second",
+        );
+        assert_eq!(
+            fabricate_name(&[(OID_ORGANIZATION_NAME, "Org"), (OID_COMMON_NAME, "signer")]),
+            "Org: signer",
+        );
+        // The organization is dropped if the common name starts with it.
+        assert_eq!(
+            fabricate_name(&[
+                (OID_ORGANIZATION_NAME, "Org"),
+                (OID_COMMON_NAME, "Org signing key"),
+            ]),
+            "Org signing key",
+        );
+        // Or if they share the same first 7 characters.
+        assert_eq!(
+            fabricate_name(&[
+                (OID_ORGANIZATION_NAME, "Example Corporation"),
+                (OID_COMMON_NAME, "Example Corp signing key"),
+            ]),
+            "Example Corp signing key",
+        );
+        assert_eq!(
+            fabricate_name(&[
+                (OID_ORGANIZATION_NAME, "Example"),
+                (OID_COMMON_NAME, "Exampl"),
+            ]),
+            "Example: Exampl",
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_parse() {
+        assert_eq!(
+            KeyFingerprint::parse("t: 8dd27e7ce54c6e9a2e828431f8d62fb65130b747"),
+            Some(fingerprint(
+                "t",
+                &[
+                    0x8d, 0xd2, 0x7e, 0x7c, 0xe5, 0x4c, 0x6e, 0x9a, 0x2e, 0x82, 0x84, 0x31, 0xf8,
+                    0xd6, 0x2f, 0xb6, 0x51, 0x30, 0xb7, 0x47,
+                ],
+            )),
+        );
+        assert_eq!(
+            KeyFingerprint::parse("Example: signer: 0123"),
+            Some(fingerprint("Example: signer", &[0x01, 0x23])),
+        );
+        assert_eq!(
+            KeyFingerprint::parse(": 00"),
+            Some(fingerprint("", &[0x00]))
+        );
+
+        assert_eq!(KeyFingerprint::parse("signer"), None);
+        assert_eq!(KeyFingerprint::parse("signer: "), None);
+        assert_eq!(KeyFingerprint::parse("signer: 012"), None);
+        assert_eq!(KeyFingerprint::parse("signer: key"), None);
+        assert_eq!(KeyFingerprint::parse("signer:0123"), None);
+    }
+
+    #[test]
+    fn test_fingerprint_payload() {
+        let payload = Payload::from_der(CERTIFICATE_DER).unwrap();
+        let fingerprint = payload.fingerprint().unwrap();
+        assert_eq!(fingerprint.subject, "Example: signer");
+        assert_eq!(fingerprint.id, [0x01, 0x23]);
+        assert_eq!(
+            KeyFingerprint::parse(&payload.description().unwrap()),
+            Some(fingerprint),
+        );
+
+        let payload = Payload::from_der(PRIVATE_KEY_DER).unwrap();
+        assert_eq!(payload.fingerprint(), None);
+    }
+
+    #[test]
+    fn test_query_description() {
+        assert_eq!(
+            AsymmetricQuery::Exact(vec![0xde, 0xad, 0xbe, 0xef]).description(),
+            "ex:deadbeef",
+        );
+        assert_eq!(
+            AsymmetricQuery::Partial(vec![0xbe, 0xef]).description(),
+            "id:beef",
+        );
+        assert_eq!(
+            AsymmetricQuery::Subject("my key".into()).description(),
+            "my key",
+        );
+        assert_eq!(
+            AsymmetricQuery::from(fingerprint("Example: signer", &[0x01, 0x23])).description(),
+            "Example: signer: 0123",
+        );
+    }
+}
@@ -27,22 +27,31 @@
 //! Certificate blacklist keys
 
 use std::borrow::Cow;
+use std::fs;
+
+use sha2::{Digest, Sha256};
 
 use super::ByteBuf;
+use crate::der;
 use crate::keytype::*;
+use crate::{Keyring, KeyringSerial, Result};
 
 /// Blacklist hashes.
+///
+/// Blacklist keys live in the `.blacklist` system keyring (see `system_keyring`). Adding keys to it
+/// from userspace requires a kernel with `CONFIG_SYSTEM_BLACKLIST_AUTH_UPDATE` (5.13+).
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct Blacklist;
 
 impl KeyType for Blacklist {
-    /// Login key descriptions are free-form.
+    /// Blacklist key descriptions are a hash type and the hash.
     type Description = Description;
-    /// Login payloads are free-form.
-    type Payload = ();
+    /// Blacklist payloads are a PKCS#7 signature of the description made by a key in the system
+    /// trusted keyrings.
+    type Payload = [u8];
 
     fn name() -> &'static str {
-        "keyring"
+        "blacklist"
     }
 }
 
@@ -50,8 +59,14 @@ impl KeyType for Blacklist {
 #[derive(Debug, Clone, Eq)]
 // #[non_exhaustive]
 pub enum HashType {
-    /// x509 data
+    /// The SHA-256 hash of the "to be signed" portion of an X.509 certificate.
+    ///
+    /// See `Description::for_certificate`.
     Tbs,
+    /// The hash of a binary (e.g., a kernel image).
+    ///
+    /// Requires Linux 5.13+.
+    Bin,
     /// Custom hash type
     Other(Cow<'static, str>),
 }
@@ -61,6 +76,7 @@ impl HashType {
     fn name(&self) -> &str {
         match *self {
             HashType::Tbs => "tbs",
+            HashType::Bin => "bin",
             HashType::Other(ref s) => s,
         }
     }
@@ -73,6 +89,7 @@ impl PartialEq for HashType {
 }
 
 /// The description of a blacklist key.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Description {
     /// The hash type to blacklist.
     pub hash_type: HashType,
@@ -80,8 +97,136 @@ pub struct Description {
     pub hash: Vec<u8>,
 }
 
+impl Description {
+    /// The description which blacklists a DER-encoded X.509 certificate.
+    ///
+    /// Returns `None` if the certificate cannot be parsed.
+    pub fn for_certificate(certificate: &[u8]) -> Option<Self> {
+        tbs_hash(certificate).map(|hash| {
+            Description {
+                hash_type: HashType::Tbs,
+                hash: hash.to_vec(),
+            }
+        })
+    }
+}
+
 impl KeyDescription for Description {
     fn description(&self) -> Cow<str> {
         format!("{}:{:x}", self.hash_type.name(), ByteBuf(&self.hash)).into()
     }
 }
+
+/// Compute the TBS hash of a DER-encoded X.509 certificate.
+///
+/// This is the SHA-256 hash of the `tbsCertificate` field of the certificate which is how the
+/// kernel identifies blacklisted certificates. Returns `None` if the certificate cannot be
+/// parsed.
+pub fn tbs_hash(certificate: &[u8]) -> Option<[u8; 32]> {
+    let (cert, _) = der::expect(certificate, der::TAG_SEQUENCE)?;
+    let (tbs, _) = der::expect(cert.contents, der::TAG_SEQUENCE)?;
+
+    let mut hash = [0; 32];
+    hash.copy_from_slice(&Sha256::digest(tbs.raw));
+    Some(hash)
+}
+
+const PROC_KEYS: &str = "/proc/keys";
+
+/// Find the `.blacklist` system keyring.
+///
+/// The keyring is found by looking through `/proc/keys`, so it must be viewable by the current
+/// process.
+pub fn system_keyring() -> Result<Keyring> {
+    let keys = fs::read_to_string(PROC_KEYS)
+        .map_err(|err| errno::Errno(err.raw_os_error().unwrap_or(libc::EIO)))?;
+    find_keyring(&keys, ".blacklist")
+        .map(|serial| unsafe { Keyring::new(serial) })
+        .ok_or(errno::Errno(libc::ENOKEY))
+}
+
+/// Find a keyring by its description in the contents of `/proc/keys`.
+fn find_keyring(keys: &str, description: &str) -> Option<KeyringSerial> {
+    keys.lines().find_map(|line| {
+        // <serial> <flags> <usage> <expiry> <perms> <uid> <gid> <type> <description>: <summary>
+        let mut fields = line.split_whitespace();
+        let serial = fields.next()?;
+        let type_ = fields.nth(6)?;
+        let desc = fields.next()?;
+        if type_ != "keyring" || desc.trim_end_matches(':') != description {
+            return None;
+        }
+        i32::from_str_radix(serial, 16)
+            .ok()
+            .and_then(KeyringSerial::new)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keytype::*;
+    use crate::keytypes::Blacklist;
+
+    use super::*;
+
+    // A minimal certificate structure: the TBS is `SEQUENCE { INTEGER 1 }`.
+    const CERTIFICATE: &[u8] = &[
+        0x30, 0x0c, // Certificate
+        0x30, 0x03, 0x02, 0x01, 0x01, // tbsCertificate
+        0x30, 0x00, // signatureAlgorithm
+        0x03, 0x03, 0x00, 0xaa, 0xbb, // signatureValue
+    ];
+    // sha256(30 03 02 01 01)
+    const TBS_HASH: &str = "1b65f68a522c858715f5dd951cd0402dc16691778814bf0759822b7a257421d0";
+
+    #[test]
+    fn test_name() {
+        assert_eq!(Blacklist::name(), "blacklist");
+    }
+
+    #[test]
+    fn test_description() {
+        let desc = Description {
+            hash_type: HashType::Bin,
+            hash: vec![0xde, 0xad, 0xbe, 0xef],
+        };
+        assert_eq!(desc.description(), "bin:deadbeef");
+
+        let desc = Description {
+            hash_type: HashType::Other("custom".into()),
+            hash: vec![0x00, 0x01],
+        };
+        assert_eq!(desc.description(), "custom:0001");
+    }
+
+    #[test]
+    fn test_tbs_hash() {
+        let hash = tbs_hash(CERTIFICATE).unwrap();
+        assert_eq!(format!("{:x}", ByteBuf(&hash)), TBS_HASH);
+
+        let desc = Description::for_certificate(CERTIFICATE).unwrap();
+        assert_eq!(desc.hash_type, HashType::Tbs);
+        assert_eq!(desc.description(), format!("tbs:{}", TBS_HASH));
+    }
+
+    #[test]
+    fn test_tbs_hash_invalid() {
+        assert_eq!(tbs_hash(&[]), None);
+        assert_eq!(tbs_hash(&CERTIFICATE[..5]), None);
+        // The TBS must be a sequence.
+        assert_eq!(tbs_hash(&[0x30, 0x03, 0x02, 0x01, 0x01]), None);
+    }
+
+    #[test]
+    fn test_find_keyring() {
+        let keys = "\
+            011d1a1e I--Q---     2 perm 1f3f0000     0 65534 keyring   _uid.0: empty\n\
+            0d3851d0 I------     1 perm 0f0b0000     0     0 keyring   .blacklist: empty\n\
+            1fd0777b I------     1 perm 1f030000     0     0 user      .blacklist: 4\n";
+        assert_eq!(
+            find_keyring(keys, ".blacklist").map(KeyringSerial::get),
+            Some(0x0d38_51d0),
+        );
+        assert_eq!(find_keyring(keys, ".builtin_trusted_keys"), None);
+    }
+}
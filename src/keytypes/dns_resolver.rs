@@ -27,6 +27,10 @@
 //! DNS resolution keys
 
 use std::borrow::Cow;
+use std::error;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str;
 
 use crate::keytype::*;
 
@@ -52,7 +56,13 @@ pub enum QueryType {
     /// An IPv6 address.
     AAAA,
     /// An AFS database address.
+    ///
+    /// Results are returned in the binary server list format.
     AFSDB,
+    /// A service location record.
+    ///
+    /// Results are returned in the binary server list format.
+    SRV,
     /// A custom DNS record.
     Other(Cow<'static, str>),
 }
@@ -64,9 +74,21 @@ impl QueryType {
             QueryType::A => "a",
             QueryType::AAAA => "aaaa",
             QueryType::AFSDB => "afsdb",
+            QueryType::SRV => "srv",
             QueryType::Other(s) => s,
         }
     }
+
+    /// The query type for a DNS record name.
+    fn from_name(name: &str) -> Self {
+        match name {
+            "a" => QueryType::A,
+            "aaaa" => QueryType::AAAA,
+            "afsdb" => QueryType::AFSDB,
+            "srv" => QueryType::SRV,
+            name => QueryType::Other(name.to_string().into()),
+        }
+    }
 }
 
 impl PartialEq for QueryType {
@@ -94,3 +116,657 @@ impl KeyDescription for Description {
         }
     }
 }
+
+impl Description {
+    /// Parse a description as given to the `key.dns_resolver` upcall.
+    ///
+    /// Returns `None` if the name is empty.
+    pub fn parse(desc: &str) -> Option<Self> {
+        let mut split = desc.splitn(2, ':');
+        let first = split.next().unwrap_or("");
+        let (query_type, name) = match split.next() {
+            Some(name) => (Some(QueryType::from_name(first)), name),
+            None => (None, first),
+        };
+
+        if name.is_empty() {
+            return None;
+        }
+
+        Some(Description {
+            query_type,
+            name: name.to_string().into(),
+        })
+    }
+}
+
+/// Errors which may occur when decoding a DNS resolver key's payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+// #[non_exhaustive]
+pub enum DecodeError {
+    /// An address in the text format could not be parsed.
+    InvalidAddress(String),
+    /// The payload ended before the expected data.
+    Truncated,
+    /// The binary payload has an unsupported content type.
+    UnsupportedContent(u8),
+    /// The binary payload has an unsupported version.
+    UnsupportedVersion(u8),
+    /// An address in the binary format has an unknown type.
+    UnknownAddressType(u8),
+    /// A server name is not valid UTF-8.
+    InvalidServerName,
+    /// There is data after the end of the server list.
+    TrailingData(usize),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::InvalidAddress(addr) => write!(f, "invalid address: `{}`", addr),
+            DecodeError::Truncated => write!(f, "truncated payload"),
+            DecodeError::UnsupportedContent(content) => {
+                write!(f, "unsupported payload content type: {}", content)
+            },
+            DecodeError::UnsupportedVersion(version) => {
+                write!(f, "unsupported payload version: {}", version)
+            },
+            DecodeError::UnknownAddressType(addr_type) => {
+                write!(f, "unknown address type: {}", addr_type)
+            },
+            DecodeError::InvalidServerName => write!(f, "server name is not valid UTF-8"),
+            DecodeError::TrailingData(len) => {
+                write!(f, "{} bytes of trailing data after the server list", len)
+            },
+        }
+    }
+}
+
+impl error::Error for DecodeError {}
+
+/// Decode the text format of a DNS resolver payload.
+///
+/// This is the format used for `A` and `AAAA` queries (and queries without a type). It is a
+/// comma-separated list of addresses, optionally followed by `#`-separated options.
+pub fn decode_addresses(payload: &[u8]) -> Result<Vec<IpAddr>, DecodeError> {
+    let end = payload
+        .iter()
+        .position(|&c| c == b'#' || c == 0)
+        .unwrap_or(payload.len());
+    let text = str::from_utf8(&payload[..end])
+        .map_err(|_| DecodeError::InvalidAddress(String::from_utf8_lossy(payload).into()))?;
+
+    text.split(',')
+        .map(str::trim)
+        .filter(|addr| !addr.is_empty())
+        .map(|addr| {
+            addr.parse()
+                .map_err(|_| DecodeError::InvalidAddress(addr.into()))
+        })
+        .collect()
+}
+
+/// Encode addresses in the text format of a DNS resolver payload.
+pub fn encode_addresses(addresses: &[IpAddr]) -> Vec<u8> {
+    addresses
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+        .into_bytes()
+}
+
+/// Where a record came from.
+///
+/// This is `enum dns_record_source` from `<keys/dns_resolver-type.h>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordSource {
+    /// The source is not known.
+    Unspecified,
+    /// The record is from static configuration.
+    StaticConfig,
+    /// The record is from a DNS `A` or `AAAA` record.
+    DnsA,
+    /// The record is from a DNS `AFSDB` record.
+    DnsAfsdb,
+    /// The record is from a DNS `SRV` record.
+    DnsSrv,
+    /// The record is from NSS (e.g., `/etc/hosts`).
+    Nss,
+    /// A source unknown to this crate.
+    Other(u8),
+}
+
+impl From<u8> for RecordSource {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => RecordSource::Unspecified,
+            1 => RecordSource::StaticConfig,
+            2 => RecordSource::DnsA,
+            3 => RecordSource::DnsAfsdb,
+            4 => RecordSource::DnsSrv,
+            5 => RecordSource::Nss,
+            value => RecordSource::Other(value),
+        }
+    }
+}
+
+impl From<RecordSource> for u8 {
+    fn from(value: RecordSource) -> Self {
+        match value {
+            RecordSource::Unspecified => 0,
+            RecordSource::StaticConfig => 1,
+            RecordSource::DnsA => 2,
+            RecordSource::DnsAfsdb => 3,
+            RecordSource::DnsSrv => 4,
+            RecordSource::Nss => 5,
+            RecordSource::Other(value) => value,
+        }
+    }
+}
+
+/// The status of a lookup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LookupStatus {
+    /// The lookup was not performed.
+    NotDone,
+    /// The lookup succeeded.
+    Good,
+    /// The lookup succeeded, but some results were bad.
+    GoodWithBad,
+    /// The lookup failed.
+    Bad,
+    /// The name was not found.
+    NotFound,
+    /// A local failure occurred.
+    LocalFailure,
+    /// A temporary failure occurred.
+    TemporaryFailure,
+    /// The nameserver failed.
+    NameserverFailure,
+    /// A status unknown to this crate.
+    Other(u8),
+}
+
+impl From<u8> for LookupStatus {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => LookupStatus::NotDone,
+            1 => LookupStatus::Good,
+            2 => LookupStatus::GoodWithBad,
+            3 => LookupStatus::Bad,
+            4 => LookupStatus::NotFound,
+            5 => LookupStatus::LocalFailure,
+            6 => LookupStatus::TemporaryFailure,
+            7 => LookupStatus::NameserverFailure,
+            value => LookupStatus::Other(value),
+        }
+    }
+}
+
+impl From<LookupStatus> for u8 {
+    fn from(value: LookupStatus) -> Self {
+        match value {
+            LookupStatus::NotDone => 0,
+            LookupStatus::Good => 1,
+            LookupStatus::GoodWithBad => 2,
+            LookupStatus::Bad => 3,
+            LookupStatus::NotFound => 4,
+            LookupStatus::LocalFailure => 5,
+            LookupStatus::TemporaryFailure => 6,
+            LookupStatus::NameserverFailure => 7,
+            LookupStatus::Other(value) => value,
+        }
+    }
+}
+
+/// The protocol used to contact a server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    /// The protocol is not specified.
+    Unspecified,
+    /// UDP
+    Udp,
+    /// TCP
+    Tcp,
+    /// A protocol unknown to this crate.
+    Other(u8),
+}
+
+impl From<u8> for Protocol {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Protocol::Unspecified,
+            1 => Protocol::Udp,
+            2 => Protocol::Tcp,
+            value => Protocol::Other(value),
+        }
+    }
+}
+
+impl From<Protocol> for u8 {
+    fn from(value: Protocol) -> Self {
+        match value {
+            Protocol::Unspecified => 0,
+            Protocol::Udp => 1,
+            Protocol::Tcp => 2,
+            Protocol::Other(value) => value,
+        }
+    }
+}
+
+/// A server in a server list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Server {
+    /// The name of the server.
+    pub name: String,
+    /// The priority of the server (as in `SRV` records).
+    pub priority: u16,
+    /// The weight of the server (as in `SRV` records).
+    pub weight: u16,
+    /// The port of the server.
+    pub port: u16,
+    /// The protocol to use to contact the server.
+    pub protocol: Protocol,
+    /// Where the server's addresses came from.
+    pub source: RecordSource,
+    /// The status of the lookup of the server's addresses.
+    pub status: LookupStatus,
+    /// The addresses of the server.
+    pub addresses: Vec<IpAddr>,
+}
+
+/// A list of servers.
+///
+/// This is the binary format used for `AFSDB` and `SRV` queries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerList {
+    /// Where the list came from.
+    pub source: RecordSource,
+    /// The status of the lookup.
+    pub status: LookupStatus,
+    /// The servers in the list.
+    pub servers: Vec<Server>,
+}
+
+/// The content type for server lists.
+const DNS_PAYLOAD_IS_SERVER_LIST: u8 = 0;
+/// The supported version of the server list format.
+const DNS_SERVER_LIST_VERSION: u8 = 1;
+
+/// A cursor over binary payload data.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        if self.data.len() < len {
+            return Err(DecodeError::Truncated);
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, DecodeError> {
+        self.bytes(1).map(|bytes| bytes[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, DecodeError> {
+        self.bytes(2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+}
+
+impl ServerList {
+    /// Decode the binary server list format of a DNS resolver payload.
+    pub fn decode(payload: &[u8]) -> Result<Self, DecodeError> {
+        // All fields are little endian.
+        //
+        // struct dns_server_list_v1_header {
+        //     uint8_t zero;
+        //     uint8_t content;
+        //     uint8_t version;
+        //     uint8_t source;
+        //     uint8_t status;
+        //     uint8_t nr_servers;
+        // };
+        let mut reader = Reader {
+            data: payload,
+        };
+
+        if reader.u8()? != 0 {
+            return Err(DecodeError::UnsupportedContent(payload[0]));
+        }
+        let content = reader.u8()?;
+        if content != DNS_PAYLOAD_IS_SERVER_LIST {
+            return Err(DecodeError::UnsupportedContent(content));
+        }
+        let version = reader.u8()?;
+        if version != DNS_SERVER_LIST_VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+        let source = reader.u8()?.into();
+        let status = reader.u8()?.into();
+        let nr_servers = reader.u8()?;
+
+        let servers = (0..nr_servers)
+            .map(|_| Self::decode_server(&mut reader))
+            .collect::<Result<_, _>>()?;
+
+        if !reader.data.is_empty() {
+            return Err(DecodeError::TrailingData(reader.data.len()));
+        }
+
+        Ok(ServerList {
+            source,
+            status,
+            servers,
+        })
+    }
+
+    /// Encode the server list in the binary format.
+    ///
+    /// The format can describe at most 255 servers with 255 addresses each; any beyond that are
+    /// omitted.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut payload = vec![
+            0,
+            DNS_PAYLOAD_IS_SERVER_LIST,
+            DNS_SERVER_LIST_VERSION,
+            self.source.into(),
+            self.status.into(),
+            self.servers.len().min(255) as u8,
+        ];
+
+        for server in self.servers.iter().take(255) {
+            let name = server.name.as_bytes();
+            let name = &name[..name.len().min(0xffff)];
+
+            payload.extend((name.len() as u16).to_le_bytes().iter());
+            payload.extend(server.priority.to_le_bytes().iter());
+            payload.extend(server.weight.to_le_bytes().iter());
+            payload.extend(server.port.to_le_bytes().iter());
+            payload.push(server.source.into());
+            payload.push(server.status.into());
+            payload.push(server.protocol.into());
+            payload.push(server.addresses.len().min(255) as u8);
+            payload.extend(name.iter());
+
+            for address in server.addresses.iter().take(255) {
+                match address {
+                    IpAddr::V4(addr) => {
+                        payload.push(0);
+                        payload.extend(addr.octets().iter());
+                    },
+                    IpAddr::V6(addr) => {
+                        payload.push(1);
+                        payload.extend(addr.octets().iter());
+                    },
+                }
+            }
+        }
+
+        payload
+    }
+
+    fn decode_server(reader: &mut Reader) -> Result<Server, DecodeError> {
+        // struct dns_server_list_v1_server {
+        //     uint16_t name_len;
+        //     uint16_t priority;
+        //     uint16_t weight;
+        //     uint16_t port;
+        //     uint8_t  source;
+        //     uint8_t  status;
+        //     uint8_t  protocol;
+        //     uint8_t  nr_addrs;
+        //     char     name[name_len];
+        // };
+        let name_len = reader.u16()?;
+        let priority = reader.u16()?;
+        let weight = reader.u16()?;
+        let port = reader.u16()?;
+        let source = reader.u8()?.into();
+        let status = reader.u8()?.into();
+        let protocol = reader.u8()?.into();
+        let nr_addrs = reader.u8()?;
+        let name = str::from_utf8(reader.bytes(name_len.into())?)
+            .map_err(|_| DecodeError::InvalidServerName)?
+            .into();
+
+        let addresses = (0..nr_addrs)
+            .map(|_| Self::decode_address(reader))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Server {
+            name,
+            priority,
+            weight,
+            port,
+            protocol,
+            source,
+            status,
+            addresses,
+        })
+    }
+
+    fn decode_address(reader: &mut Reader) -> Result<IpAddr, DecodeError> {
+        // struct dns_server_list_v1_address {
+        //     uint8_t address_type;
+        //     uint8_t address[]; /* 4 or 16 bytes in network order */
+        // };
+        match reader.u8()? {
+            0 => {
+                let b = reader.bytes(4)?;
+                Ok(Ipv4Addr::new(b[0], b[1], b[2], b[3]).into())
+            },
+            1 => {
+                let mut octets = [0; 16];
+                octets.copy_from_slice(reader.bytes(16)?);
+                Ok(Ipv6Addr::from(octets).into())
+            },
+            addr_type => Err(DecodeError::UnknownAddressType(addr_type)),
+        }
+    }
+}
+
+/// The decoded payload of a DNS resolver key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolved {
+    /// A list of addresses.
+    Addresses(Vec<IpAddr>),
+    /// A list of servers.
+    ServerList(ServerList),
+}
+
+impl Resolved {
+    /// Decode a DNS resolver payload in either format.
+    ///
+    /// Binary payloads are distinguished from text payloads by a leading zero byte.
+    pub fn decode(payload: &[u8]) -> Result<Self, DecodeError> {
+        if payload.first() == Some(&0) {
+            ServerList::decode(payload).map(Resolved::ServerList)
+        } else {
+            decode_addresses(payload).map(Resolved::Addresses)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    use crate::keytype::*;
+
+    use super::*;
+
+    #[test]
+    fn test_description() {
+        let desc = Description {
+            query_type: Some(QueryType::SRV),
+            name: "_afs3-vlserver._udp.example.org".into(),
+        };
+        assert_eq!(desc.description(), "srv:_afs3-vlserver._udp.example.org");
+    }
+
+    #[test]
+    fn test_parse_description() {
+        let desc = Description::parse("afsdb:example.org").unwrap();
+        assert_eq!(desc.query_type, Some(QueryType::AFSDB));
+        assert_eq!(desc.name, "example.org");
+
+        let desc = Description::parse("example.org").unwrap();
+        assert_eq!(desc.query_type, None);
+        assert_eq!(desc.name, "example.org");
+
+        let desc = Description::parse("txt:example.org").unwrap();
+        assert_eq!(desc.query_type, Some(QueryType::Other("txt".into())));
+
+        assert_eq!(Description::parse(""), None);
+        assert_eq!(Description::parse("a:"), None);
+    }
+
+    #[test]
+    fn test_encode_addresses() {
+        let addrs = [
+            IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+            IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+        ];
+        let payload = encode_addresses(&addrs);
+        assert_eq!(payload, b"192.0.2.1,2001:db8::1");
+        assert_eq!(decode_addresses(&payload).unwrap(), addrs);
+    }
+
+    #[test]
+    fn test_decode_addresses() {
+        let addrs = decode_addresses(b"192.0.2.1,2001:db8::1").unwrap();
+        assert_eq!(
+            addrs,
+            [
+                IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+                IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+            ],
+        );
+
+        assert_eq!(
+            decode_addresses(b"192.0.2.1#dnserror=3").unwrap(),
+            [IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))],
+        );
+        assert!(decode_addresses(b"").unwrap().is_empty());
+        assert_eq!(
+            decode_addresses(b"192.0.2.1,bogus").unwrap_err(),
+            DecodeError::InvalidAddress("bogus".into()),
+        );
+    }
+
+    #[test]
+    fn test_record_source() {
+        let sources = [
+            (0, RecordSource::Unspecified),
+            (1, RecordSource::StaticConfig),
+            (2, RecordSource::DnsA),
+            (3, RecordSource::DnsAfsdb),
+            (4, RecordSource::DnsSrv),
+            (5, RecordSource::Nss),
+            (6, RecordSource::Other(6)),
+        ];
+
+        for &(value, source) in sources.iter() {
+            assert_eq!(RecordSource::from(value), source);
+            assert_eq!(u8::from(source), value);
+        }
+    }
+
+    #[rustfmt::skip]
+    const SERVER_LIST: &[u8] = &[
+        0, 0, 1, // header
+        3, 1, 2, // source, status, nr_servers
+        // server 1
+        4, 0, 10, 0, 20, 0, 0x5b, 0x1b, // name_len, priority, weight, port
+        3, 1, 1, 2, // source, status, protocol, nr_addrs
+        b'v', b'l', b'0', b'1',
+        0, 192, 0, 2, 1,
+        1, 0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
+        // server 2
+        4, 0, 20, 0, 0, 0, 0, 0, // name_len, priority, weight, port
+        0, 4, 0, 0, // source, status, protocol, nr_addrs
+        b'v', b'l', b'0', b'2',
+    ];
+
+    #[test]
+    fn test_decode_server_list() {
+        let list = ServerList::decode(SERVER_LIST).unwrap();
+
+        assert_eq!(list.source, RecordSource::DnsAfsdb);
+        assert_eq!(list.status, LookupStatus::Good);
+        assert_eq!(list.servers.len(), 2);
+
+        let server = &list.servers[0];
+        assert_eq!(server.name, "vl01");
+        assert_eq!(server.priority, 10);
+        assert_eq!(server.weight, 20);
+        assert_eq!(server.port, 7003);
+        assert_eq!(server.source, RecordSource::DnsAfsdb);
+        assert_eq!(server.status, LookupStatus::Good);
+        assert_eq!(server.protocol, Protocol::Udp);
+        assert_eq!(
+            server.addresses,
+            [
+                IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+                IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+            ],
+        );
+
+        let server = &list.servers[1];
+        assert_eq!(server.name, "vl02");
+        assert_eq!(server.priority, 20);
+        assert_eq!(server.status, LookupStatus::NotFound);
+        assert_eq!(server.source, RecordSource::Unspecified);
+        assert_eq!(server.protocol, Protocol::Unspecified);
+        assert!(server.addresses.is_empty());
+    }
+
+    #[test]
+    fn test_encode_server_list() {
+        let list = ServerList::decode(SERVER_LIST).unwrap();
+        assert_eq!(list.encode(), SERVER_LIST);
+    }
+
+    #[test]
+    fn test_decode_server_list_errors() {
+        assert_eq!(
+            ServerList::decode(&SERVER_LIST[..SERVER_LIST.len() - 1]).unwrap_err(),
+            DecodeError::Truncated,
+        );
+        assert_eq!(
+            ServerList::decode(&[0, 1, 1, 0, 0, 0]).unwrap_err(),
+            DecodeError::UnsupportedContent(1),
+        );
+        assert_eq!(
+            ServerList::decode(&[0, 0, 2, 0, 0, 0]).unwrap_err(),
+            DecodeError::UnsupportedVersion(2),
+        );
+        assert_eq!(
+            ServerList::decode(&[0, 0, 1, 0, 0, 0, 0]).unwrap_err(),
+            DecodeError::TrailingData(1),
+        );
+        assert_eq!(
+            ServerList::decode(&[0, 0, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 7])
+                .unwrap_err(),
+            DecodeError::UnknownAddressType(7),
+        );
+    }
+
+    #[test]
+    fn test_resolved() {
+        assert_eq!(
+            Resolved::decode(b"192.0.2.1").unwrap(),
+            Resolved::Addresses(vec![IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))]),
+        );
+        match Resolved::decode(SERVER_LIST).unwrap() {
+            Resolved::ServerList(list) => assert_eq!(list.servers.len(), 2),
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+}
@@ -0,0 +1,530 @@
+// Copyright (c) 2026, Ben Boeckel
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of this project nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! eCryptfs authentication tokens
+//!
+//! eCryptfs looks up its file encryption key encryption keys (FEKEKs) as `user` or `encrypted`
+//! (`ecryptfs` format) keys whose description is the key's signature. The payload of `user`
+//! keys is a `struct ecryptfs_auth_tok`, as built by `AuthToken`.
+
+use std::borrow::Cow;
+use std::error;
+use std::fmt;
+use std::str;
+
+use sha2::{Digest, Sha512};
+
+use super::ByteBuf;
+use crate::keytype::*;
+
+/// The salt used by `ecryptfs-utils` if none is configured.
+pub const DEFAULT_SALT: [u8; SALT_SIZE] = [0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77];
+
+/// The size of a salt.
+const SALT_SIZE: usize = 8;
+/// The size of a signature (in bytes).
+const SIG_SIZE: usize = 8;
+/// The size of a signature (in hexadecimal characters).
+const SIG_SIZE_HEX: usize = 2 * SIG_SIZE;
+/// The maximum size of a key.
+const MAX_KEY_BYTES: usize = 64;
+/// The maximum size of an encrypted key.
+const MAX_ENCRYPTED_KEY_BYTES: usize = 512;
+/// The maximum size of a passphrase.
+const MAX_PASSPHRASE_BYTES: usize = 64;
+/// The number of hash iterations used to derive a key from a passphrase.
+const HASH_ITERATIONS: usize = 65536;
+/// The token format version (major 0, minor 4).
+const VERSION: u16 = 0x0004;
+/// The token type of password tokens.
+const ECRYPTFS_PASSWORD: u16 = 0;
+/// The OpenPGP identifier for SHA-512.
+const PGP_DIGEST_ALGO_SHA512: i32 = 10;
+/// Password flag indicating that the session key encryption key is present.
+const ECRYPTFS_SESSION_KEY_ENCRYPTION_KEY_SET: u32 = 0x02;
+
+/// The size of `struct ecryptfs_auth_tok`.
+///
+/// The outer structure is packed, but the nested `struct ecryptfs_password` is not, so it has 3
+/// bytes of tail padding after the salt.
+const AUTH_TOK_SIZE: usize = 740;
+
+// Offsets into the packed `struct ecryptfs_auth_tok`.
+const OFF_TOKEN_TYPE: usize = 2;
+const OFF_FLAGS: usize = 4;
+const OFF_SESSION_KEY: usize = 8;
+const OFF_PASSWORD: usize = OFF_SESSION_KEY + 12 + MAX_ENCRYPTED_KEY_BYTES + MAX_KEY_BYTES + 32;
+const OFF_SIGNATURE: usize = OFF_PASSWORD + 20 + MAX_KEY_BYTES;
+const OFF_SALT: usize = OFF_SIGNATURE + SIG_SIZE_HEX + 1;
+
+/// Errors which may occur when building or parsing an authentication token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+// #[non_exhaustive]
+pub enum AuthTokenError {
+    /// Passphrases may be at most 64 bytes.
+    PassphraseTooLong(usize),
+    /// The token has the wrong size.
+    InvalidSize(usize),
+    /// The token has an unsupported version.
+    UnsupportedVersion(u16),
+    /// The token is not a password token.
+    UnsupportedTokenType(u16),
+    /// The token's signature is not a 16-character hexadecimal string.
+    InvalidSignature,
+    /// A key in the token has an invalid size.
+    InvalidKeySize(u32),
+}
+
+impl fmt::Display for AuthTokenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AuthTokenError::PassphraseTooLong(len) => {
+                write!(
+                    f,
+                    "passphrases may be at most {} bytes, not {} bytes",
+                    MAX_PASSPHRASE_BYTES, len,
+                )
+            },
+            AuthTokenError::InvalidSize(len) => {
+                write!(
+                    f,
+                    "authentication tokens must be {} bytes, not {} bytes",
+                    AUTH_TOK_SIZE, len,
+                )
+            },
+            AuthTokenError::UnsupportedVersion(version) => {
+                write!(f, "unsupported token version: {:#06x}", version)
+            },
+            AuthTokenError::UnsupportedTokenType(token_type) => {
+                write!(f, "unsupported token type: {}", token_type)
+            },
+            AuthTokenError::InvalidSignature => write!(f, "invalid signature"),
+            AuthTokenError::InvalidKeySize(size) => write!(f, "invalid key size: {}", size),
+        }
+    }
+}
+
+impl error::Error for AuthTokenError {}
+
+/// Whether a string is a valid eCryptfs key signature.
+///
+/// Signatures are exactly 16 hexadecimal characters.
+pub fn is_signature(sig: &str) -> bool {
+    sig.len() == SIG_SIZE_HEX && sig.bytes().all(|c| c.is_ascii_hexdigit())
+}
+
+/// An eCryptfs password authentication token.
+#[derive(Clone, PartialEq, Eq)]
+pub struct AuthToken {
+    version: u16,
+    flags: u32,
+    session_key_flags: u32,
+    encrypted_key: Vec<u8>,
+    decrypted_key: Vec<u8>,
+    hash_algo: i32,
+    hash_iterations: u32,
+    password_flags: u32,
+    session_key_encryption_key: Vec<u8>,
+    signature: String,
+    salt: [u8; SALT_SIZE],
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_ne_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(&data[offset..offset + 4]);
+    u32::from_ne_bytes(bytes)
+}
+
+fn read_key(data: &[u8], offset: usize, size: u32, max: usize) -> Result<Vec<u8>, AuthTokenError> {
+    let len = size as usize;
+    if len > max {
+        return Err(AuthTokenError::InvalidKeySize(size));
+    }
+    Ok(data[offset..offset + len].into())
+}
+
+impl AuthToken {
+    /// Build a token from a passphrase and salt.
+    ///
+    /// The session key encryption key and signature are derived the same way `ecryptfs-utils`
+    /// derives them: the salt and passphrase are hashed using 65536 iterations of SHA-512.
+    pub fn from_passphrase(
+        passphrase: &[u8],
+        salt: [u8; SALT_SIZE],
+    ) -> Result<Self, AuthTokenError> {
+        if passphrase.len() > MAX_PASSPHRASE_BYTES {
+            return Err(AuthTokenError::PassphraseTooLong(passphrase.len()));
+        }
+
+        let mut hasher = Sha512::new();
+        hasher.update(salt);
+        hasher.update(passphrase);
+        let mut digest = hasher.finalize();
+        for _ in 1..HASH_ITERATIONS {
+            digest = Sha512::digest(&digest);
+        }
+        let session_key_encryption_key = digest.to_vec();
+        let signature = format!("{:x}", ByteBuf(&Sha512::digest(&digest)[..SIG_SIZE]));
+
+        Ok(AuthToken {
+            version: VERSION,
+            flags: 0,
+            session_key_flags: 0,
+            encrypted_key: Vec::new(),
+            decrypted_key: Vec::new(),
+            hash_algo: PGP_DIGEST_ALGO_SHA512,
+            hash_iterations: 0,
+            password_flags: ECRYPTFS_SESSION_KEY_ENCRYPTION_KEY_SET,
+            session_key_encryption_key,
+            signature,
+            salt,
+        })
+    }
+
+    /// Parse a token from a key payload.
+    pub fn parse(data: &[u8]) -> Result<Self, AuthTokenError> {
+        if data.len() != AUTH_TOK_SIZE {
+            return Err(AuthTokenError::InvalidSize(data.len()));
+        }
+
+        let version = read_u16(data, 0);
+        if version != VERSION {
+            return Err(AuthTokenError::UnsupportedVersion(version));
+        }
+        let token_type = read_u16(data, OFF_TOKEN_TYPE);
+        if token_type != ECRYPTFS_PASSWORD {
+            return Err(AuthTokenError::UnsupportedTokenType(token_type));
+        }
+
+        let encrypted_key = read_key(
+            data,
+            OFF_SESSION_KEY + 12,
+            read_u32(data, OFF_SESSION_KEY + 4),
+            MAX_ENCRYPTED_KEY_BYTES,
+        )?;
+        let decrypted_key = read_key(
+            data,
+            OFF_SESSION_KEY + 12 + MAX_ENCRYPTED_KEY_BYTES,
+            read_u32(data, OFF_SESSION_KEY + 8),
+            MAX_KEY_BYTES,
+        )?;
+        let session_key_encryption_key = read_key(
+            data,
+            OFF_PASSWORD + 20,
+            read_u32(data, OFF_PASSWORD + 12),
+            MAX_KEY_BYTES,
+        )?;
+
+        let signature = &data[OFF_SIGNATURE..OFF_SIGNATURE + SIG_SIZE_HEX];
+        let signature = str::from_utf8(signature)
+            .ok()
+            .filter(|sig| is_signature(sig))
+            .ok_or(AuthTokenError::InvalidSignature)?;
+        let mut salt = [0; SALT_SIZE];
+        salt.copy_from_slice(&data[OFF_SALT..OFF_SALT + SALT_SIZE]);
+
+        Ok(AuthToken {
+            version,
+            flags: read_u32(data, OFF_FLAGS),
+            session_key_flags: read_u32(data, OFF_SESSION_KEY),
+            encrypted_key,
+            decrypted_key,
+            hash_algo: read_u32(data, OFF_PASSWORD + 4) as i32,
+            hash_iterations: read_u32(data, OFF_PASSWORD + 8),
+            password_flags: read_u32(data, OFF_PASSWORD + 16),
+            session_key_encryption_key,
+            signature: signature.into(),
+            salt,
+        })
+    }
+
+    /// The version of the token format.
+    ///
+    /// The major version is in the high byte and the minor version is in the low byte.
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// The token's flags.
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    /// The flags of the token's session key.
+    pub fn session_key_flags(&self) -> u32 {
+        self.session_key_flags
+    }
+
+    /// The OpenPGP identifier of the hash algorithm used for the passphrase.
+    pub fn hash_algo(&self) -> i32 {
+        self.hash_algo
+    }
+
+    /// The flags of the password.
+    pub fn password_flags(&self) -> u32 {
+        self.password_flags
+    }
+
+    /// The key used to encrypt file encryption keys.
+    pub fn session_key_encryption_key(&self) -> &[u8] {
+        &self.session_key_encryption_key
+    }
+
+    /// The signature of the token.
+    ///
+    /// This is the description to use for the key holding the token.
+    pub fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    /// The salt used with the passphrase.
+    pub fn salt(&self) -> [u8; SALT_SIZE] {
+        self.salt
+    }
+}
+
+impl fmt::Debug for AuthToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AuthToken")
+            .field("version", &self.version)
+            .field("flags", &self.flags)
+            .field("session_key_flags", &self.session_key_flags)
+            .field("hash_algo", &self.hash_algo)
+            .field("password_flags", &self.password_flags)
+            .field("signature", &self.signature)
+            .finish()
+    }
+}
+
+impl KeyPayload for AuthToken {
+    fn payload(&self) -> Cow<[u8]> {
+        // All fields are in host byte order and the outer structure is packed.
+        //
+        // struct ecryptfs_auth_tok {
+        //     uint16_t version;
+        //     uint16_t token_type;
+        //     uint32_t flags;
+        //     struct ecryptfs_session_key {
+        //         uint32_t flags;
+        //         uint32_t encrypted_key_size;
+        //         uint32_t decrypted_key_size;
+        //         uint8_t  encrypted_key[512];
+        //         uint8_t  decrypted_key[64];
+        //     } session_key;
+        //     uint8_t reserved[32];
+        //     struct ecryptfs_password {
+        //         uint32_t password_bytes;
+        //         int32_t  hash_algo;
+        //         uint32_t hash_iterations;
+        //         uint32_t session_key_encryption_key_bytes;
+        //         uint32_t flags;
+        //         uint8_t  session_key_encryption_key[64];
+        //         uint8_t  signature[17];
+        //         uint8_t  salt[8];
+        //         // 3 bytes of padding
+        //     } password;
+        // };
+        fn pad(payload: &mut Vec<u8>, data: &[u8], size: usize) {
+            payload.extend(data.iter());
+            payload.resize(payload.len() + size - data.len(), 0);
+        }
+
+        let mut payload = Vec::with_capacity(AUTH_TOK_SIZE);
+        payload.extend(self.version.to_ne_bytes().iter());
+        payload.extend(ECRYPTFS_PASSWORD.to_ne_bytes().iter());
+        payload.extend(self.flags.to_ne_bytes().iter());
+        payload.extend(self.session_key_flags.to_ne_bytes().iter());
+        payload.extend((self.encrypted_key.len() as u32).to_ne_bytes().iter());
+        payload.extend((self.decrypted_key.len() as u32).to_ne_bytes().iter());
+        pad(&mut payload, &self.encrypted_key, MAX_ENCRYPTED_KEY_BYTES);
+        pad(&mut payload, &self.decrypted_key, MAX_KEY_BYTES);
+        pad(&mut payload, &[], 32);
+        payload.extend(0u32.to_ne_bytes().iter());
+        payload.extend(self.hash_algo.to_ne_bytes().iter());
+        payload.extend(self.hash_iterations.to_ne_bytes().iter());
+        payload.extend(
+            (self.session_key_encryption_key.len() as u32)
+                .to_ne_bytes()
+                .iter(),
+        );
+        payload.extend(self.password_flags.to_ne_bytes().iter());
+        pad(
+            &mut payload,
+            &self.session_key_encryption_key,
+            MAX_KEY_BYTES,
+        );
+        pad(&mut payload, self.signature.as_bytes(), SIG_SIZE_HEX + 1);
+        payload.extend(self.salt.iter());
+        // Tail padding of `struct ecryptfs_password`.
+        payload.resize(AUTH_TOK_SIZE, 0);
+
+        payload.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keytype::*;
+
+    use super::*;
+
+    #[test]
+    fn test_is_signature() {
+        assert!(is_signature("0123456789abcdef"));
+        assert!(is_signature("0123456789ABCDEF"));
+        assert!(!is_signature("0123456789abcde"));
+        assert!(!is_signature("0123456789abcdef0"));
+        assert!(!is_signature("0123456789abcdeg"));
+    }
+
+    #[test]
+    fn test_from_passphrase() {
+        let token = AuthToken::from_passphrase(b"passphrase", DEFAULT_SALT).unwrap();
+
+        assert_eq!(token.signature(), "1848e1d7b8a187fc");
+        assert_eq!(token.session_key_encryption_key().len(), 64);
+        assert_eq!(
+            &token.session_key_encryption_key()[..8],
+            &[0xc5, 0x67, 0x2c, 0x1b, 0x3c, 0x30, 0xcd, 0x17],
+        );
+        assert_eq!(token.hash_algo(), PGP_DIGEST_ALGO_SHA512);
+        assert_eq!(
+            token.password_flags(),
+            ECRYPTFS_SESSION_KEY_ENCRYPTION_KEY_SET,
+        );
+    }
+
+    #[test]
+    fn test_passphrase_too_long() {
+        assert_eq!(
+            AuthToken::from_passphrase(&[b'x'; 65], DEFAULT_SALT).unwrap_err(),
+            AuthTokenError::PassphraseTooLong(65),
+        );
+    }
+
+    #[test]
+    fn test_payload_layout() {
+        let token = AuthToken::from_passphrase(b"passphrase", DEFAULT_SALT).unwrap();
+        let payload = token.payload();
+
+        assert_eq!(payload.len(), AUTH_TOK_SIZE);
+        assert_eq!(&payload[..2], &VERSION.to_ne_bytes());
+        assert_eq!(
+            &payload[OFF_PASSWORD + 4..OFF_PASSWORD + 8],
+            &10i32.to_ne_bytes()
+        );
+        assert_eq!(
+            &payload[OFF_PASSWORD + 12..OFF_PASSWORD + 16],
+            &64u32.to_ne_bytes()
+        );
+        assert_eq!(&payload[OFF_SIGNATURE..OFF_SALT], b"1848e1d7b8a187fc\0");
+        assert_eq!(&payload[OFF_SALT..OFF_SALT + SALT_SIZE], &DEFAULT_SALT);
+        assert_eq!(&payload[OFF_SALT + SALT_SIZE..], &[0; 3]);
+    }
+
+    #[test]
+    fn test_parse_ecryptfs_utils() {
+        // A token laid out as `ecryptfs-utils` writes `sizeof(struct ecryptfs_auth_tok)` bytes.
+        let mut data = vec![0; 740];
+        data[0..2].copy_from_slice(&0x0004u16.to_ne_bytes());
+        // password.hash_algo
+        data[632..636].copy_from_slice(&10i32.to_ne_bytes());
+        // password.session_key_encryption_key_bytes
+        data[640..644].copy_from_slice(&64u32.to_ne_bytes());
+        // password.flags
+        data[644..648].copy_from_slice(&2u32.to_ne_bytes());
+        // password.session_key_encryption_key
+        data[648..712].copy_from_slice(&[0xaa; 64]);
+        // password.signature
+        data[712..728].copy_from_slice(b"0123456789abcdef");
+        // password.salt
+        data[729..737].copy_from_slice(&DEFAULT_SALT);
+
+        let token = AuthToken::parse(&data).unwrap();
+        assert_eq!(token.version(), 0x0004);
+        assert_eq!(token.hash_algo(), PGP_DIGEST_ALGO_SHA512);
+        assert_eq!(
+            token.password_flags(),
+            ECRYPTFS_SESSION_KEY_ENCRYPTION_KEY_SET,
+        );
+        assert_eq!(token.session_key_encryption_key(), &[0xaa; 64][..]);
+        assert_eq!(token.signature(), "0123456789abcdef");
+        assert_eq!(token.salt(), DEFAULT_SALT);
+        assert_eq!(token.payload(), &data[..]);
+    }
+
+    #[test]
+    fn test_parse_roundtrip() {
+        let token = AuthToken::from_passphrase(b"passphrase", [1; 8]).unwrap();
+        let parsed = AuthToken::parse(&token.payload()).unwrap();
+
+        assert_eq!(parsed, token);
+        assert_eq!(parsed.salt(), [1; 8]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let token = AuthToken::from_passphrase(b"passphrase", DEFAULT_SALT).unwrap();
+        let payload = token.payload().into_owned();
+
+        assert_eq!(
+            AuthToken::parse(&payload[1..]).unwrap_err(),
+            AuthTokenError::InvalidSize(739),
+        );
+
+        let mut bad = payload.clone();
+        bad[..2].copy_from_slice(&0x0103u16.to_ne_bytes());
+        assert_eq!(
+            AuthToken::parse(&bad).unwrap_err(),
+            AuthTokenError::UnsupportedVersion(0x0103),
+        );
+
+        let mut bad = payload.clone();
+        bad[2..4].copy_from_slice(&1u16.to_ne_bytes());
+        assert_eq!(
+            AuthToken::parse(&bad).unwrap_err(),
+            AuthTokenError::UnsupportedTokenType(1),
+        );
+
+        let mut bad = payload.clone();
+        bad[OFF_PASSWORD + 12..OFF_PASSWORD + 16].copy_from_slice(&65u32.to_ne_bytes());
+        assert_eq!(
+            AuthToken::parse(&bad).unwrap_err(),
+            AuthTokenError::InvalidKeySize(65),
+        );
+
+        let mut bad = payload;
+        bad[OFF_SIGNATURE] = b'z';
+        assert_eq!(
+            AuthToken::parse(&bad).unwrap_err(),
+            AuthTokenError::InvalidSignature,
+        );
+    }
+}
@@ -27,8 +27,11 @@
 //! Encrypted keys
 
 use std::borrow::Cow;
+use std::error;
+use std::fmt;
+use std::str;
 
-use super::ByteBuf;
+use super::{ecryptfs, from_hex, ByteBuf};
 use crate::keytype::*;
 
 /// Encrypted keys.
@@ -77,6 +80,16 @@ impl Format {
             Format::Enc32 => "enc32",
         }
     }
+
+    /// The key format with the given name.
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Format::Default),
+            "ecryptfs" => Some(Format::Ecryptfs),
+            "enc32" => Some(Format::Enc32),
+            _ => None,
+        }
+    }
 }
 
 impl Default for Format {
@@ -109,6 +122,15 @@ impl MasterKeyType {
             MasterKeyType::User => "user",
         }
     }
+
+    /// The master key type with the given name.
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "trusted" => Some(MasterKeyType::Trusted),
+            "user" => Some(MasterKeyType::User),
+            _ => None,
+        }
+    }
 }
 
 /// The payload for an encrypted key.
@@ -128,6 +150,24 @@ pub enum Payload {
         /// The size of the key to generate.
         keylen: usize,
     },
+    /// Create a new key using the given key material.
+    ///
+    /// Use this with `add_key`. Requires the kernel's `encrypted_keys.user_decrypted_data`
+    /// parameter to be enabled.
+    NewWithData {
+        /// The format of the new key.
+        format: Option<Format>,
+        /// The type of key to use for encrypting the new key.
+        keytype: MasterKeyType,
+        /// The description of the key to use for encrypting the new key.
+        description: Cow<'static, str>,
+        /// The size of the key.
+        ///
+        /// Must be the length of `data`.
+        keylen: usize,
+        /// The decrypted key material.
+        data: Vec<u8>,
+    },
     /// Load a blob into the key.
     ///
     /// Use this with `add_key`.
@@ -149,6 +189,106 @@ pub enum Payload {
     },
 }
 
+/// The key length required for `ecryptfs`-format keys.
+const ECRYPTFS_KEYLEN: usize = 64;
+
+/// Errors found when validating an encrypted key request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+// #[non_exhaustive]
+pub enum ValidationError {
+    /// The description of an `ecryptfs`-format key is not 16 hexadecimal characters.
+    InvalidEcryptfsDescription(String),
+    /// The length of an `ecryptfs`-format key is not 64.
+    InvalidEcryptfsKeyLength(usize),
+    /// The length of the provided key material does not match the key length.
+    InvalidDataLength {
+        /// The length of the key.
+        keylen: usize,
+        /// The length of the provided key material.
+        actual: usize,
+    },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationError::InvalidEcryptfsDescription(desc) => {
+                write!(
+                    f,
+                    "ecryptfs key descriptions must be 16 hexadecimal characters: `{}`",
+                    desc,
+                )
+            },
+            ValidationError::InvalidEcryptfsKeyLength(keylen) => {
+                write!(
+                    f,
+                    "ecryptfs keys must have a length of {}, not {}",
+                    ECRYPTFS_KEYLEN, keylen,
+                )
+            },
+            ValidationError::InvalidDataLength {
+                keylen,
+                actual,
+            } => {
+                write!(
+                    f,
+                    "key material must be {} bytes long, not {} bytes",
+                    keylen, actual,
+                )
+            },
+        }
+    }
+}
+
+impl error::Error for ValidationError {}
+
+impl Payload {
+    /// Check that the payload may be used for a key with the given description.
+    ///
+    /// The kernel rejects `ecryptfs`-format keys with `EINVAL` unless the description is a
+    /// 16-character hexadecimal string and the key length is 64. Provided key material must also
+    /// match the key length. This reports which constraint was violated before attempting to
+    /// create the key.
+    pub fn validate(&self, description: &str) -> Result<(), ValidationError> {
+        let (format, keylen) = match self {
+            Payload::New {
+                format,
+                keylen,
+                ..
+            } => (format, *keylen),
+            Payload::NewWithData {
+                format,
+                keylen,
+                data,
+                ..
+            } => {
+                if data.len() != *keylen {
+                    return Err(ValidationError::InvalidDataLength {
+                        keylen: *keylen,
+                        actual: data.len(),
+                    });
+                }
+
+                (format, *keylen)
+            },
+            _ => return Ok(()),
+        };
+
+        if let Some(Format::Ecryptfs) = format {
+            if !ecryptfs::is_signature(description) {
+                return Err(ValidationError::InvalidEcryptfsDescription(
+                    description.into(),
+                ));
+            }
+            if keylen != ECRYPTFS_KEYLEN {
+                return Err(ValidationError::InvalidEcryptfsKeyLength(keylen));
+            }
+        }
+
+        Ok(())
+    }
+}
+
 impl KeyPayload for Payload {
     fn payload(&self) -> Cow<[u8]> {
         match self {
@@ -166,6 +306,22 @@ impl KeyPayload for Payload {
                     keylen,
                 )
             },
+            Payload::NewWithData {
+                format,
+                keytype,
+                description,
+                keylen,
+                data,
+            } => {
+                format!(
+                    "new {} {}:{} {} {:x}",
+                    format.unwrap_or_default().name(),
+                    keytype.name(),
+                    description,
+                    keylen,
+                    ByteBuf(data),
+                )
+            },
             Payload::Load {
                 blob,
             } => format!("load {:x}", ByteBuf(&blob)),
@@ -178,3 +334,282 @@ impl KeyPayload for Payload {
         .into()
     }
 }
+
+/// The size of the initialization vector in an exported blob.
+const BLOB_IV_SIZE: usize = 16;
+/// The offset of the encrypted key in an exported blob.
+///
+/// The kernel separates the IV from the encrypted key with a zero byte.
+const BLOB_DATA_OFFSET: usize = BLOB_IV_SIZE + 1;
+/// The size of the HMAC in an exported blob.
+const BLOB_HMAC_SIZE: usize = 32;
+
+/// Errors which may occur when parsing an exported blob.
+#[derive(Debug, Clone, PartialEq, Eq)]
+// #[non_exhaustive]
+pub enum BlobError {
+    /// The blob is not valid UTF-8.
+    InvalidUtf8,
+    /// A field is missing from the blob.
+    MissingField(&'static str),
+    /// The key format is not known.
+    UnknownFormat(String),
+    /// The master key reference is invalid.
+    InvalidMasterKey(String),
+    /// The key length is invalid.
+    InvalidLength(String),
+    /// The encrypted data is not valid hexadecimal.
+    InvalidHex,
+    /// The encrypted data is too short to contain the IV and HMAC.
+    Truncated(usize),
+}
+
+impl fmt::Display for BlobError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BlobError::InvalidUtf8 => write!(f, "blob is not valid UTF-8"),
+            BlobError::MissingField(field) => write!(f, "missing field: {}", field),
+            BlobError::UnknownFormat(format) => write!(f, "unknown key format: `{}`", format),
+            BlobError::InvalidMasterKey(master) => {
+                write!(f, "invalid master key reference: `{}`", master)
+            },
+            BlobError::InvalidLength(len) => write!(f, "invalid key length: `{}`", len),
+            BlobError::InvalidHex => write!(f, "encrypted data is not valid hexadecimal"),
+            BlobError::Truncated(len) => {
+                write!(f, "encrypted data is too short ({} bytes)", len)
+            },
+        }
+    }
+}
+
+impl error::Error for BlobError {}
+
+/// An encrypted key as exported by the kernel.
+///
+/// This is the payload read from an encrypted key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Blob {
+    /// The format of the key.
+    pub format: Format,
+    /// The type of key used for encrypting the key.
+    pub keytype: MasterKeyType,
+    /// The description of the key used for encrypting the key.
+    pub description: String,
+    /// The size of the decrypted key.
+    pub keylen: usize,
+    /// The IV, encrypted key, and HMAC.
+    ///
+    /// Use the `iv`, `encrypted_data`, and `hmac` methods to access the parts.
+    pub data: Vec<u8>,
+}
+
+impl Blob {
+    /// Parse an exported blob.
+    ///
+    /// The format is `format key-type:master-key-name keylen hex-data`.
+    pub fn parse(blob: &[u8]) -> Result<Self, BlobError> {
+        let blob = str::from_utf8(blob).map_err(|_| BlobError::InvalidUtf8)?;
+        let mut fields = blob.split_whitespace();
+
+        let format = fields.next().ok_or(BlobError::MissingField("format"))?;
+        let format =
+            Format::from_name(format).ok_or_else(|| BlobError::UnknownFormat(format.into()))?;
+
+        let master = fields.next().ok_or(BlobError::MissingField("master key"))?;
+        let mut master_split = master.splitn(2, ':');
+        let keytype = master_split.next().and_then(MasterKeyType::from_name);
+        let description = master_split.next().filter(|desc| !desc.is_empty());
+        let (keytype, description) = match (keytype, description) {
+            (Some(keytype), Some(description)) => (keytype, description.into()),
+            _ => return Err(BlobError::InvalidMasterKey(master.into())),
+        };
+
+        let keylen = fields.next().ok_or(BlobError::MissingField("key length"))?;
+        let keylen = keylen
+            .parse()
+            .map_err(|_| BlobError::InvalidLength(keylen.into()))?;
+
+        let data = fields.next().ok_or(BlobError::MissingField("data"))?;
+        let data = from_hex(data).ok_or(BlobError::InvalidHex)?;
+        if data.len() < BLOB_DATA_OFFSET + BLOB_HMAC_SIZE {
+            return Err(BlobError::Truncated(data.len()));
+        }
+
+        Ok(Blob {
+            format,
+            keytype,
+            description,
+            keylen,
+            data,
+        })
+    }
+
+    /// The initialization vector used to encrypt the key.
+    pub fn iv(&self) -> &[u8] {
+        &self.data[..BLOB_IV_SIZE]
+    }
+
+    /// The encrypted key.
+    pub fn encrypted_data(&self) -> &[u8] {
+        &self.data[BLOB_DATA_OFFSET..self.data.len() - BLOB_HMAC_SIZE]
+    }
+
+    /// The HMAC of the encrypted key.
+    pub fn hmac(&self) -> &[u8] {
+        &self.data[self.data.len() - BLOB_HMAC_SIZE..]
+    }
+}
+
+impl fmt::Display for Blob {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {}:{} {} {:x}",
+            self.format.name(),
+            self.keytype.name(),
+            self.description,
+            self.keylen,
+            ByteBuf(&self.data),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keytype::*;
+
+    use super::*;
+
+    fn ecryptfs_payload(keylen: usize) -> Payload {
+        Payload::New {
+            format: Some(Format::Ecryptfs),
+            keytype: MasterKeyType::User,
+            description: "master".into(),
+            keylen,
+        }
+    }
+
+    #[test]
+    fn test_validate_ecryptfs() {
+        assert_eq!(ecryptfs_payload(64).validate("0123456789abcdef"), Ok(()));
+        assert_eq!(
+            ecryptfs_payload(64).validate("not-a-signature"),
+            Err(ValidationError::InvalidEcryptfsDescription(
+                "not-a-signature".into(),
+            )),
+        );
+        assert_eq!(
+            ecryptfs_payload(32).validate("0123456789abcdef"),
+            Err(ValidationError::InvalidEcryptfsKeyLength(32)),
+        );
+    }
+
+    #[test]
+    fn test_validate_with_data() {
+        let payload = Payload::NewWithData {
+            format: Some(Format::Ecryptfs),
+            keytype: MasterKeyType::User,
+            description: "master".into(),
+            keylen: 64,
+            data: vec![0; 64],
+        };
+        assert_eq!(payload.validate("0123456789abcdef"), Ok(()));
+
+        let payload = Payload::NewWithData {
+            format: None,
+            keytype: MasterKeyType::User,
+            description: "master".into(),
+            keylen: 32,
+            data: vec![0; 31],
+        };
+        assert_eq!(
+            payload.validate("key"),
+            Err(ValidationError::InvalidDataLength {
+                keylen: 32,
+                actual: 31,
+            }),
+        );
+    }
+
+    #[test]
+    fn test_payload_with_data() {
+        let payload = Payload::NewWithData {
+            format: Some(Format::Enc32),
+            keytype: MasterKeyType::Trusted,
+            description: "kmk".into(),
+            keylen: 4,
+            data: vec![0xde, 0xad, 0xbe, 0xef],
+        };
+        assert_eq!(payload.payload(), &b"new enc32 trusted:kmk 4 deadbeef"[..]);
+    }
+
+    #[test]
+    fn test_blob_parse() {
+        let data = format!(
+            "default user:master 32 {}00{}{}",
+            "01".repeat(BLOB_IV_SIZE),
+            "11".repeat(32),
+            "22".repeat(BLOB_HMAC_SIZE),
+        );
+        let blob = Blob::parse(data.as_bytes()).unwrap();
+
+        assert_eq!(blob.format, Format::Default);
+        assert_eq!(blob.keytype, MasterKeyType::User);
+        assert_eq!(blob.description, "master");
+        assert_eq!(blob.keylen, 32);
+        assert_eq!(blob.iv(), &[1; BLOB_IV_SIZE][..]);
+        assert_eq!(blob.encrypted_data(), &[0x11; 32][..]);
+        assert_eq!(blob.hmac(), &[0x22; BLOB_HMAC_SIZE][..]);
+        assert_eq!(blob.to_string(), data);
+    }
+
+    #[test]
+    fn test_blob_parse_errors() {
+        let hex = "00".repeat(BLOB_DATA_OFFSET + BLOB_HMAC_SIZE);
+
+        assert_eq!(
+            Blob::parse(b"").unwrap_err(),
+            BlobError::MissingField("format"),
+        );
+        assert_eq!(
+            Blob::parse(b"other user:master 32 00").unwrap_err(),
+            BlobError::UnknownFormat("other".into()),
+        );
+        assert_eq!(
+            Blob::parse(b"default logon:master 32 00").unwrap_err(),
+            BlobError::InvalidMasterKey("logon:master".into()),
+        );
+        assert_eq!(
+            Blob::parse(b"default user: 32 00").unwrap_err(),
+            BlobError::InvalidMasterKey("user:".into()),
+        );
+        assert_eq!(
+            Blob::parse(b"default user:master big 00").unwrap_err(),
+            BlobError::InvalidLength("big".into()),
+        );
+        assert_eq!(
+            Blob::parse(b"default user:master 32").unwrap_err(),
+            BlobError::MissingField("data"),
+        );
+        assert_eq!(
+            Blob::parse(b"default user:master 32 0g").unwrap_err(),
+            BlobError::InvalidHex,
+        );
+        assert_eq!(
+            Blob::parse(b"default user:master 32 0000").unwrap_err(),
+            BlobError::Truncated(2),
+        );
+        assert!(Blob::parse(format!("enc32 trusted:kmk 32 {}", hex).as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn test_validate_other_formats() {
+        let payload = Payload::New {
+            format: None,
+            keytype: MasterKeyType::User,
+            description: "master".into(),
+            keylen: 32,
+        };
+        assert_eq!(payload.validate("anything"), Ok(()));
+    }
+}
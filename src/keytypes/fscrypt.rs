@@ -0,0 +1,318 @@
+// Copyright (c) 2026, Ben Boeckel
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of this project nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Filesystem encryption keys
+//!
+//! Keys for filesystems using v1 encryption policies may be provided through `logon` keys (see
+//! `logon::Description::fscrypt`) with a `FscryptKey` payload. Keys for `FS_IOC_ADD_ENCRYPTION_KEY`
+//! may be provisioned using `fscrypt-provisioning` keys.
+
+use std::borrow::Cow;
+use std::error;
+use std::fmt;
+
+use crate::keytype::*;
+
+/// The maximum size of a raw filesystem encryption key.
+const FSCRYPT_MAX_KEY_SIZE: usize = 64;
+/// The minimum size of a raw key in a provisioning payload.
+const FSCRYPT_MIN_KEY_SIZE: usize = 16;
+
+/// Errors which may occur when constructing a filesystem encryption key payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// #[non_exhaustive]
+pub enum PayloadError {
+    /// The raw key has an invalid size.
+    InvalidKeySize {
+        /// The size of the given key.
+        size: usize,
+        /// The minimum size of the key.
+        min: usize,
+        /// The maximum size of the key.
+        max: usize,
+    },
+}
+
+impl fmt::Display for PayloadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PayloadError::InvalidKeySize {
+                size,
+                min,
+                max,
+            } => {
+                write!(
+                    f,
+                    "raw keys must be between {} and {} bytes, not {} bytes",
+                    min, max, size,
+                )
+            },
+        }
+    }
+}
+
+impl error::Error for PayloadError {}
+
+fn check_size(raw: &[u8], min: usize) -> Result<(), PayloadError> {
+    if raw.len() < min || raw.len() > FSCRYPT_MAX_KEY_SIZE {
+        Err(PayloadError::InvalidKeySize {
+            size: raw.len(),
+            min,
+            max: FSCRYPT_MAX_KEY_SIZE,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// The payload of a `logon` key for a v1 encryption policy.
+///
+/// This is `struct fscrypt_key` from `<linux/fscrypt.h>`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct FscryptKey {
+    mode: u32,
+    raw: [u8; FSCRYPT_MAX_KEY_SIZE],
+    size: usize,
+}
+
+impl FscryptKey {
+    /// A payload for the given raw key.
+    ///
+    /// The key must be between 1 and 64 bytes long.
+    pub fn new(raw: &[u8]) -> Result<Self, PayloadError> {
+        check_size(raw, 1)?;
+
+        let mut key = FscryptKey {
+            mode: 0,
+            raw: [0; FSCRYPT_MAX_KEY_SIZE],
+            size: raw.len(),
+        };
+        key.raw[..raw.len()].copy_from_slice(raw);
+
+        Ok(key)
+    }
+
+    /// Set the encryption mode of the key.
+    ///
+    /// The kernel ignores this field; it defaults to 0.
+    pub fn mode(mut self, mode: u32) -> Self {
+        self.mode = mode;
+        self
+    }
+}
+
+impl fmt::Debug for FscryptKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Do not leak the key material into logs.
+        f.debug_struct("FscryptKey")
+            .field("mode", &self.mode)
+            .field("size", &self.size)
+            .finish()
+    }
+}
+
+impl KeyPayload for FscryptKey {
+    fn payload(&self) -> Cow<[u8]> {
+        // The kernel reads the structure in host byte order.
+        //
+        // struct fscrypt_key {
+        //     uint32_t mode;
+        //     uint8_t  raw[64];
+        //     uint32_t size;
+        // };
+        let mut payload = Vec::with_capacity(4 + FSCRYPT_MAX_KEY_SIZE + 4);
+        payload.extend(self.mode.to_ne_bytes().iter());
+        payload.extend(self.raw.iter());
+        payload.extend((self.size as u32).to_ne_bytes().iter());
+
+        payload.into()
+    }
+}
+
+/// Keys provisioned for adding to a filesystem with `FS_IOC_ADD_ENCRYPTION_KEY`.
+///
+/// Unlike `logon` keys, these keys may only be used with the policy version they were created
+/// for.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FscryptProvisioning;
+
+impl KeyType for FscryptProvisioning {
+    /// Provisioning key descriptions are free-form.
+    type Description = str;
+    type Payload = ProvisioningPayload;
+
+    fn name() -> &'static str {
+        "fscrypt-provisioning"
+    }
+}
+
+/// The encryption policy version a key is provisioned for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyType {
+    /// A v1 policy (keys specified by an 8-byte descriptor).
+    V1,
+    /// A v2 policy (keys specified by a 16-byte identifier).
+    V2,
+}
+
+impl PolicyType {
+    fn key_spec_type(self) -> u32 {
+        match self {
+            PolicyType::V1 => 1,
+            PolicyType::V2 => 2,
+        }
+    }
+}
+
+/// The payload of an `fscrypt-provisioning` key.
+///
+/// This is `struct fscrypt_provisioning_key_payload` from the kernel.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ProvisioningPayload {
+    policy: PolicyType,
+    raw: Vec<u8>,
+}
+
+impl ProvisioningPayload {
+    /// A payload for the given raw key.
+    ///
+    /// The key must be between 16 and 64 bytes long.
+    pub fn new(policy: PolicyType, raw: &[u8]) -> Result<Self, PayloadError> {
+        check_size(raw, FSCRYPT_MIN_KEY_SIZE)?;
+
+        Ok(ProvisioningPayload {
+            policy,
+            raw: raw.into(),
+        })
+    }
+}
+
+impl fmt::Debug for ProvisioningPayload {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ProvisioningPayload")
+            .field("policy", &self.policy)
+            .field("size", &self.raw.len())
+            .finish()
+    }
+}
+
+impl KeyPayload for ProvisioningPayload {
+    fn payload(&self) -> Cow<[u8]> {
+        // The kernel reads the structure in host byte order.
+        //
+        // struct fscrypt_provisioning_key_payload {
+        //     uint32_t type;
+        //     uint32_t __reserved;
+        //     uint8_t  raw[];
+        // };
+        let mut payload = Vec::with_capacity(8 + self.raw.len());
+        payload.extend(self.policy.key_spec_type().to_ne_bytes().iter());
+        payload.extend(0u32.to_ne_bytes().iter());
+        payload.extend(self.raw.iter());
+
+        payload.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keytype::*;
+
+    use super::*;
+
+    #[test]
+    fn test_fscrypt_key() {
+        let key = FscryptKey::new(&[0xaa; 32]).unwrap().mode(1);
+        let payload = key.payload();
+
+        assert_eq!(payload.len(), 72);
+        assert_eq!(&payload[..4], &1u32.to_ne_bytes());
+        assert_eq!(&payload[4..36], &[0xaa; 32][..]);
+        assert_eq!(&payload[36..68], &[0; 32][..]);
+        assert_eq!(&payload[68..], &32u32.to_ne_bytes());
+    }
+
+    #[test]
+    fn test_fscrypt_key_size() {
+        assert_eq!(
+            FscryptKey::new(&[]).unwrap_err(),
+            PayloadError::InvalidKeySize {
+                size: 0,
+                min: 1,
+                max: 64,
+            },
+        );
+        assert!(FscryptKey::new(&[0; 64]).is_ok());
+        assert_eq!(
+            FscryptKey::new(&[0; 65]).unwrap_err(),
+            PayloadError::InvalidKeySize {
+                size: 65,
+                min: 1,
+                max: 64,
+            },
+        );
+    }
+
+    #[test]
+    fn test_fscrypt_key_debug() {
+        let key = FscryptKey::new(&[0xaa; 16]).unwrap();
+        assert_eq!(format!("{:?}", key), "FscryptKey { mode: 0, size: 16 }");
+    }
+
+    #[test]
+    fn test_provisioning_payload() {
+        let payload = ProvisioningPayload::new(PolicyType::V2, &[0x55; 16]).unwrap();
+        let data = payload.payload();
+
+        assert_eq!(data.len(), 24);
+        assert_eq!(&data[..4], &2u32.to_ne_bytes());
+        assert_eq!(&data[4..8], &[0; 4]);
+        assert_eq!(&data[8..], &[0x55; 16][..]);
+
+        let payload = ProvisioningPayload::new(PolicyType::V1, &[0x55; 64]).unwrap();
+        assert_eq!(&payload.payload()[..4], &1u32.to_ne_bytes());
+    }
+
+    #[test]
+    fn test_provisioning_payload_size() {
+        assert_eq!(
+            ProvisioningPayload::new(PolicyType::V2, &[0; 15]).unwrap_err(),
+            PayloadError::InvalidKeySize {
+                size: 15,
+                min: 16,
+                max: 64,
+            },
+        );
+        assert_eq!(
+            ProvisioningPayload::new(PolicyType::V2, &[0; 65]).unwrap_err(),
+            PayloadError::InvalidKeySize {
+                size: 65,
+                min: 16,
+                max: 64,
+            },
+        );
+    }
+}
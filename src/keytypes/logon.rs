@@ -32,6 +32,7 @@ use std::borrow::Cow;
 
 use keyutils_raw::KEY_TYPE_LOGON;
 
+use super::ByteBuf;
 use crate::keytype::*;
 
 /// Keys which can only be created and updated from userspace but not read back.
@@ -58,8 +59,33 @@ pub struct Description {
     pub description: Cow<'static, str>,
 }
 
+impl Description {
+    /// The description of a key for filesystem encryption using a v1 policy.
+    ///
+    /// The payload of such keys should be built using `fscrypt::FscryptKey`.
+    pub fn fscrypt(descriptor: [u8; 8]) -> Self {
+        Description {
+            subtype: "fscrypt".into(),
+            description: format!("{:x}", ByteBuf(&descriptor)).into(),
+        }
+    }
+}
+
 impl KeyDescription for Description {
     fn description(&self) -> Cow<str> {
         format!("{}:{}", self.subtype, self.description).into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::keytype::*;
+
+    use super::*;
+
+    #[test]
+    fn test_fscrypt_description() {
+        let desc = Description::fscrypt([0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef]);
+        assert_eq!(desc.description(), "fscrypt:0123456789abcdef");
+    }
+}
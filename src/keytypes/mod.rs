@@ -43,15 +43,23 @@ pub use self::blacklist::Blacklist;
 pub mod dns_resolver;
 pub use self::dns_resolver::DnsResolver;
 
+pub mod ecryptfs;
+
 pub mod encrypted;
 pub use self::encrypted::Encrypted;
 
+pub mod fscrypt;
+pub use self::fscrypt::FscryptProvisioning;
+
 pub mod keyring;
 pub use self::keyring::Keyring;
 
 pub mod logon;
 pub use self::logon::Logon;
 
+pub mod pkcs7_test;
+pub use self::pkcs7_test::Pkcs7Test;
+
 pub mod rxrpc;
 pub use self::rxrpc::RxRPC;
 
@@ -76,9 +84,21 @@ impl fmt::LowerHex for ByteBuf<'_> {
     }
 }
 
+/// Decode a hexadecimal string into bytes.
+pub(crate) fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 == 1 || !hex.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
-    use super::ByteBuf;
+    use super::{from_hex, ByteBuf};
 
     fn check(input: &[u8], expected: &str) {
         assert_eq!(format!("{:x}", ByteBuf(input)), expected);
@@ -90,4 +110,15 @@ mod tests {
         check(&[0, 1], "0001");
         check(&[222, 173, 190, 239], "deadbeef");
     }
+
+    #[test]
+    fn test_from_hex() {
+        assert_eq!(from_hex(""), Some(Vec::new()));
+        assert_eq!(from_hex("0001"), Some(vec![0, 1]));
+        assert_eq!(from_hex("DEADbeef"), Some(vec![222, 173, 190, 239]));
+        assert_eq!(from_hex("abc"), None);
+        assert_eq!(from_hex("zz"), None);
+        assert_eq!(from_hex("+1"), None);
+        assert_eq!(from_hex("é0"), None);
+    }
 }
@@ -0,0 +1,372 @@
+// Copyright (c) 2026, Ben Boeckel
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of this project nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! PKCS#7 test keys
+//!
+//! The kernel verifies the signature of a `pkcs7_test` key's payload against the system trusted
+//! keyrings when it is instantiated, the same way it verifies module and firmware signatures. The
+//! key then holds the signed content. The type is only available with `CONFIG_PKCS7_TEST_KEY`.
+
+use std::borrow::Borrow;
+use std::error;
+use std::fmt;
+
+use crate::keytype::*;
+use crate::{der, pem};
+use crate::{Key, Keyring, Result};
+
+/// Keys which verify a PKCS#7 message when they are created.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Pkcs7Test;
+
+impl KeyType for Pkcs7Test {
+    /// PKCS#7 test key descriptions are free-form.
+    type Description = str;
+    /// PKCS#7 test key payloads are PKCS#7 `SignedData` messages with embedded content. See
+    /// `Payload` to check the message beforehand.
+    type Payload = [u8];
+
+    fn name() -> &'static str {
+        "pkcs7_test"
+    }
+}
+
+/// Errors which may occur when reading a PKCS#7 message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+// #[non_exhaustive]
+pub enum PayloadError {
+    /// The input looked like PEM, but could not be decoded.
+    InvalidPem,
+    /// The PEM block has a label other than `PKCS7`.
+    UnsupportedPemLabel(String),
+    /// The data is not a DER-encoded PKCS#7 message.
+    InvalidMessage,
+    /// The message is not a `SignedData` message.
+    NotSignedData,
+    /// The message does not embed the signed content.
+    ///
+    /// The kernel only verifies detached signatures when the content is provided separately
+    /// (e.g., for modules), which is not possible through this key type.
+    DetachedContent,
+}
+
+impl fmt::Display for PayloadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PayloadError::InvalidPem => write!(f, "invalid PEM data"),
+            PayloadError::UnsupportedPemLabel(label) => {
+                write!(f, "unsupported PEM label: {}", label)
+            },
+            PayloadError::InvalidMessage => write!(f, "invalid PKCS#7 message"),
+            PayloadError::NotSignedData => write!(f, "not a PKCS#7 signed data message"),
+            PayloadError::DetachedContent => {
+                write!(f, "the PKCS#7 message does not contain the signed content")
+            },
+        }
+    }
+}
+
+impl error::Error for PayloadError {}
+
+/// `signedData` (1.2.840.113549.1.7.2)
+const OID_SIGNED_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02];
+
+/// The PEM label used for PKCS#7 messages.
+const PEM_LABEL: &str = "PKCS7";
+
+/// A payload for a PKCS#7 test key.
+///
+/// The message is checked to be a `SignedData` message with embedded content before it is handed
+/// to the kernel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Payload {
+    der: Vec<u8>,
+    content: Vec<u8>,
+}
+
+impl Payload {
+    /// Read a payload from either PEM or DER data.
+    pub fn parse(data: &[u8]) -> std::result::Result<Self, PayloadError> {
+        if pem::is_pem(data) {
+            Self::from_pem(data)
+        } else {
+            Self::from_der(data)
+        }
+    }
+
+    /// Read a payload from a PEM block.
+    ///
+    /// Only the first PEM block in the input is used.
+    pub fn from_pem(data: &[u8]) -> std::result::Result<Self, PayloadError> {
+        let pem = pem::parse(data).ok_or(PayloadError::InvalidPem)?;
+        if pem.label != PEM_LABEL {
+            return Err(PayloadError::UnsupportedPemLabel(pem.label));
+        }
+
+        Self::from_der(&pem.contents)
+    }
+
+    /// Read a payload from DER data.
+    pub fn from_der(data: &[u8]) -> std::result::Result<Self, PayloadError> {
+        // ContentInfo ::= SEQUENCE { contentType, content [0] EXPLICIT }
+        let (info, rest) =
+            der::expect(data, der::TAG_SEQUENCE).ok_or(PayloadError::InvalidMessage)?;
+        if !rest.is_empty() {
+            return Err(PayloadError::InvalidMessage);
+        }
+        let (content_type, rest) =
+            der::expect(info.contents, der::TAG_OID).ok_or(PayloadError::InvalidMessage)?;
+        if content_type.contents != OID_SIGNED_DATA {
+            return Err(PayloadError::NotSignedData);
+        }
+        let (content, _) =
+            der::expect(rest, der::tag_context(0)).ok_or(PayloadError::InvalidMessage)?;
+        let (signed_data, _) =
+            der::expect(content.contents, der::TAG_SEQUENCE).ok_or(PayloadError::InvalidMessage)?;
+
+        let content = Self::encapsulated_content(signed_data.contents)
+            .ok_or(PayloadError::InvalidMessage)?
+            .ok_or(PayloadError::DetachedContent)?;
+
+        Ok(Payload {
+            der: data.into(),
+            content: content.into(),
+        })
+    }
+
+    /// Find the embedded content of a `SignedData` message.
+    ///
+    /// Returns `Some(None)` if the content is detached.
+    fn encapsulated_content(signed_data: &[u8]) -> Option<Option<&[u8]>> {
+        // SignedData ::= SEQUENCE { version, digestAlgorithms, encapContentInfo, ... }
+        let (_, rest) = der::expect(signed_data, der::TAG_INTEGER)?;
+        let (_, rest) = der::expect(rest, der::TAG_SET)?;
+        // EncapsulatedContentInfo ::= SEQUENCE { eContentType, eContent [0] EXPLICIT OPTIONAL }
+        let (encap, _) = der::expect(rest, der::TAG_SEQUENCE)?;
+        let (_, rest) = der::expect(encap.contents, der::TAG_OID)?;
+        if rest.is_empty() {
+            return Some(None);
+        }
+
+        let (econtent, _) = der::expect(rest, der::tag_context(0))?;
+        // The kernel uses the contents of the octet string as the signed data.
+        let (data, _) = der::expect(econtent.contents, der::TAG_OCTET_STRING)?;
+        Some(Some(data.contents))
+    }
+
+    /// The DER encoding of the message.
+    pub fn der(&self) -> &[u8] {
+        &self.der
+    }
+
+    /// The signed content embedded in the message.
+    ///
+    /// This is the payload of the key once the kernel has verified the message.
+    pub fn content(&self) -> &[u8] {
+        &self.content
+    }
+}
+
+impl Borrow<[u8]> for Payload {
+    fn borrow(&self) -> &[u8] {
+        &self.der
+    }
+}
+
+/// The result of verifying a PKCS#7 message with the kernel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+// #[non_exhaustive]
+pub enum Verification {
+    /// The signature is valid.
+    ///
+    /// The key holds the signed content.
+    Verified(Key),
+    /// The signature does not match the content or the signing key is blacklisted
+    /// (`EKEYREJECTED`).
+    Rejected,
+    /// None of the signers are in the system trusted keyrings (`ENOKEY`).
+    UntrustedSigner,
+    /// The kernel could not parse the message (`EBADMSG`).
+    Malformed,
+}
+
+impl Verification {
+    /// Whether the message was verified.
+    pub fn is_verified(&self) -> bool {
+        self.key().is_some()
+    }
+
+    /// The key holding the signed content if the message was verified.
+    pub fn key(&self) -> Option<&Key> {
+        match self {
+            Verification::Verified(key) => Some(key),
+            _ => None,
+        }
+    }
+
+    /// The verification result the kernel reports using an error.
+    fn from_error(err: errno::Errno) -> Option<Self> {
+        match err.0 {
+            libc::EKEYREJECTED => Some(Verification::Rejected),
+            libc::ENOKEY => Some(Verification::UntrustedSigner),
+            libc::EBADMSG => Some(Verification::Malformed),
+            _ => None,
+        }
+    }
+}
+
+/// Verify a PKCS#7 message by adding it as a `pkcs7_test` key to a keyring.
+///
+/// Errors which indicate a verification failure are reported in the result while any other error
+/// (e.g., `ENODEV` if the kernel does not support the key type) is returned as-is.
+pub fn verify(keyring: &mut Keyring, description: &str, payload: &Payload) -> Result<Verification> {
+    match keyring.add_key::<Pkcs7Test, _, _>(description, payload.der()) {
+        Ok(key) => Ok(Verification::Verified(key)),
+        Err(err) => Verification::from_error(err).ok_or(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT_TYPE_DATA: &[u8] = &[
+        0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x01, // data
+    ];
+
+    fn wrap(tag: u8, contents: &[u8]) -> Vec<u8> {
+        assert!(contents.len() < 0x80);
+        let mut der = vec![tag, contents.len() as u8];
+        der.extend_from_slice(contents);
+        der
+    }
+
+    fn message(content_type: &[u8], econtent: Option<&[u8]>) -> Vec<u8> {
+        let mut encap = CONTENT_TYPE_DATA.to_vec();
+        if let Some(econtent) = econtent {
+            encap.extend(wrap(
+                der::tag_context(0),
+                &wrap(der::TAG_OCTET_STRING, econtent),
+            ));
+        }
+
+        let mut signed_data = vec![0x02, 0x01, 0x01]; // version
+        signed_data.extend(wrap(der::TAG_SET, &[])); // digestAlgorithms
+        signed_data.extend(wrap(der::TAG_SEQUENCE, &encap)); // encapContentInfo
+        signed_data.extend(wrap(der::TAG_SET, &[])); // signerInfos
+
+        let mut info = wrap(der::TAG_OID, content_type);
+        info.extend(wrap(
+            der::tag_context(0),
+            &wrap(der::TAG_SEQUENCE, &signed_data),
+        ));
+        wrap(der::TAG_SEQUENCE, &info)
+    }
+
+    #[test]
+    fn test_name() {
+        assert_eq!(Pkcs7Test::name(), "pkcs7_test");
+    }
+
+    #[test]
+    fn test_payload_der() {
+        let der = message(OID_SIGNED_DATA, Some(b"signed content"));
+        let payload = Payload::parse(&der).unwrap();
+        assert_eq!(payload.der(), &der[..]);
+        assert_eq!(payload.content(), b"signed content");
+        assert_eq!(Borrow::<[u8]>::borrow(&payload), &der[..]);
+
+        let payload = Payload::parse(&message(OID_SIGNED_DATA, Some(b""))).unwrap();
+        assert_eq!(payload.content(), b"");
+    }
+
+    #[test]
+    fn test_payload_pem() {
+        let der = message(OID_SIGNED_DATA, Some(b"signed content"));
+        let pem = pem::encode("PKCS7", &der);
+        let payload = Payload::parse(pem.as_bytes()).unwrap();
+        assert_eq!(payload.der(), &der[..]);
+
+        let pem = pem::encode("CERTIFICATE", &der);
+        assert_eq!(
+            Payload::parse(pem.as_bytes()).unwrap_err(),
+            PayloadError::UnsupportedPemLabel("CERTIFICATE".into()),
+        );
+        assert_eq!(
+            Payload::parse(b"-----BEGIN PKCS7-----\n!!\n-----END PKCS7-----\n").unwrap_err(),
+            PayloadError::InvalidPem,
+        );
+    }
+
+    #[test]
+    fn test_payload_invalid() {
+        assert_eq!(
+            Payload::parse(&[]).unwrap_err(),
+            PayloadError::InvalidMessage,
+        );
+
+        let der = message(OID_SIGNED_DATA, Some(b"signed content"));
+        assert_eq!(
+            Payload::parse(&der[..der.len() - 1]).unwrap_err(),
+            PayloadError::InvalidMessage,
+        );
+        let mut trailing = der.clone();
+        trailing.push(0);
+        assert_eq!(
+            Payload::parse(&trailing).unwrap_err(),
+            PayloadError::InvalidMessage,
+        );
+
+        let enveloped_data = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x03];
+        assert_eq!(
+            Payload::parse(&message(enveloped_data, Some(b"content"))).unwrap_err(),
+            PayloadError::NotSignedData,
+        );
+        assert_eq!(
+            Payload::parse(&message(OID_SIGNED_DATA, None)).unwrap_err(),
+            PayloadError::DetachedContent,
+        );
+    }
+
+    #[test]
+    fn test_verification_from_error() {
+        assert_eq!(
+            Verification::from_error(errno::Errno(libc::EKEYREJECTED)),
+            Some(Verification::Rejected),
+        );
+        assert_eq!(
+            Verification::from_error(errno::Errno(libc::ENOKEY)),
+            Some(Verification::UntrustedSigner),
+        );
+        assert_eq!(
+            Verification::from_error(errno::Errno(libc::EBADMSG)),
+            Some(Verification::Malformed),
+        );
+        assert_eq!(Verification::from_error(errno::Errno(libc::ENODEV)), None);
+        assert!(!Verification::Rejected.is_verified());
+        assert_eq!(Verification::Malformed.key(), None);
+    }
+}
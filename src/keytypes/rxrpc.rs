@@ -27,6 +27,8 @@
 //! Keys for RxRPC clients.
 
 use std::borrow::Cow;
+use std::error;
+use std::fmt;
 
 use crate::keytype::*;
 
@@ -44,37 +46,476 @@ impl KeyType for RxRPC {
     }
 }
 
+/// The security index of rxkad tokens.
+const RXRPC_SECURITY_RXKAD: u32 = 2;
+/// The security index of yfs-rxgk tokens.
+const RXRPC_SECURITY_YFS_RXGK: u32 = 6;
+/// The maximum length of a cell name.
+const AFSTOKEN_CELL_MAX: usize = 64;
+/// The maximum number of tokens in a key.
+const AFSTOKEN_MAX: usize = 8;
+/// The maximum length of an rxkad ticket.
+const AFSTOKEN_RK_TIX_MAX: usize = 12000;
+
+/// Errors which may occur when constructing an RxRPC client payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+// #[non_exhaustive]
+pub enum PayloadError {
+    /// The cell name must be 1 to 64 printable ASCII characters.
+    InvalidCellName(String),
+    /// A key must contain between 1 and 8 tokens.
+    InvalidTokenCount(usize),
+    /// An rxkad ticket may be at most 12000 bytes.
+    TicketTooLong(usize),
+}
+
+impl fmt::Display for PayloadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PayloadError::InvalidCellName(cell) => write!(f, "invalid cell name: `{}`", cell),
+            PayloadError::InvalidTokenCount(count) => {
+                write!(
+                    f,
+                    "a key must have between 1 and {} tokens, not {}",
+                    AFSTOKEN_MAX, count,
+                )
+            },
+            PayloadError::TicketTooLong(len) => {
+                write!(
+                    f,
+                    "rxkad tickets may be at most {} bytes, not {}",
+                    AFSTOKEN_RK_TIX_MAX, len,
+                )
+            },
+        }
+    }
+}
+
+impl error::Error for PayloadError {}
+
+/// A writer for XDR-encoded data.
+///
+/// XDR encodes all values in big-endian order and pads everything to a multiple of 4 bytes.
+#[derive(Debug, Default)]
+struct XdrWriter {
+    data: Vec<u8>,
+}
+
+impl XdrWriter {
+    fn u32(&mut self, value: u32) {
+        self.data.extend(value.to_be_bytes().iter());
+    }
+
+    fn i64(&mut self, value: i64) {
+        self.data.extend(value.to_be_bytes().iter());
+    }
+
+    fn fixed(&mut self, data: &[u8]) {
+        self.data.extend(data.iter());
+        let padding = (4 - data.len() % 4) % 4;
+        self.data.extend([0; 3][..padding].iter());
+    }
+
+    fn opaque(&mut self, data: &[u8]) {
+        self.u32(data.len() as u32);
+        self.fixed(data);
+    }
+}
+
+/// A legacy rxkad token.
+///
+/// This is the original (version 1) key format which holds a single Kerberos 4 style ticket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct V1Token {
+    /// The time at which the token expires (in seconds since the epoch).
+    pub expiry: u32,
+    /// The key version number.
+    pub kvno: u8,
+    /// The DES session key.
+    pub session_key: [u8; 8],
+    /// The encrypted ticket.
+    pub ticket: Vec<u8>,
+}
+
+/// An rxkad token for the XDR payload format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RxkadToken {
+    /// The AFS ID of the user.
+    pub vice_id: u32,
+    /// The key version number.
+    pub kvno: u32,
+    /// The DES session key.
+    pub session_key: [u8; 8],
+    /// The time at which the token becomes valid (in seconds since the epoch).
+    pub start: u32,
+    /// The time at which the token expires (in seconds since the epoch).
+    pub expiry: u32,
+    /// Whether this is the primary token for the cell.
+    pub primary: bool,
+    /// The encrypted ticket.
+    pub ticket: Vec<u8>,
+}
+
+impl RxkadToken {
+    fn encode(&self, xdr: &mut XdrWriter) {
+        xdr.u32(RXRPC_SECURITY_RXKAD);
+        xdr.u32(self.vice_id);
+        xdr.u32(self.kvno);
+        xdr.fixed(&self.session_key);
+        xdr.u32(self.start);
+        xdr.u32(self.expiry);
+        xdr.u32(self.primary as u32);
+        xdr.opaque(&self.ticket);
+    }
+}
+
+/// The level of protection applied to RxRPC packets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecurityLevel {
+    /// Packets are neither authenticated nor encrypted.
+    Plain,
+    /// Packets are authenticated.
+    Authenticated,
+    /// Packets are authenticated and encrypted.
+    Encrypted,
+}
+
+impl SecurityLevel {
+    fn value(self) -> i64 {
+        match self {
+            SecurityLevel::Plain => 0,
+            SecurityLevel::Authenticated => 1,
+            SecurityLevel::Encrypted => 2,
+        }
+    }
+}
+
+/// A yfs-rxgk token for the XDR payload format.
+///
+/// Requires Linux 5.11+. Times are given in 100 nanosecond units since the epoch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct YfsRxgkToken {
+    /// The time at which the token becomes valid.
+    pub begin_time: i64,
+    /// The time at which the token expires (0 for no expiration).
+    pub end_time: i64,
+    /// The security level of the connection.
+    pub level: SecurityLevel,
+    /// The maximum lifetime of a connection key (in seconds).
+    pub lifetime: i64,
+    /// The maximum number of bytes (as a power of 2) which may be sent using a connection key.
+    pub bytelife: i64,
+    /// The Kerberos encryption type of the key.
+    pub enctype: u32,
+    /// The token's session key.
+    pub key: Vec<u8>,
+    /// The encrypted ticket.
+    pub ticket: Vec<u8>,
+}
+
+impl YfsRxgkToken {
+    fn encode(&self, xdr: &mut XdrWriter) {
+        xdr.u32(RXRPC_SECURITY_YFS_RXGK);
+        xdr.i64(self.begin_time);
+        xdr.i64(self.end_time);
+        xdr.i64(self.level.value());
+        xdr.i64(self.lifetime);
+        xdr.i64(self.bytelife);
+        xdr.i64(self.enctype.into());
+        xdr.opaque(&self.key);
+        xdr.opaque(&self.ticket);
+    }
+}
+
+/// A token in the XDR payload format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+// #[non_exhaustive]
+pub enum Token {
+    /// An rxkad token.
+    Rxkad(RxkadToken),
+    /// A yfs-rxgk token.
+    YfsRxgk(YfsRxgkToken),
+}
+
+impl Token {
+    fn encode(&self, xdr: &mut XdrWriter) {
+        let mut token = XdrWriter::default();
+        match self {
+            Token::Rxkad(rxkad) => rxkad.encode(&mut token),
+            Token::YfsRxgk(rxgk) => rxgk.encode(&mut token),
+        }
+        xdr.opaque(&token.data);
+    }
+}
+
+impl From<RxkadToken> for Token {
+    fn from(token: RxkadToken) -> Self {
+        Token::Rxkad(token)
+    }
+}
+
+impl From<YfsRxgkToken> for Token {
+    fn from(token: YfsRxgkToken) -> Self {
+        Token::YfsRxgk(token)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Format {
+    V1(V1Token),
+    Xdr { cell: String, tokens: Vec<Token> },
+}
+
 /// The payload for RxRPC client keys.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Payload {
-    expiry: u32,
-    version: u8,
-    session_key: [u8; 8],
-    ticket: Vec<u8>,
+    format: Format,
+}
+
+impl Payload {
+    /// A payload in the legacy (version 1) format.
+    pub fn v1(token: V1Token) -> Result<Self, PayloadError> {
+        if token.ticket.len() > AFSTOKEN_RK_TIX_MAX {
+            return Err(PayloadError::TicketTooLong(token.ticket.len()));
+        }
+
+        Ok(Payload {
+            format: Format::V1(token),
+        })
+    }
+
+    /// A payload in the XDR format holding tokens for the given cell.
+    pub fn xdr<C, I>(cell: C, tokens: I) -> Result<Self, PayloadError>
+    where
+        C: Into<String>,
+        I: IntoIterator,
+        I::Item: Into<Token>,
+    {
+        let cell = cell.into();
+        let cell_valid = !cell.is_empty()
+            && cell.len() <= AFSTOKEN_CELL_MAX
+            && cell.bytes().all(|c| c.is_ascii_graphic() || c == b' ');
+        if !cell_valid {
+            return Err(PayloadError::InvalidCellName(cell));
+        }
+
+        let tokens = tokens.into_iter().map(Into::into).collect::<Vec<_>>();
+        if tokens.is_empty() || tokens.len() > AFSTOKEN_MAX {
+            return Err(PayloadError::InvalidTokenCount(tokens.len()));
+        }
+        for token in &tokens {
+            if let Token::Rxkad(rxkad) = token {
+                if rxkad.ticket.len() > AFSTOKEN_RK_TIX_MAX {
+                    return Err(PayloadError::TicketTooLong(rxkad.ticket.len()));
+                }
+            }
+        }
+
+        Ok(Payload {
+            format: Format::Xdr {
+                cell,
+                tokens,
+            },
+        })
+    }
 }
 
 impl KeyPayload for Payload {
     fn payload(&self) -> Cow<[u8]> {
-        let mut payload = Vec::new();
-
-        // struct rxrpc_key_sec2_v1 {
-        //     uint16_t    security_index; /* 2 */
-        //     uint16_t    ticket_length;  /* length of ticket[] */
-        //     uint32_t    expiry;         /* time at which expires */
-        //     uint8_t     kvno;           /* key version number */
-        //     uint8_t     __pad[3];
-        //     uint8_t     session_key[8]; /* DES session key */
-        //     uint8_t     ticket[0];      /* the encrypted ticket */
-        // };
-
-        payload.extend((2 as u16).to_ne_bytes().iter());
-        payload.extend((self.ticket.len() as u16).to_ne_bytes().iter());
-        payload.extend(self.expiry.to_ne_bytes().iter());
-        payload.extend(self.version.to_ne_bytes().iter());
-        payload.extend([0u8; 3].iter());
-        payload.extend(self.session_key.iter());
-        payload.extend(self.ticket.iter());
-
-        payload.into()
+        match &self.format {
+            Format::V1(token) => {
+                let mut payload = Vec::new();
+
+                // The kernel reads the version 1 format in host byte order.
+                //
+                // uint32_t kver; /* 1 */
+                // struct rxrpc_key_sec2_v1 {
+                //     uint16_t    security_index; /* 2 */
+                //     uint16_t    ticket_length;  /* length of ticket[] */
+                //     uint32_t    expiry;         /* time at which expires */
+                //     uint8_t     kvno;           /* key version number */
+                //     uint8_t     __pad[3];
+                //     uint8_t     session_key[8]; /* DES session key */
+                //     uint8_t     ticket[0];      /* the encrypted ticket */
+                // };
+
+                payload.extend(1u32.to_ne_bytes().iter());
+                payload.extend((RXRPC_SECURITY_RXKAD as u16).to_ne_bytes().iter());
+                payload.extend((token.ticket.len() as u16).to_ne_bytes().iter());
+                payload.extend(token.expiry.to_ne_bytes().iter());
+                payload.push(token.kvno);
+                payload.extend([0u8; 3].iter());
+                payload.extend(token.session_key.iter());
+                payload.extend(token.ticket.iter());
+
+                payload.into()
+            },
+            Format::Xdr {
+                cell,
+                tokens,
+            } => {
+                let mut xdr = XdrWriter::default();
+
+                // Flags (must be zero).
+                xdr.u32(0);
+                xdr.opaque(cell.as_bytes());
+                xdr.u32(tokens.len() as u32);
+                for token in tokens {
+                    token.encode(&mut xdr);
+                }
+
+                xdr.data.into()
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keytype::*;
+
+    use super::*;
+
+    fn rxkad_token() -> RxkadToken {
+        RxkadToken {
+            vice_id: 0x0102_0304,
+            kvno: 5,
+            session_key: [0xa0, 0xa1, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7],
+            start: 0x1000,
+            expiry: 0x2000,
+            primary: true,
+            ticket: vec![0xee; 5],
+        }
+    }
+
+    #[test]
+    fn test_v1_payload() {
+        let payload = Payload::v1(V1Token {
+            expiry: 0x1234_5678,
+            kvno: 3,
+            session_key: [1, 2, 3, 4, 5, 6, 7, 8],
+            ticket: vec![0xff, 0xfe],
+        })
+        .unwrap();
+
+        let mut expected = Vec::new();
+        expected.extend(1u32.to_ne_bytes().iter());
+        expected.extend(2u16.to_ne_bytes().iter());
+        expected.extend(2u16.to_ne_bytes().iter());
+        expected.extend(0x1234_5678u32.to_ne_bytes().iter());
+        expected.extend([3, 0, 0, 0].iter());
+        expected.extend([1, 2, 3, 4, 5, 6, 7, 8].iter());
+        expected.extend([0xff, 0xfe].iter());
+
+        assert_eq!(payload.payload(), expected.as_slice());
+    }
+
+    #[test]
+    fn test_v1_ticket_too_long() {
+        let err = Payload::v1(V1Token {
+            expiry: 0,
+            kvno: 0,
+            session_key: [0; 8],
+            ticket: vec![0; 12001],
+        })
+        .unwrap_err();
+        assert_eq!(err, PayloadError::TicketTooLong(12001));
+    }
+
+    #[test]
+    fn test_xdr_rxkad_payload() {
+        let payload = Payload::xdr("cell", vec![rxkad_token()]).unwrap();
+
+        #[rustfmt::skip]
+        let expected: &[u8] = &[
+            0, 0, 0, 0, // flags
+            0, 0, 0, 4, b'c', b'e', b'l', b'l', // cell
+            0, 0, 0, 1, // ntoken
+            0, 0, 0, 44, // toklen
+            0, 0, 0, 2, // security index
+            1, 2, 3, 4, // vice_id
+            0, 0, 0, 5, // kvno
+            0xa0, 0xa1, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7, // session key
+            0, 0, 0x10, 0, // start
+            0, 0, 0x20, 0, // expiry
+            0, 0, 0, 1, // primary
+            0, 0, 0, 5, 0xee, 0xee, 0xee, 0xee, 0xee, 0, 0, 0, // ticket
+        ];
+
+        assert_eq!(payload.payload(), expected);
+    }
+
+    #[test]
+    fn test_xdr_yfs_rxgk_payload() {
+        let token = YfsRxgkToken {
+            begin_time: 1,
+            end_time: 0,
+            level: SecurityLevel::Encrypted,
+            lifetime: 3600,
+            bytelife: 30,
+            enctype: 18,
+            key: vec![0x11; 2],
+            ticket: vec![0x22; 4],
+        };
+        let payload = Payload::xdr("c", vec![token]).unwrap();
+
+        #[rustfmt::skip]
+        let expected: &[u8] = &[
+            0, 0, 0, 0, // flags
+            0, 0, 0, 1, b'c', 0, 0, 0, // cell
+            0, 0, 0, 1, // ntoken
+            0, 0, 0, 68, // toklen
+            0, 0, 0, 6, // security index
+            0, 0, 0, 0, 0, 0, 0, 1, // begintime
+            0, 0, 0, 0, 0, 0, 0, 0, // endtime
+            0, 0, 0, 0, 0, 0, 0, 2, // level
+            0, 0, 0, 0, 0, 0, 0x0e, 0x10, // lifetime
+            0, 0, 0, 0, 0, 0, 0, 30, // bytelife
+            0, 0, 0, 0, 0, 0, 0, 18, // enctype
+            0, 0, 0, 2, 0x11, 0x11, 0, 0, // key
+            0, 0, 0, 4, 0x22, 0x22, 0x22, 0x22, // ticket
+        ];
+
+        assert_eq!(payload.payload(), expected);
+    }
+
+    #[test]
+    fn test_xdr_multiple_tokens() {
+        let payload = Payload::xdr("cell", vec![rxkad_token(), rxkad_token()]).unwrap();
+        let data = payload.payload();
+        assert_eq!(&data[12..16], &[0, 0, 0, 2]);
+        assert_eq!(data.len(), 16 + 2 * 48);
+    }
+
+    #[test]
+    fn test_xdr_invalid() {
+        assert_eq!(
+            Payload::xdr("", vec![rxkad_token()]).unwrap_err(),
+            PayloadError::InvalidCellName(String::new()),
+        );
+        assert_eq!(
+            Payload::xdr("bad\ncell", vec![rxkad_token()]).unwrap_err(),
+            PayloadError::InvalidCellName("bad\ncell".into()),
+        );
+        let long_cell = "c".repeat(65);
+        assert_eq!(
+            Payload::xdr(long_cell.clone(), vec![rxkad_token()]).unwrap_err(),
+            PayloadError::InvalidCellName(long_cell),
+        );
+        assert_eq!(
+            Payload::xdr("cell", Vec::<Token>::new()).unwrap_err(),
+            PayloadError::InvalidTokenCount(0),
+        );
+        assert_eq!(
+            Payload::xdr("cell", vec![rxkad_token(); 9]).unwrap_err(),
+            PayloadError::InvalidTokenCount(9),
+        );
+
+        let mut token = rxkad_token();
+        token.ticket = vec![0; 12001];
+        assert_eq!(
+            Payload::xdr("cell", vec![token]).unwrap_err(),
+            PayloadError::TicketTooLong(12001),
+        );
     }
 }
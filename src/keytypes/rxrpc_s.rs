@@ -27,6 +27,8 @@
 //! RxRPC server keys
 
 use std::borrow::Cow;
+use std::error;
+use std::fmt;
 
 use crate::keytype::*;
 
@@ -43,6 +45,59 @@ impl KeyType for RxRPCServer {
     }
 }
 
+/// Kerberos encryption types supported for rxgk server keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// #[non_exhaustive]
+pub enum Enctype {
+    /// `aes128-cts-hmac-sha1-96`
+    Aes128CtsHmacSha1_96,
+    /// `aes256-cts-hmac-sha1-96`
+    Aes256CtsHmacSha1_96,
+    /// `aes128-cts-hmac-sha256-128`
+    Aes128CtsHmacSha256_128,
+    /// `aes256-cts-hmac-sha384-192`
+    Aes256CtsHmacSha384_192,
+    /// `camellia128-cts-cmac`
+    Camellia128CtsCmac,
+    /// `camellia256-cts-cmac`
+    Camellia256CtsCmac,
+}
+
+impl Enctype {
+    /// The Kerberos number for the encryption type.
+    pub fn number(self) -> u32 {
+        match self {
+            Enctype::Aes128CtsHmacSha1_96 => 17,
+            Enctype::Aes256CtsHmacSha1_96 => 18,
+            Enctype::Aes128CtsHmacSha256_128 => 19,
+            Enctype::Aes256CtsHmacSha384_192 => 20,
+            Enctype::Camellia128CtsCmac => 25,
+            Enctype::Camellia256CtsCmac => 26,
+        }
+    }
+
+    /// The length of keys for the encryption type.
+    pub fn key_len(self) -> usize {
+        match self {
+            Enctype::Aes128CtsHmacSha1_96
+            | Enctype::Aes128CtsHmacSha256_128
+            | Enctype::Camellia128CtsCmac => 16,
+            Enctype::Aes256CtsHmacSha1_96
+            | Enctype::Aes256CtsHmacSha384_192
+            | Enctype::Camellia256CtsCmac => 32,
+        }
+    }
+}
+
+/// The key identification used by rxgk server keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RxgkKeyId {
+    /// The key version number.
+    pub kvno: u32,
+    /// The encryption type of the key.
+    pub enctype: Enctype,
+}
+
 /// The description of an RxRPC server key.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Description {
@@ -50,18 +105,154 @@ pub struct Description {
     pub service_id: u16,
     /// The security index.
     pub security_index: u8,
+    /// The key identification for rxgk keys.
+    ///
+    /// Required when using the rxgk security index.
+    pub rxgk: Option<RxgkKeyId>,
+}
+
+impl Description {
+    /// The security index for rxkad.
+    pub const RXKAD: u8 = 2;
+    /// The security index for rxgk.
+    pub const RXGK: u8 = 4;
+
+    /// A description for an rxkad key for the given service.
+    pub fn rxkad(service_id: u16) -> Self {
+        Description {
+            service_id,
+            security_index: Self::RXKAD,
+            rxgk: None,
+        }
+    }
+
+    /// A description for an rxgk key for the given service.
+    pub fn rxgk(service_id: u16, kvno: u32, enctype: Enctype) -> Self {
+        Description {
+            service_id,
+            security_index: Self::RXGK,
+            rxgk: Some(RxgkKeyId {
+                kvno,
+                enctype,
+            }),
+        }
+    }
 }
 
 impl KeyDescription for Description {
     fn description(&self) -> Cow<str> {
-        format!("{}:{}", self.service_id, self.security_index).into()
+        if let Some(rxgk) = self.rxgk.as_ref() {
+            format!(
+                "{}:{}:{}:{}",
+                self.service_id,
+                self.security_index,
+                rxgk.kvno,
+                rxgk.enctype.number(),
+            )
+        } else {
+            format!("{}:{}", self.service_id, self.security_index)
+        }
+        .into()
+    }
+}
+
+/// Errors which may occur when constructing an RxRPC server key payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+// #[non_exhaustive]
+pub enum PayloadError {
+    /// The security index does not support server keys.
+    UnsupportedSecurityIndex(u8),
+    /// An rxgk key was requested without key identification in the description.
+    MissingRxgkKeyId,
+    /// The key has the wrong length for the security class.
+    InvalidKeyLength {
+        /// The length required by the security class.
+        expected: usize,
+        /// The length of the given key.
+        actual: usize,
+    },
+}
+
+impl fmt::Display for PayloadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PayloadError::UnsupportedSecurityIndex(index) => {
+                write!(f, "unsupported security index: {}", index)
+            },
+            PayloadError::MissingRxgkKeyId => {
+                write!(f, "rxgk keys require a key version and encryption type")
+            },
+            PayloadError::InvalidKeyLength {
+                expected,
+                actual,
+            } => {
+                write!(
+                    f,
+                    "keys must be {} bytes long, not {} bytes",
+                    expected, actual,
+                )
+            },
+        }
     }
 }
 
+impl error::Error for PayloadError {}
+
 /// The payload for an RxRPC server key.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Payload {
-    key: [u8; 8],
+    key: Vec<u8>,
+}
+
+impl Payload {
+    /// A DES key for an rxkad service.
+    pub fn rxkad(key: [u8; 8]) -> Self {
+        Payload {
+            key: key.to_vec(),
+        }
+    }
+
+    /// A key for an rxgk service.
+    pub fn rxgk(enctype: Enctype, key: &[u8]) -> Result<Self, PayloadError> {
+        if key.len() != enctype.key_len() {
+            return Err(PayloadError::InvalidKeyLength {
+                expected: enctype.key_len(),
+                actual: key.len(),
+            });
+        }
+
+        Ok(Payload {
+            key: key.to_vec(),
+        })
+    }
+
+    /// A key for the security class of the given description.
+    pub fn for_description(description: &Description, key: &[u8]) -> Result<Self, PayloadError> {
+        match description.security_index {
+            Description::RXKAD => {
+                if description.rxgk.is_some() {
+                    return Err(PayloadError::UnsupportedSecurityIndex(
+                        description.security_index,
+                    ));
+                }
+                if key.len() != 8 {
+                    return Err(PayloadError::InvalidKeyLength {
+                        expected: 8,
+                        actual: key.len(),
+                    });
+                }
+
+                Ok(Payload {
+                    key: key.to_vec(),
+                })
+            },
+            Description::RXGK => {
+                let rxgk = description.rxgk.ok_or(PayloadError::MissingRxgkKeyId)?;
+                Self::rxgk(rxgk.enctype, key)
+            },
+            index => Err(PayloadError::UnsupportedSecurityIndex(index)),
+        }
+    }
 }
 
 impl KeyPayload for Payload {
@@ -69,3 +260,86 @@ impl KeyPayload for Payload {
         Cow::Borrowed(&self.key)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::keytype::*;
+
+    use super::*;
+
+    #[test]
+    fn test_description() {
+        assert_eq!(Description::rxkad(52).description(), "52:2");
+        assert_eq!(
+            Description::rxgk(2500, 3, Enctype::Aes256CtsHmacSha1_96).description(),
+            "2500:4:3:18",
+        );
+    }
+
+    #[test]
+    fn test_rxkad_payload() {
+        let desc = Description::rxkad(52);
+        let key = [1, 2, 3, 4, 5, 6, 7, 8];
+
+        assert_eq!(Payload::rxkad(key).payload(), &key[..]);
+        assert_eq!(
+            Payload::for_description(&desc, &key).unwrap(),
+            Payload::rxkad(key)
+        );
+        assert_eq!(
+            Payload::for_description(&desc, &[0; 16]).unwrap_err(),
+            PayloadError::InvalidKeyLength {
+                expected: 8,
+                actual: 16,
+            },
+        );
+    }
+
+    #[test]
+    fn test_rxgk_payload() {
+        let desc = Description::rxgk(2500, 1, Enctype::Aes128CtsHmacSha256_128);
+        let key = [0x5a; 16];
+
+        assert_eq!(
+            Payload::for_description(&desc, &key).unwrap().payload(),
+            &key[..],
+        );
+        assert_eq!(
+            Payload::for_description(&desc, &[0; 32]).unwrap_err(),
+            PayloadError::InvalidKeyLength {
+                expected: 16,
+                actual: 32,
+            },
+        );
+        assert_eq!(
+            Payload::rxgk(Enctype::Camellia256CtsCmac, &[0; 16]).unwrap_err(),
+            PayloadError::InvalidKeyLength {
+                expected: 32,
+                actual: 16,
+            },
+        );
+    }
+
+    #[test]
+    fn test_invalid_descriptions() {
+        let missing = Description {
+            service_id: 1,
+            security_index: Description::RXGK,
+            rxgk: None,
+        };
+        assert_eq!(
+            Payload::for_description(&missing, &[0; 16]).unwrap_err(),
+            PayloadError::MissingRxgkKeyId,
+        );
+
+        let unsupported = Description {
+            service_id: 1,
+            security_index: 1,
+            rxgk: None,
+        };
+        assert_eq!(
+            Payload::for_description(&unsupported, &[0; 8]).unwrap_err(),
+            PayloadError::UnsupportedSecurityIndex(1),
+        );
+    }
+}
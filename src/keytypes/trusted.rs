@@ -27,12 +27,19 @@
 //! Trusted keys
 
 use std::borrow::Cow;
+use std::error;
 use std::fmt;
+use std::fs;
+use std::io;
+use std::str::{self, FromStr};
 
-use super::ByteBuf;
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use super::{from_hex, ByteBuf};
 use crate::keytype::*;
 
-/// Trusted keys are rooted in the TPM.
+/// Trusted keys are rooted in a hardware source of trust (e.g., a TPM).
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct Trusted;
 
@@ -46,6 +53,87 @@ impl KeyType for Trusted {
     }
 }
 
+/// The source of trust backing trusted keys.
+///
+/// The kernel selects a single source at boot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+// #[non_exhaustive]
+pub enum TrustSource {
+    /// A Trusted Platform Module.
+    Tpm,
+    /// A Trusted Execution Environment.
+    Tee,
+    /// NXP's Cryptographic Acceleration and Assurance Module.
+    Caam,
+    /// NXP's Data Co-Processor.
+    Dcp,
+    /// A source unknown to this crate.
+    Other(String),
+}
+
+impl TrustSource {
+    /// The name of the source as used by the `trusted.source` kernel parameter.
+    pub fn name(&self) -> &str {
+        match self {
+            TrustSource::Tpm => "tpm",
+            TrustSource::Tee => "tee",
+            TrustSource::Caam => "caam",
+            TrustSource::Dcp => "dcp",
+            TrustSource::Other(name) => name,
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "" | "(null)" => return None,
+            "tpm" => TrustSource::Tpm,
+            "tee" => TrustSource::Tee,
+            "caam" => TrustSource::Caam,
+            "dcp" => TrustSource::Dcp,
+            name => TrustSource::Other(name.into()),
+        })
+    }
+
+    /// Whether the source supports the TPM-specific options in `TrustedOptions`.
+    pub fn supports_tpm_options(&self) -> bool {
+        *self == TrustSource::Tpm
+    }
+
+    /// Find the source requested on a kernel command line.
+    ///
+    /// Returns `None` if no `trusted.source` parameter is present.
+    pub fn from_cmdline(cmdline: &str) -> Option<Self> {
+        cmdline
+            .split_whitespace()
+            // Arguments after `--` are passed to init.
+            .take_while(|arg| *arg != "--")
+            .filter_map(|arg| {
+                let mut split = arg.splitn(2, '=');
+                match (split.next(), split.next()) {
+                    (Some("trusted.source"), Some(value)) => Some(value),
+                    _ => None,
+                }
+            })
+            .last()
+            .and_then(Self::from_name)
+    }
+
+    /// Find the source requested for the running kernel.
+    ///
+    /// Only the `trusted.source` parameter on the kernel command line is consulted. Returns `None`
+    /// if no source was requested, in which case the kernel uses the first available source
+    /// (preferring a TPM); the source chosen that way is not exposed to userspace.
+    pub fn requested() -> io::Result<Option<Self>> {
+        fs::read_to_string("/proc/cmdline").map(|cmdline| Self::from_cmdline(&cmdline))
+    }
+}
+
+impl fmt::Display for TrustSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
 /// Hashes supported by TPM devices.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 // #[non_exhaustive]
@@ -80,6 +168,233 @@ impl TpmHash {
             TpmHash::Sm3_256 => "sm3-256",
         }
     }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sha1" => Some(TpmHash::Sha1),
+            "sha256" => Some(TpmHash::Sha256),
+            "sha384" => Some(TpmHash::Sha384),
+            "sha512" => Some(TpmHash::Sha512),
+            "sm3-256" => Some(TpmHash::Sm3_256),
+            _ => None,
+        }
+    }
+
+    /// The TPM algorithm identifier of the hash.
+    fn alg_id(self) -> u16 {
+        match self {
+            TpmHash::Sha1 => 0x0004,
+            TpmHash::Sha256 => 0x000b,
+            TpmHash::Sha384 => 0x000c,
+            TpmHash::Sha512 => 0x000d,
+            TpmHash::Sm3_256 => 0x0012,
+        }
+    }
+
+    /// The size of digests using the hash.
+    pub fn digest_size(self) -> usize {
+        match self {
+            TpmHash::Sha1 => 20,
+            TpmHash::Sha256 | TpmHash::Sm3_256 => 32,
+            TpmHash::Sha384 => 48,
+            TpmHash::Sha512 => 64,
+        }
+    }
+
+    /// Hash the concatenation of the given data.
+    fn hash(self, data: &[&[u8]]) -> Result<Vec<u8>, PolicyError> {
+        fn hash_with<D: Digest>(data: &[&[u8]]) -> Vec<u8> {
+            let mut hasher = D::new();
+            for datum in data {
+                hasher.update(datum);
+            }
+            hasher.finalize().to_vec()
+        }
+
+        Ok(match self {
+            TpmHash::Sha1 => hash_with::<Sha1>(data),
+            TpmHash::Sha256 => hash_with::<Sha256>(data),
+            TpmHash::Sha384 => hash_with::<Sha384>(data),
+            TpmHash::Sha512 => hash_with::<Sha512>(data),
+            TpmHash::Sm3_256 => return Err(PolicyError::UnsupportedHash(self)),
+        })
+    }
+}
+
+/// `TPM_CC_PolicyAuthValue`
+const TPM_CC_POLICY_AUTH_VALUE: u32 = 0x0000_016b;
+/// `TPM_CC_PolicyPCR`
+const TPM_CC_POLICY_PCR: u32 = 0x0000_017f;
+/// The number of PCRs which may be selected.
+const PCR_SELECT_MAX: u32 = 24;
+
+/// Errors which may occur when computing a policy digest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+// #[non_exhaustive]
+pub enum PolicyError {
+    /// The hash is not supported for computing policies.
+    UnsupportedHash(TpmHash),
+    /// No PCRs were given.
+    NoPcrs,
+    /// The PCR index is out of range.
+    InvalidPcrIndex(u32),
+    /// A PCR was given more than once.
+    DuplicatePcr(u32),
+    /// A PCR value has the wrong size for its bank.
+    InvalidPcrValue {
+        /// The PCR index.
+        pcr: u32,
+        /// The size of digests in the PCR bank.
+        expected: usize,
+        /// The size of the given value.
+        actual: usize,
+    },
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PolicyError::UnsupportedHash(hash) => {
+                write!(f, "unsupported policy hash: {}", hash.name())
+            },
+            PolicyError::NoPcrs => write!(f, "no PCRs were given"),
+            PolicyError::InvalidPcrIndex(pcr) => write!(f, "invalid PCR index: {}", pcr),
+            PolicyError::DuplicatePcr(pcr) => write!(f, "PCR {} was given more than once", pcr),
+            PolicyError::InvalidPcrValue {
+                pcr,
+                expected,
+                actual,
+            } => {
+                write!(
+                    f,
+                    "the value for PCR {} must be {} bytes, not {} bytes",
+                    pcr, expected, actual,
+                )
+            },
+        }
+    }
+}
+
+impl error::Error for PolicyError {}
+
+/// A TPM2 authorization policy digest.
+///
+/// This computes the digest a TPM policy session would have after executing the same sequence
+/// of policy commands. The result may be used as the `policydigest` option for sealing a
+/// trusted key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyDigest {
+    hash: TpmHash,
+    digest: Vec<u8>,
+}
+
+impl PolicyDigest {
+    /// An empty policy using the given hash.
+    pub fn new(hash: TpmHash) -> Result<Self, PolicyError> {
+        // Check that the hash is supported up front.
+        hash.hash(&[])?;
+
+        Ok(PolicyDigest {
+            hash,
+            digest: vec![0; hash.digest_size()],
+        })
+    }
+
+    fn extend(&mut self, data: &[&[u8]]) {
+        let mut parts = Vec::with_capacity(data.len() + 1);
+        parts.push(&self.digest[..]);
+        parts.extend(data.iter().cloned());
+        self.digest = self
+            .hash
+            .hash(&parts)
+            .expect("the hash was checked at construction");
+    }
+
+    /// Extend the policy with `TPM2_PolicyPCR`.
+    ///
+    /// The policy is satisfied only when the given PCRs in the `bank` have the given values.
+    pub fn pcr(mut self, bank: TpmHash, pcrs: &[(u32, &[u8])]) -> Result<Self, PolicyError> {
+        if pcrs.is_empty() {
+            return Err(PolicyError::NoPcrs);
+        }
+
+        let mut pcrs = pcrs.to_vec();
+        pcrs.sort_by_key(|&(pcr, _)| pcr);
+
+        let mut select = [0u8; (PCR_SELECT_MAX / 8) as usize];
+        for &(pcr, value) in &pcrs {
+            if pcr >= PCR_SELECT_MAX {
+                return Err(PolicyError::InvalidPcrIndex(pcr));
+            }
+            let byte = &mut select[(pcr / 8) as usize];
+            let bit = 1 << (pcr % 8);
+            if *byte & bit != 0 {
+                return Err(PolicyError::DuplicatePcr(pcr));
+            }
+            *byte |= bit;
+
+            if value.len() != bank.digest_size() {
+                return Err(PolicyError::InvalidPcrValue {
+                    pcr,
+                    expected: bank.digest_size(),
+                    actual: value.len(),
+                });
+            }
+        }
+
+        // The PCR values are hashed in order of their index.
+        let values = pcrs.iter().map(|&(_, value)| value).collect::<Vec<_>>();
+        let pcr_digest = self.hash.hash(&values)?;
+
+        // TPML_PCR_SELECTION with a single TPMS_PCR_SELECTION.
+        let mut selection = Vec::with_capacity(4 + 2 + 1 + select.len());
+        selection.extend(1u32.to_be_bytes().iter());
+        selection.extend(bank.alg_id().to_be_bytes().iter());
+        selection.push(select.len() as u8);
+        selection.extend(select.iter());
+
+        self.extend(&[&TPM_CC_POLICY_PCR.to_be_bytes(), &selection, &pcr_digest]);
+
+        Ok(self)
+    }
+
+    /// Extend the policy with `TPM2_PolicyAuthValue`.
+    ///
+    /// The policy additionally requires the key's authorization value (`blobauth`) in an HMAC
+    /// session.
+    pub fn auth_value(mut self) -> Self {
+        self.extend(&[&TPM_CC_POLICY_AUTH_VALUE.to_be_bytes()]);
+        self
+    }
+
+    /// Extend the policy with `TPM2_PolicyPassword`.
+    ///
+    /// The policy additionally requires the key's authorization value (`blobauth`) in plaintext.
+    /// The digest is the same as for `TPM2_PolicyAuthValue`.
+    pub fn password(self) -> Self {
+        self.auth_value()
+    }
+
+    /// The hash used by the policy.
+    pub fn hash(&self) -> TpmHash {
+        self.hash
+    }
+
+    /// The digest of the policy.
+    pub fn digest(&self) -> &[u8] {
+        &self.digest
+    }
+
+    /// Options for sealing a trusted key to the policy.
+    ///
+    /// Sets the `hash` and `policydigest` options.
+    pub fn options(&self) -> TrustedOptions {
+        TrustedOptions {
+            hash: Some(self.hash),
+            policydigest: Some(self.digest.clone()),
+            ..Default::default()
+        }
+    }
 }
 
 /// Options for trusted keys.
@@ -111,6 +426,78 @@ pub struct TrustedOptions {
     pub policyhandle: Option<u32>,
 }
 
+/// Errors found when validating trusted key options.
+#[derive(Debug, Clone, PartialEq, Eq)]
+// #[non_exhaustive]
+pub enum OptionsError {
+    /// An option is only supported when using a TPM.
+    TpmOnly {
+        /// The trust source in use.
+        source: TrustSource,
+        /// The name of the option.
+        option: &'static str,
+    },
+}
+
+impl fmt::Display for OptionsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OptionsError::TpmOnly {
+                source,
+                option,
+            } => {
+                write!(
+                    f,
+                    "the `{}` option requires a TPM, but the trust source is `{}`",
+                    option, source,
+                )
+            },
+        }
+    }
+}
+
+impl error::Error for OptionsError {}
+
+impl TrustedOptions {
+    /// The names of the options which are set.
+    fn set_options(&self) -> Vec<&'static str> {
+        let options = [
+            ("keyhandle", self.keyhandle.is_some()),
+            ("keyauth", self.keyauth.is_some()),
+            ("blobauth", self.blobauth.is_some()),
+            ("pcrinfo", self.pcrinfo.is_some()),
+            ("pcrlock", self.pcrlock.is_some()),
+            ("migratable", self.migratable.is_some()),
+            ("hash", self.hash.is_some()),
+            ("policydigest", self.policydigest.is_some()),
+            ("policyhandle", self.policyhandle.is_some()),
+        ];
+
+        options
+            .iter()
+            .filter_map(|&(name, set)| if set { Some(name) } else { None })
+            .collect()
+    }
+
+    /// Check that the options may be used with the given trust source.
+    ///
+    /// All of the options are specific to TPMs; other sources silently ignore them.
+    pub fn validate(&self, source: &TrustSource) -> Result<(), OptionsError> {
+        if source.supports_tpm_options() {
+            return Ok(());
+        }
+
+        if let Some(option) = self.set_options().into_iter().next() {
+            Err(OptionsError::TpmOnly {
+                source: source.clone(),
+                option,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
 impl fmt::Display for TrustedOptions {
     /// Formats the options that are present. Starts with a leading space.
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -163,6 +550,186 @@ impl fmt::Display for TrustedOptions {
     }
 }
 
+/// Errors which may occur when parsing trusted key options.
+#[derive(Debug, Clone, PartialEq, Eq)]
+// #[non_exhaustive]
+pub enum ParseOptionsError {
+    /// The option is not known.
+    UnknownOption(String),
+    /// The option is missing its value.
+    MissingValue(String),
+    /// The option was given more than once.
+    DuplicateOption(String),
+    /// The value of the option is invalid.
+    InvalidValue {
+        /// The name of the option.
+        option: String,
+        /// The invalid value.
+        value: String,
+    },
+}
+
+impl fmt::Display for ParseOptionsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseOptionsError::UnknownOption(option) => write!(f, "unknown option: `{}`", option),
+            ParseOptionsError::MissingValue(option) => {
+                write!(f, "missing value for option `{}`", option)
+            },
+            ParseOptionsError::DuplicateOption(option) => {
+                write!(f, "option `{}` given more than once", option)
+            },
+            ParseOptionsError::InvalidValue {
+                option,
+                value,
+            } => write!(f, "invalid value for option `{}`: `{}`", option, value),
+        }
+    }
+}
+
+impl error::Error for ParseOptionsError {}
+
+fn parse_auth(value: &str) -> Option<[u8; 20]> {
+    let bytes = from_hex(value)?;
+    if bytes.len() != 20 {
+        return None;
+    }
+
+    let mut auth = [0; 20];
+    auth.copy_from_slice(&bytes);
+    Some(auth)
+}
+
+fn set_option<T>(
+    field: &mut Option<T>,
+    name: &str,
+    value: Option<T>,
+    raw: &str,
+) -> Result<(), ParseOptionsError> {
+    if field.is_some() {
+        return Err(ParseOptionsError::DuplicateOption(name.into()));
+    }
+    *field = Some(value.ok_or_else(|| {
+        ParseOptionsError::InvalidValue {
+            option: name.into(),
+            value: raw.into(),
+        }
+    })?);
+    Ok(())
+}
+
+impl FromStr for TrustedOptions {
+    type Err = ParseOptionsError;
+
+    /// Parses options in the `key=value` format used by `Display`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut options = TrustedOptions::default();
+
+        for option in s.split_whitespace() {
+            let mut split = option.splitn(2, '=');
+            let name = split.next().unwrap_or("");
+            let value = split
+                .next()
+                .ok_or_else(|| ParseOptionsError::MissingValue(name.into()))?;
+
+            match name {
+                "keyhandle" => {
+                    let parsed = u32::from_str_radix(value, 16).ok();
+                    set_option(&mut options.keyhandle, name, parsed, value)?;
+                },
+                "keyauth" => set_option(&mut options.keyauth, name, parse_auth(value), value)?,
+                "blobauth" => set_option(&mut options.blobauth, name, parse_auth(value), value)?,
+                "pcrinfo" => set_option(&mut options.pcrinfo, name, from_hex(value), value)?,
+                "pcrlock" => set_option(&mut options.pcrlock, name, value.parse().ok(), value)?,
+                "migratable" => {
+                    let parsed = match value {
+                        "0" => Some(false),
+                        "1" => Some(true),
+                        _ => None,
+                    };
+                    set_option(&mut options.migratable, name, parsed, value)?;
+                },
+                "hash" => set_option(&mut options.hash, name, TpmHash::from_name(value), value)?,
+                "policydigest" => {
+                    set_option(&mut options.policydigest, name, from_hex(value), value)?;
+                },
+                "policyhandle" => {
+                    let parsed = u32::from_str_radix(value, 16).ok();
+                    set_option(&mut options.policyhandle, name, parsed, value)?;
+                },
+                _ => return Err(ParseOptionsError::UnknownOption(name.into())),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Errors which may occur when decoding a sealed blob.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// #[non_exhaustive]
+pub enum SealedBlobError {
+    /// The blob is empty.
+    Empty,
+    /// The blob is not valid hexadecimal.
+    InvalidHex,
+}
+
+impl fmt::Display for SealedBlobError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SealedBlobError::Empty => write!(f, "the sealed blob is empty"),
+            SealedBlobError::InvalidHex => write!(f, "the sealed blob is not valid hexadecimal"),
+        }
+    }
+}
+
+impl error::Error for SealedBlobError {}
+
+/// A sealed trusted key blob.
+///
+/// Reading a trusted key returns its sealed blob encoded as hexadecimal. The blob may be stored
+/// and loaded into a new key later (e.g., on the next boot).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SealedBlob {
+    blob: Vec<u8>,
+}
+
+impl SealedBlob {
+    /// Decode a sealed blob as read from a trusted key.
+    pub fn parse(data: &[u8]) -> Result<Self, SealedBlobError> {
+        let hex = str::from_utf8(data).map_err(|_| SealedBlobError::InvalidHex)?;
+        let hex = hex.trim();
+        if hex.is_empty() {
+            return Err(SealedBlobError::Empty);
+        }
+
+        Ok(SealedBlob {
+            blob: from_hex(hex).ok_or(SealedBlobError::InvalidHex)?,
+        })
+    }
+
+    /// The sealed blob.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.blob
+    }
+
+    /// A payload to load the blob into a new key.
+    pub fn into_payload(self, options: TrustedOptions) -> Payload {
+        Payload::Load {
+            blob: self.blob,
+            options,
+        }
+    }
+}
+
+impl fmt::Display for SealedBlob {
+    /// Formats the blob as hexadecimal (as read from the kernel).
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:x}", ByteBuf(&self.blob))
+    }
+}
+
 /// The payload for trusted keys.
 #[derive(Debug, Clone, PartialEq, Eq)]
 // #[non_exhaustive]
@@ -194,6 +761,33 @@ pub enum Payload {
     },
 }
 
+impl Payload {
+    /// A payload to load a blob read from a trusted key.
+    pub fn load(read_back: &[u8], options: TrustedOptions) -> Result<Self, SealedBlobError> {
+        SealedBlob::parse(read_back).map(|blob| blob.into_payload(options))
+    }
+
+    /// The options of the payload.
+    pub fn options(&self) -> &TrustedOptions {
+        match self {
+            Payload::New {
+                options, ..
+            }
+            | Payload::Load {
+                options, ..
+            }
+            | Payload::Update {
+                options,
+            } => options,
+        }
+    }
+
+    /// Check that the payload may be used with the given trust source.
+    pub fn validate(&self, source: &TrustSource) -> Result<(), OptionsError> {
+        self.options().validate(source)
+    }
+}
+
 impl KeyPayload for Payload {
     fn payload(&self) -> Cow<[u8]> {
         match self {
@@ -213,3 +807,290 @@ impl KeyPayload for Payload {
         .into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::keytype::*;
+
+    use super::*;
+
+    #[test]
+    fn test_policy_auth_value() {
+        let policy = PolicyDigest::new(TpmHash::Sha256).unwrap().auth_value();
+        assert_eq!(
+            format!("{:x}", ByteBuf(policy.digest())),
+            "8fcd2169ab92694e0c633f1ab772842b8241bbc20288981fc7ac1eddc1fddb0e",
+        );
+
+        let password = PolicyDigest::new(TpmHash::Sha256).unwrap().password();
+        assert_eq!(password, policy);
+    }
+
+    #[test]
+    fn test_policy_pcr() {
+        let pcr0 = [0; 32];
+        let pcr7 = [0x11; 32];
+
+        // The order of the PCRs does not matter.
+        let policy = PolicyDigest::new(TpmHash::Sha256)
+            .unwrap()
+            .pcr(TpmHash::Sha256, &[(7, &pcr7), (0, &pcr0)])
+            .unwrap();
+        assert_eq!(
+            format!("{:x}", ByteBuf(policy.digest())),
+            "e543270061aadd84e54f13c220912eefd4ee992a5ec53df25eb22aebca0458ab",
+        );
+
+        let policy = policy.auth_value();
+        assert_eq!(
+            format!("{:x}", ByteBuf(policy.digest())),
+            "0775ee58c20d8bfca1b78461b2f8eebace929e6b7170184423df167c86a8ef53",
+        );
+
+        let options = policy.options();
+        assert_eq!(options.hash, Some(TpmHash::Sha256));
+        assert_eq!(options.policydigest, Some(policy.digest().to_vec()));
+    }
+
+    #[test]
+    fn test_policy_pcr_banks() {
+        let policy = PolicyDigest::new(TpmHash::Sha1)
+            .unwrap()
+            .pcr(TpmHash::Sha1, &[(23, &[0; 20])])
+            .unwrap();
+        assert_eq!(
+            format!("{:x}", ByteBuf(policy.digest())),
+            "a3edc74fa48158f99928e094f2e80733bc786720",
+        );
+
+        let policy = PolicyDigest::new(TpmHash::Sha256)
+            .unwrap()
+            .pcr(TpmHash::Sha1, &[(0, &[0; 20])])
+            .unwrap();
+        assert_eq!(
+            format!("{:x}", ByteBuf(policy.digest())),
+            "702d9ea2bbe19f3fd2f3dcb56f416246fffa3eef1b5805d6dde37ebe42262a32",
+        );
+    }
+
+    #[test]
+    fn test_policy_errors() {
+        assert_eq!(
+            PolicyDigest::new(TpmHash::Sm3_256).unwrap_err(),
+            PolicyError::UnsupportedHash(TpmHash::Sm3_256),
+        );
+
+        let policy = PolicyDigest::new(TpmHash::Sha256).unwrap();
+        assert_eq!(
+            policy.clone().pcr(TpmHash::Sha256, &[]).unwrap_err(),
+            PolicyError::NoPcrs,
+        );
+        assert_eq!(
+            policy
+                .clone()
+                .pcr(TpmHash::Sha256, &[(24, &[0; 32])])
+                .unwrap_err(),
+            PolicyError::InvalidPcrIndex(24),
+        );
+        assert_eq!(
+            policy
+                .clone()
+                .pcr(TpmHash::Sha256, &[(1, &[0; 32]), (1, &[1; 32])])
+                .unwrap_err(),
+            PolicyError::DuplicatePcr(1),
+        );
+        assert_eq!(
+            policy.pcr(TpmHash::Sm3_256, &[(1, &[0; 20])]).unwrap_err(),
+            PolicyError::InvalidPcrValue {
+                pcr: 1,
+                expected: 32,
+                actual: 20,
+            },
+        );
+    }
+
+    #[test]
+    fn test_options_roundtrip() {
+        let options = TrustedOptions {
+            keyhandle: Some(0x8100_0001),
+            keyauth: Some([0x11; 20]),
+            blobauth: Some([0xab; 20]),
+            pcrinfo: Some(vec![0x01, 0x02]),
+            pcrlock: Some(10),
+            migratable: Some(false),
+            hash: Some(TpmHash::Sm3_256),
+            policydigest: Some(vec![0xde, 0xad]),
+            policyhandle: Some(0x0300_0000),
+        };
+
+        let formatted = options.to_string();
+        assert_eq!(formatted.parse::<TrustedOptions>().unwrap(), options);
+        assert_eq!(
+            "".parse::<TrustedOptions>().unwrap(),
+            TrustedOptions::default(),
+        );
+        assert_eq!(
+            "hash=sha256 migratable=1"
+                .parse::<TrustedOptions>()
+                .unwrap(),
+            TrustedOptions {
+                hash: Some(TpmHash::Sha256),
+                migratable: Some(true),
+                ..Default::default()
+            },
+        );
+    }
+
+    #[test]
+    fn test_options_parse_errors() {
+        assert_eq!(
+            "bogus=1".parse::<TrustedOptions>().unwrap_err(),
+            ParseOptionsError::UnknownOption("bogus".into()),
+        );
+        assert_eq!(
+            "pcrlock".parse::<TrustedOptions>().unwrap_err(),
+            ParseOptionsError::MissingValue("pcrlock".into()),
+        );
+        assert_eq!(
+            "pcrlock=1 pcrlock=2".parse::<TrustedOptions>().unwrap_err(),
+            ParseOptionsError::DuplicateOption("pcrlock".into()),
+        );
+        assert_eq!(
+            "keyauth=00".parse::<TrustedOptions>().unwrap_err(),
+            ParseOptionsError::InvalidValue {
+                option: "keyauth".into(),
+                value: "00".into(),
+            },
+        );
+        assert_eq!(
+            "hash=md5".parse::<TrustedOptions>().unwrap_err(),
+            ParseOptionsError::InvalidValue {
+                option: "hash".into(),
+                value: "md5".into(),
+            },
+        );
+        assert_eq!(
+            "migratable=yes".parse::<TrustedOptions>().unwrap_err(),
+            ParseOptionsError::InvalidValue {
+                option: "migratable".into(),
+                value: "yes".into(),
+            },
+        );
+    }
+
+    #[test]
+    fn test_sealed_blob() {
+        let blob = SealedBlob::parse(b"0011aabb\n").unwrap();
+        assert_eq!(blob.as_bytes(), &[0x00, 0x11, 0xaa, 0xbb]);
+        assert_eq!(blob.to_string(), "0011aabb");
+
+        assert_eq!(SealedBlob::parse(b"").unwrap_err(), SealedBlobError::Empty);
+        assert_eq!(
+            SealedBlob::parse(b"001").unwrap_err(),
+            SealedBlobError::InvalidHex,
+        );
+        assert_eq!(
+            SealedBlob::parse(b"\xff\xfe").unwrap_err(),
+            SealedBlobError::InvalidHex,
+        );
+    }
+
+    #[test]
+    fn test_load_payload() {
+        let options = "keyhandle=81000001".parse().unwrap();
+        let payload = Payload::load(b"0011aabb", options).unwrap();
+
+        assert_eq!(
+            payload,
+            Payload::Load {
+                blob: vec![0x00, 0x11, 0xaa, 0xbb],
+                options: TrustedOptions {
+                    keyhandle: Some(0x8100_0001),
+                    ..Default::default()
+                },
+            },
+        );
+        assert_eq!(payload.payload(), &b"load 0011aabb keyhandle=81000001"[..]);
+    }
+
+    #[test]
+    fn test_source_from_cmdline() {
+        assert_eq!(TrustSource::from_cmdline("quiet ro"), None);
+        assert_eq!(
+            TrustSource::from_cmdline("quiet trusted.source=tee ro"),
+            Some(TrustSource::Tee),
+        );
+        assert_eq!(
+            TrustSource::from_cmdline("trusted.source=tpm trusted.source=caam"),
+            Some(TrustSource::Caam),
+        );
+        assert_eq!(
+            TrustSource::from_cmdline("trusted.source=pkcs11"),
+            Some(TrustSource::Other("pkcs11".into())),
+        );
+        assert_eq!(
+            TrustSource::from_cmdline("quiet -- trusted.source=dcp"),
+            None,
+        );
+        assert_eq!(TrustSource::from_cmdline("trusted.source="), None);
+    }
+
+    #[test]
+    fn test_source_names() {
+        for source in &[
+            TrustSource::Tpm,
+            TrustSource::Tee,
+            TrustSource::Caam,
+            TrustSource::Dcp,
+        ] {
+            assert_eq!(TrustSource::from_name(source.name()).as_ref(), Some(source));
+        }
+        assert_eq!(TrustSource::from_name("(null)"), None);
+    }
+
+    #[test]
+    fn test_validate_options() {
+        let options = TrustedOptions {
+            keyhandle: Some(0x8100_0001),
+            ..Default::default()
+        };
+
+        assert_eq!(options.validate(&TrustSource::Tpm), Ok(()));
+        assert_eq!(
+            options.validate(&TrustSource::Caam),
+            Err(OptionsError::TpmOnly {
+                source: TrustSource::Caam,
+                option: "keyhandle",
+            }),
+        );
+        assert_eq!(
+            TrustedOptions::default().validate(&TrustSource::Tee),
+            Ok(()),
+        );
+    }
+
+    #[test]
+    fn test_validate_payload() {
+        let payload = Payload::Load {
+            blob: Vec::new(),
+            options: TrustedOptions {
+                pcrlock: Some(7),
+                ..Default::default()
+            },
+        };
+
+        assert_eq!(
+            payload.validate(&TrustSource::Dcp),
+            Err(OptionsError::TpmOnly {
+                source: TrustSource::Dcp,
+                option: "pcrlock",
+            }),
+        );
+
+        let payload = Payload::New {
+            keylen: 32,
+            options: TrustedOptions::default(),
+        };
+        assert_eq!(payload.validate(&TrustSource::Dcp), Ok(()));
+    }
+}
@@ -31,12 +31,27 @@
 #![warn(missing_docs)]
 
 mod api;
+mod builder;
 mod constants;
+mod der;
 mod keytype;
+mod pem;
 
+pub mod access;
+pub mod cache;
+pub mod dh;
+pub mod dns_upcall;
 pub mod keytypes;
+pub mod pkey;
+pub mod rotation;
+#[cfg(feature = "signature")]
+pub mod signer;
+pub mod ssh_agent;
+#[cfg(feature = "rustls")]
+pub mod tls;
 
 pub use self::api::*;
+pub use self::builder::KeyBuilder;
 pub use self::constants::*;
 pub use self::keytype::*;
 
@@ -0,0 +1,240 @@
+// Copyright (c) 2026, Ben Boeckel
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of this project nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Minimal PEM decoding
+//!
+//! Only enough of PEM is supported to extract DER-encoded keys and certificates. Encapsulated
+//! headers (such as those used by legacy encrypted keys) are not supported.
+
+/// A decoded PEM block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Pem {
+    /// The label of the block (e.g., `CERTIFICATE`).
+    pub label: String,
+    /// The decoded contents of the block.
+    pub contents: Vec<u8>,
+}
+
+const BEGIN: &str = "-----BEGIN ";
+const END: &str = "-----END ";
+const DASHES: &str = "-----";
+
+/// Whether the input looks like PEM rather than binary data.
+pub(crate) fn is_pem(input: &[u8]) -> bool {
+    let start = input
+        .iter()
+        .position(|c| !c.is_ascii_whitespace())
+        .unwrap_or(input.len());
+    input[start..].starts_with(BEGIN.as_bytes())
+}
+
+/// Decode the first PEM block in `input`.
+pub(crate) fn parse(input: &[u8]) -> Option<Pem> {
+    let text = std::str::from_utf8(input).ok()?;
+    let mut lines = text.lines().map(str::trim);
+
+    let label = lines.find_map(|line| {
+        if line.starts_with(BEGIN) && line.ends_with(DASHES) && line.len() >= BEGIN.len() + 5 {
+            Some(&line[BEGIN.len()..line.len() - DASHES.len()])
+        } else {
+            None
+        }
+    })?;
+    let end = format!("{}{}{}", END, label, DASHES);
+
+    let mut encoded = Vec::new();
+    let mut found_end = false;
+    for line in lines {
+        if line == end {
+            found_end = true;
+            break;
+        }
+        encoded.extend(line.bytes().filter(|c| !c.is_ascii_whitespace()));
+    }
+    if !found_end {
+        return None;
+    }
+
+    Some(Pem {
+        label: label.into(),
+        contents: decode_base64(&encoded)?,
+    })
+}
+
+fn sextet(c: u8) -> Option<u32> {
+    let value = match c {
+        b'A'..=b'Z' => c - b'A',
+        b'a'..=b'z' => c - b'a' + 26,
+        b'0'..=b'9' => c - b'0' + 52,
+        b'+' => 62,
+        b'/' => 63,
+        _ => return None,
+    };
+    Some(value.into())
+}
+
+/// Decode padded standard base64.
+pub(crate) fn decode_base64(input: &[u8]) -> Option<Vec<u8>> {
+    let chunks = input.chunks_exact(4);
+    if !chunks.remainder().is_empty() {
+        return None;
+    }
+
+    let mut output = Vec::with_capacity(input.len() / 4 * 3);
+    let nchunks = chunks.len();
+    for (idx, chunk) in chunks.enumerate() {
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        // Padding may only appear at the end of the input.
+        if padding > 2 || (padding > 0 && idx + 1 != nchunks) {
+            return None;
+        }
+
+        let mut acc = 0;
+        for &c in &chunk[..4 - padding] {
+            acc = (acc << 6) | sextet(c)?;
+        }
+        acc <<= 6 * padding;
+
+        let bytes = [(acc >> 16) as u8, (acc >> 8) as u8, acc as u8];
+        output.extend_from_slice(&bytes[..3 - padding]);
+    }
+
+    Some(output)
+}
+
+/// Encode DER data as a PEM block.
+#[cfg(test)]
+pub(crate) fn encode(label: &str, der: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::new();
+    for chunk in der.chunks(3) {
+        let acc = chunk.iter().enumerate().fold(0u32, |acc, (idx, &byte)| {
+            acc | u32::from(byte) << (16 - 8 * idx)
+        });
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                encoded.push(ALPHABET[(acc >> (18 - 6 * idx) & 0x3f) as usize].into());
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    format!(
+        "{}{}{}\n{}\n{}{}{}\n",
+        BEGIN, label, DASHES, encoded, END, label, DASHES,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_base64, encode, is_pem, parse, Pem};
+
+    #[test]
+    fn test_decode_base64() {
+        assert_eq!(decode_base64(b""), Some(Vec::new()));
+        assert_eq!(decode_base64(b"Zg=="), Some(b"f".to_vec()));
+        assert_eq!(decode_base64(b"Zm8="), Some(b"fo".to_vec()));
+        assert_eq!(decode_base64(b"Zm9v"), Some(b"foo".to_vec()));
+        assert_eq!(decode_base64(b"Zm9vYg=="), Some(b"foob".to_vec()));
+        assert_eq!(decode_base64(b"+/+/"), Some(vec![0xfb, 0xff, 0xbf]));
+    }
+
+    #[test]
+    fn test_decode_base64_invalid() {
+        assert_eq!(decode_base64(b"Zm9"), None);
+        assert_eq!(decode_base64(b"Zg==Zm9v"), None);
+        assert_eq!(decode_base64(b"Z==="), None);
+        assert_eq!(decode_base64(b"Z=g="), None);
+        assert_eq!(decode_base64(b"Zm9-"), None);
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(
+            encode("TEST", b"foob"),
+            "-----BEGIN TEST-----\nZm9vYg==\n-----END TEST-----\n",
+        );
+        for len in 0..8 {
+            let der = (0..len).map(|byte| byte * 37).collect::<Vec<u8>>();
+            assert_eq!(
+                parse(encode("TEST", &der).as_bytes()),
+                Some(Pem {
+                    label: "TEST".into(),
+                    contents: der,
+                }),
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let input =
+            b"leading text\n-----BEGIN TEST-----\nZm9v\nYmFy\n-----END TEST-----\ntrailing\n";
+        assert!(!is_pem(input));
+        assert_eq!(
+            parse(input),
+            Some(Pem {
+                label: "TEST".into(),
+                contents: b"foobar".to_vec(),
+            }),
+        );
+
+        let input = b"\r\n-----BEGIN A B-----\r\nZm9v\r\n-----END A B-----\r\n";
+        assert!(is_pem(input));
+        assert_eq!(
+            parse(input),
+            Some(Pem {
+                label: "A B".into(),
+                contents: b"foo".to_vec(),
+            }),
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        // No block.
+        assert_eq!(parse(b"Zm9v"), None);
+        // Missing end marker.
+        assert_eq!(parse(b"-----BEGIN TEST-----\nZm9v\n"), None);
+        // Mismatched end marker.
+        assert_eq!(
+            parse(b"-----BEGIN TEST-----\nZm9v\n-----END OTHER-----\n"),
+            None
+        );
+        // Encapsulated headers.
+        assert_eq!(
+            parse(b"-----BEGIN TEST-----\nProc-Type: 4,ENCRYPTED\n\nZm9v\n-----END TEST-----\n"),
+            None,
+        );
+        // Not UTF-8.
+        assert_eq!(
+            parse(b"\xff-----BEGIN TEST-----\n-----END TEST-----\n"),
+            None
+        );
+    }
+}
@@ -0,0 +1,409 @@
+// Copyright (c) 2026, Ben Boeckel
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of this project nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Public key operations with cached support information
+//!
+//! `Key::encrypt`, `Key::decrypt`, and `Key::sign` query the kernel for the key's capabilities on
+//! every call. A `PublicKeyContext` performs the query once, validates its options up front, and
+//! checks input sizes against the limits reported by the kernel before making any syscalls.
+
+use std::error;
+use std::fmt;
+use std::result;
+
+use keyutils_raw::*;
+use uninit::extension_traits::VecCapacity;
+use uninit::out_ref::Out;
+
+use crate::{Key, KeySupportInfo, KeyctlEncoding, KeyctlSupportFlag, PublicKeyOptions};
+
+/// Errors in the combination of options for a public key operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+// #[non_exhaustive]
+pub enum OptionsError {
+    /// A hash was given without an encoding.
+    HashWithoutEncoding,
+    /// The encoding does not use a hash.
+    UnusedHash(KeyctlEncoding),
+    /// The encoding requires a hash.
+    MissingHash(KeyctlEncoding),
+}
+
+impl fmt::Display for OptionsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OptionsError::HashWithoutEncoding => write!(f, "a hash requires an encoding"),
+            OptionsError::UnusedHash(encoding) => {
+                write!(f, "the {:?} encoding does not use a hash", encoding)
+            },
+            OptionsError::MissingHash(encoding) => {
+                write!(f, "the {:?} encoding requires a hash", encoding)
+            },
+        }
+    }
+}
+
+impl error::Error for OptionsError {}
+
+/// Errors which may occur when using a `PublicKeyContext`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+// #[non_exhaustive]
+pub enum PublicKeyOpError {
+    /// The options are invalid.
+    InvalidOptions(OptionsError),
+    /// The key does not support the operation.
+    Unsupported(KeyctlSupportFlag),
+    /// The input is larger than the kernel accepts for the key.
+    InputTooLarge {
+        /// The size of the input.
+        size: usize,
+        /// The maximum size of the input.
+        max: usize,
+    },
+    /// The kernel returned an error.
+    Kernel(errno::Errno),
+}
+
+impl fmt::Display for PublicKeyOpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PublicKeyOpError::InvalidOptions(err) => write!(f, "invalid options: {}", err),
+            PublicKeyOpError::Unsupported(operation) => {
+                write!(f, "the key does not support {:?}", operation)
+            },
+            PublicKeyOpError::InputTooLarge {
+                size,
+                max,
+            } => {
+                write!(
+                    f,
+                    "the input is {} bytes, but at most {} bytes are allowed",
+                    size, max,
+                )
+            },
+            PublicKeyOpError::Kernel(err) => write!(f, "kernel error: {}", err),
+        }
+    }
+}
+
+impl error::Error for PublicKeyOpError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            PublicKeyOpError::InvalidOptions(err) => Some(err),
+            PublicKeyOpError::Kernel(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<OptionsError> for PublicKeyOpError {
+    fn from(err: OptionsError) -> Self {
+        PublicKeyOpError::InvalidOptions(err)
+    }
+}
+
+impl From<errno::Errno> for PublicKeyOpError {
+    fn from(err: errno::Errno) -> Self {
+        PublicKeyOpError::Kernel(err)
+    }
+}
+
+type Result<T> = result::Result<T, PublicKeyOpError>;
+
+/// A key prepared for public key operations using a fixed set of options.
+#[derive(Debug, Clone)]
+pub struct PublicKeyContext {
+    key: Key,
+    options: PublicKeyOptions,
+    info: String,
+    support: KeySupportInfo,
+}
+
+impl PublicKeyContext {
+    /// Prepare a key for operations using the given options.
+    ///
+    /// The options are validated and the kernel is queried for the key's capabilities.
+    pub fn new(key: Key, options: PublicKeyOptions) -> Result<Self> {
+        options.validate()?;
+        let support = key.pkey_query_support(&options)?;
+        let info = options.info();
+
+        Ok(PublicKeyContext {
+            key,
+            options,
+            info,
+            support,
+        })
+    }
+
+    /// The key used for operations.
+    pub fn key(&self) -> &Key {
+        &self.key
+    }
+
+    /// The options used for operations.
+    pub fn options(&self) -> &PublicKeyOptions {
+        &self.options
+    }
+
+    /// The capabilities reported by the kernel.
+    pub fn support(&self) -> &KeySupportInfo {
+        &self.support
+    }
+
+    fn check(&self, operation: KeyctlSupportFlag, input: &[u8], max: u16) -> Result<()> {
+        if !self.support.supported().contains(operation) {
+            return Err(PublicKeyOpError::Unsupported(operation));
+        }
+        if input.len() > max as usize {
+            return Err(PublicKeyOpError::InputTooLarge {
+                size: input.len(),
+                max: max as usize,
+            });
+        }
+        Ok(())
+    }
+
+    fn output<F>(size: u16, op: F) -> Result<Vec<u8>>
+    where
+        F: FnOnce(Out<[u8]>) -> crate::Result<usize>,
+    {
+        let mut buffer = vec![0; size as usize];
+        let sz = op(buffer.get_backing_buffer())?;
+        buffer.truncate(sz);
+        Ok(buffer)
+    }
+
+    /// Encrypt data using the key.
+    pub fn encrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.check(
+            KeyctlSupportFlag::SUPPORTS_ENCRYPT,
+            data,
+            self.support.max_data_size,
+        )?;
+        Self::output(self.support.max_enc_size, |buffer| {
+            keyctl_pkey_encrypt(self.key.serial(), &self.info, data, buffer)
+        })
+    }
+
+    /// Decrypt data using the key.
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.check(
+            KeyctlSupportFlag::SUPPORTS_DECRYPT,
+            data,
+            self.support.max_enc_size,
+        )?;
+        Self::output(self.support.max_dec_size, |buffer| {
+            keyctl_pkey_decrypt(self.key.serial(), &self.info, data, buffer)
+        })
+    }
+
+    /// Sign data using the key.
+    ///
+    /// The data is usually a digest of the message using the hash in the options.
+    pub fn sign(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.check(
+            KeyctlSupportFlag::SUPPORTS_SIGN,
+            data,
+            self.support.max_data_size,
+        )?;
+        Self::output(self.support.max_sig_size, |buffer| {
+            keyctl_pkey_sign(self.key.serial(), &self.info, data, buffer)
+        })
+    }
+
+    /// Verify a signature of the data using the key.
+    pub fn verify(&self, data: &[u8], signature: &[u8]) -> Result<bool> {
+        self.check(
+            KeyctlSupportFlag::SUPPORTS_VERIFY,
+            data,
+            self.support.max_data_size,
+        )?;
+        if signature.len() > self.support.max_sig_size as usize {
+            return Err(PublicKeyOpError::InputTooLarge {
+                size: signature.len(),
+                max: self.support.max_sig_size as usize,
+            });
+        }
+        Ok(keyctl_pkey_verify(
+            self.key.serial(),
+            &self.info,
+            data,
+            signature,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Key, KeySupportInfo, KeyctlEncoding, KeyctlHash, KeyctlSupportFlag, KeyringSerial,
+        PublicKeyOptions,
+    };
+
+    use super::{OptionsError, PublicKeyContext, PublicKeyOpError};
+
+    fn options(encoding: Option<KeyctlEncoding>, hash: Option<KeyctlHash>) -> PublicKeyOptions {
+        PublicKeyOptions {
+            encoding,
+            hash,
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(options(None, None).validate(), Ok(()));
+        assert_eq!(
+            options(Some(KeyctlEncoding::RsassaPkcs1V15), None).validate(),
+            Ok(()),
+        );
+        assert_eq!(
+            options(
+                Some(KeyctlEncoding::RsassaPkcs1V15),
+                Some(KeyctlHash::Sha256),
+            )
+            .validate(),
+            Ok(()),
+        );
+        assert_eq!(
+            options(Some(KeyctlEncoding::RsassaPss), Some(KeyctlHash::Sha256)).validate(),
+            Ok(()),
+        );
+        assert_eq!(
+            options(Some(KeyctlEncoding::RsaesPkcs1V15), None).validate(),
+            Ok(()),
+        );
+        assert_eq!(
+            options(Some(KeyctlEncoding::RsaesOaep), Some(KeyctlHash::Sha1)).validate(),
+            Ok(()),
+        );
+        assert_eq!(
+            options(
+                Some(KeyctlEncoding::OtherEncoding("raw".into())),
+                Some(KeyctlHash::Sha256),
+            )
+            .validate(),
+            Ok(()),
+        );
+    }
+
+    #[test]
+    fn test_validate_invalid() {
+        assert_eq!(
+            options(None, Some(KeyctlHash::Sha256)).validate(),
+            Err(OptionsError::HashWithoutEncoding),
+        );
+        assert_eq!(
+            options(
+                Some(KeyctlEncoding::RsaesPkcs1V15),
+                Some(KeyctlHash::Sha256)
+            )
+            .validate(),
+            Err(OptionsError::UnusedHash(KeyctlEncoding::RsaesPkcs1V15)),
+        );
+        assert_eq!(
+            options(Some(KeyctlEncoding::RsassaPss), None).validate(),
+            Err(OptionsError::MissingHash(KeyctlEncoding::RsassaPss)),
+        );
+        assert_eq!(
+            options(Some(KeyctlEncoding::RsaesOaep), None).validate(),
+            Err(OptionsError::MissingHash(KeyctlEncoding::RsaesOaep)),
+        );
+    }
+
+    // The checks fail before any syscall is made, so the key does not need to exist.
+    fn context(supported: KeyctlSupportFlag) -> PublicKeyContext {
+        let options = options(Some(KeyctlEncoding::RsassaPkcs1V15), None);
+        PublicKeyContext {
+            key: unsafe { Key::new(KeyringSerial::new(1).unwrap()) },
+            info: options.info(),
+            options,
+            support: KeySupportInfo {
+                supported_ops: supported.bits(),
+                key_size: 512,
+                max_data_size: 32,
+                max_sig_size: 64,
+                max_enc_size: 48,
+                max_dec_size: 16,
+            },
+        }
+    }
+
+    fn too_large(size: usize, max: usize) -> PublicKeyOpError {
+        PublicKeyOpError::InputTooLarge {
+            size,
+            max,
+        }
+    }
+
+    #[test]
+    fn test_context_unsupported() {
+        let ctx = context(KeyctlSupportFlag::empty());
+
+        assert_eq!(
+            ctx.encrypt(&[]).unwrap_err(),
+            PublicKeyOpError::Unsupported(KeyctlSupportFlag::SUPPORTS_ENCRYPT),
+        );
+        assert_eq!(
+            ctx.decrypt(&[]).unwrap_err(),
+            PublicKeyOpError::Unsupported(KeyctlSupportFlag::SUPPORTS_DECRYPT),
+        );
+        assert_eq!(
+            ctx.sign(&[]).unwrap_err(),
+            PublicKeyOpError::Unsupported(KeyctlSupportFlag::SUPPORTS_SIGN),
+        );
+        assert_eq!(
+            ctx.verify(&[], &[]).unwrap_err(),
+            PublicKeyOpError::Unsupported(KeyctlSupportFlag::SUPPORTS_VERIFY),
+        );
+
+        // Support for other operations does not matter.
+        let ctx = context(KeyctlSupportFlag::all() - KeyctlSupportFlag::SUPPORTS_SIGN);
+        assert_eq!(
+            ctx.sign(&[]).unwrap_err(),
+            PublicKeyOpError::Unsupported(KeyctlSupportFlag::SUPPORTS_SIGN),
+        );
+    }
+
+    #[test]
+    fn test_context_input_too_large() {
+        let ctx = context(KeyctlSupportFlag::all());
+
+        assert_eq!(ctx.encrypt(&[0; 33]).unwrap_err(), too_large(33, 32));
+        // Decryption takes encrypted blobs.
+        assert_eq!(ctx.decrypt(&[0; 49]).unwrap_err(), too_large(49, 48));
+        assert_eq!(ctx.sign(&[0; 33]).unwrap_err(), too_large(33, 32));
+        assert_eq!(
+            ctx.verify(&[0; 33], &[0; 64]).unwrap_err(),
+            too_large(33, 32),
+        );
+        // The signature is checked against the maximum signature size.
+        assert_eq!(
+            ctx.verify(&[0; 32], &[0; 65]).unwrap_err(),
+            too_large(65, 64),
+        );
+    }
+}
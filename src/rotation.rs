@@ -0,0 +1,246 @@
+// Copyright (c) 2026, Ben Boeckel
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of this project nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Versioned key rotation
+//!
+//! Rotating a secret replaces it with a new value while keeping the old value readable for a
+//! grace period. Each generation of a secret is stored as a separate key with a description of
+//! `<name>@v<N>`.
+
+use std::borrow::Borrow;
+use std::cmp::Reverse;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use log::warn;
+
+use crate::keytype::*;
+use crate::keytypes::{logon, BigKey, Logon, User};
+use crate::{Error, Key, Keyring, Result};
+
+/// A key type which supports versioned rotation.
+pub trait RotatableKeyType: KeyType {
+    /// The description type used for a generation of the key.
+    type GenerationDescription: Borrow<Self::Description>;
+
+    /// The description of generation `version` of the key described by `name`.
+    ///
+    /// The description as seen by the kernel must be `<name>@v<version>`.
+    fn generation(name: &Self::Description, version: u64) -> Self::GenerationDescription;
+}
+
+impl RotatableKeyType for User {
+    type GenerationDescription = String;
+
+    fn generation(name: &str, version: u64) -> String {
+        format!("{}@v{}", name, version)
+    }
+}
+
+impl RotatableKeyType for BigKey {
+    type GenerationDescription = String;
+
+    fn generation(name: &str, version: u64) -> String {
+        format!("{}@v{}", name, version)
+    }
+}
+
+impl RotatableKeyType for Logon {
+    type GenerationDescription = logon::Description;
+
+    fn generation(name: &logon::Description, version: u64) -> logon::Description {
+        logon::Description {
+            subtype: name.subtype.clone(),
+            description: format!("{}@v{}", name.description, version).into(),
+        }
+    }
+}
+
+/// Whether an error indicates that a key is no longer usable.
+fn is_gone(err: Error) -> bool {
+    err == errno::Errno(libc::ENOKEY)
+        || err == errno::Errno(libc::EKEYEXPIRED)
+        || err == errno::Errno(libc::EKEYREVOKED)
+}
+
+/// A generation of a rotated key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Generation {
+    /// The version of the generation.
+    pub version: u64,
+    /// The key holding the generation.
+    pub key: Key,
+}
+
+/// A helper for rotating a secret stored in a keyring.
+///
+/// New generations are fully configured in a staging keyring before being linked into the
+/// keyring (see `KeyBuilder`), so readers always find either the previous or the new generation.
+/// If a key with the same description already exists (e.g., left over from an interrupted
+/// rotation), linking the new key atomically replaces it.
+///
+/// When a new generation is added, the previous generation is given a timeout of the grace period
+/// and any older generations are invalidated.
+///
+/// See `Keyring::rotation`.
+#[derive(Debug)]
+pub struct KeyRotation<'a, K, D> {
+    keyring: &'a mut Keyring,
+    name: D,
+    grace: Duration,
+    _keytype: PhantomData<K>,
+}
+
+impl<'a, K, D> KeyRotation<'a, K, D>
+where
+    K: RotatableKeyType,
+    D: Borrow<K::Description>,
+{
+    pub(crate) fn new(keyring: &'a mut Keyring, name: D, grace: Duration) -> Self {
+        KeyRotation {
+            keyring,
+            name,
+            grace,
+            _keytype: PhantomData,
+        }
+    }
+
+    fn prefix(&self) -> String {
+        format!("{}@v", self.name.borrow().description())
+    }
+
+    /// The generations of the key present in the keyring, newest first.
+    ///
+    /// Generations which have expired or been revoked are skipped.
+    pub fn generations(&self) -> Result<Vec<Generation>> {
+        let prefix = self.prefix();
+        // Avoid `Keyring::read` since describing expired generations fails.
+        let mut generations = self
+            .keyring
+            .children()?
+            .into_iter()
+            .filter_map(|key| {
+                match key.description() {
+                    Ok(desc) => {
+                        if desc.type_ != K::name() || !desc.description.starts_with(&prefix) {
+                            return None;
+                        }
+                        desc.description[prefix.len()..]
+                            .parse()
+                            .ok()
+                            .map(|version| {
+                                Ok(Generation {
+                                    version,
+                                    key,
+                                })
+                            })
+                    },
+                    Err(err) if is_gone(err) => None,
+                    Err(err) => Some(Err(err)),
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        generations.sort_by_key(|generation| Reverse(generation.version));
+        Ok(generations)
+    }
+
+    /// Look up a usable generation of the key.
+    fn lookup(&self, version: u64) -> Result<Option<Key>> {
+        let description = K::generation(self.name.borrow(), version);
+        match self
+            .keyring
+            .search_for_key::<K, _, _>(description.borrow(), None)
+        {
+            Ok(key) => Ok(Some(key)),
+            Err(err) if is_gone(err) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn nth_generation(&self, n: usize) -> Result<Option<Key>> {
+        match self.generations()?.get(n) {
+            Some(generation) => self.lookup(generation.version),
+            None => Ok(None),
+        }
+    }
+
+    /// The current generation of the key.
+    pub fn current(&self) -> Result<Option<Key>> {
+        self.nth_generation(0)
+    }
+
+    /// The previous generation of the key.
+    ///
+    /// Returns `None` once the grace period of the previous generation has elapsed.
+    pub fn previous(&self) -> Result<Option<Key>> {
+        self.nth_generation(1)
+    }
+
+    /// Add a new generation of the key.
+    ///
+    /// The previous generation remains usable for the grace period. Since a timeout of zero
+    /// seconds means "no expiration" to the kernel, a grace period of less than a second
+    /// invalidates the previous generation immediately.
+    pub fn rotate<P>(&mut self, payload: P) -> Result<Key>
+    where
+        P: Borrow<K::Payload>,
+    {
+        let generations = self.generations()?;
+        let version = generations
+            .first()
+            .map_or(1, |generation| generation.version + 1);
+        let description = K::generation(self.name.borrow(), version);
+        let key = self
+            .keyring
+            .build_key::<K, _, _>(description.borrow(), payload.borrow())
+            .create()?;
+
+        let mut superseded = generations.into_iter();
+        if self.grace.as_secs() > 0 {
+            if let Some(mut previous) = superseded.next() {
+                match previous.key.set_timeout(self.grace) {
+                    Ok(()) => (),
+                    Err(err) if is_gone(err) => (),
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+        for old in superseded {
+            if let Err(err) = old.key.invalidate() {
+                if !is_gone(err) {
+                    warn!(
+                        "failed to invalidate generation {} of `{}`: {}",
+                        old.version,
+                        self.name.borrow().description(),
+                        err,
+                    );
+                }
+            }
+        }
+
+        Ok(key)
+    }
+}
@@ -0,0 +1,339 @@
+// Copyright (c) 2026, Ben Boeckel
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of this project nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! RustCrypto `signature` support for asymmetric keys
+//!
+//! Keys held by the kernel may be used wherever the `signature::Signer` and `signature::Verifier`
+//! traits are accepted. The kernel signs digests, so messages are hashed in userspace before
+//! being handed to the key.
+//!
+//! Requires the `signature` feature.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::marker::PhantomData;
+use std::result;
+
+use sha2::Digest;
+use signature::{Error, SignatureEncoding, Signer, Verifier};
+
+use crate::{
+    Key, KeySupportInfo, KeyctlEncoding, KeyctlHash, KeyctlSupportFlag, PublicKeyOptions, Result,
+};
+
+/// A signature encoding supported by the kernel.
+pub trait KernelEncoding {
+    /// The encoding to request from the kernel.
+    fn encoding() -> KeyctlEncoding;
+}
+
+/// The RSASSA-PKCS1-v1.5 signature encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pkcs1v15;
+
+impl KernelEncoding for Pkcs1v15 {
+    fn encoding() -> KeyctlEncoding {
+        KeyctlEncoding::RsassaPkcs1V15
+    }
+}
+
+/// The RSASSA-PSS signature encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pss;
+
+impl KernelEncoding for Pss {
+    fn encoding() -> KeyctlEncoding {
+        KeyctlEncoding::RsassaPss
+    }
+}
+
+/// A hash which may be used with a signature encoding.
+pub trait KernelHash: Digest {
+    /// The name of the hash for the kernel.
+    fn hash() -> KeyctlHash;
+}
+
+impl KernelHash for sha1::Sha1 {
+    fn hash() -> KeyctlHash {
+        KeyctlHash::Sha1
+    }
+}
+
+impl KernelHash for sha2::Sha224 {
+    fn hash() -> KeyctlHash {
+        KeyctlHash::Sha224
+    }
+}
+
+impl KernelHash for sha2::Sha256 {
+    fn hash() -> KeyctlHash {
+        KeyctlHash::Sha256
+    }
+}
+
+impl KernelHash for sha2::Sha384 {
+    fn hash() -> KeyctlHash {
+        KeyctlHash::Sha384
+    }
+}
+
+impl KernelHash for sha2::Sha512 {
+    fn hash() -> KeyctlHash {
+        KeyctlHash::Sha512
+    }
+}
+
+/// A signature using the encoding `E`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Signature<E> {
+    bytes: Box<[u8]>,
+    encoding: PhantomData<E>,
+}
+
+impl<E> Signature<E> {
+    fn new(bytes: Vec<u8>) -> Self {
+        Signature {
+            bytes: bytes.into(),
+            encoding: PhantomData,
+        }
+    }
+
+    /// The bytes of the signature.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl<E> fmt::Debug for Signature<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Signature").field(&self.bytes).finish()
+    }
+}
+
+impl<E> AsRef<[u8]> for Signature<E> {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl<'a, E> TryFrom<&'a [u8]> for Signature<E> {
+    type Error = Error;
+
+    fn try_from(bytes: &'a [u8]) -> result::Result<Self, Self::Error> {
+        if bytes.is_empty() {
+            Err(Error::new())
+        } else {
+            Ok(Self::new(bytes.into()))
+        }
+    }
+}
+
+impl<E> From<Signature<E>> for Box<[u8]> {
+    fn from(signature: Signature<E>) -> Self {
+        signature.bytes
+    }
+}
+
+impl<E: Clone> SignatureEncoding for Signature<E> {
+    type Repr = Box<[u8]>;
+}
+
+fn options<E: KernelEncoding, H: KernelHash>() -> PublicKeyOptions {
+    PublicKeyOptions {
+        encoding: Some(E::encoding()),
+        hash: Some(H::hash()),
+    }
+}
+
+fn query_support<E: KernelEncoding, H: KernelHash>(
+    key: &Key,
+    required: KeyctlSupportFlag,
+) -> Result<KeySupportInfo> {
+    let info = key.pkey_query_support(&options::<E, H>())?;
+    if info.supported().contains(required) {
+        Ok(info)
+    } else {
+        Err(errno::Errno(libc::EOPNOTSUPP))
+    }
+}
+
+fn verify<E: KernelEncoding, H: KernelHash>(
+    key: &Key,
+    msg: &[u8],
+    signature: &Signature<E>,
+) -> result::Result<(), Error> {
+    let digest = H::digest(msg);
+    match key.verify(&options::<E, H>(), &digest, signature.as_bytes()) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(Error::new()),
+        Err(err) => Err(Error::from_source(err)),
+    }
+}
+
+/// A signer using an asymmetric key held by the kernel.
+///
+/// The encoding and hash are chosen by the type parameters (e.g.,
+/// `KernelSigner<Pkcs1v15, sha2::Sha256>`).
+pub struct KernelSigner<E, H> {
+    key: Key,
+    info: KeySupportInfo,
+    marker: PhantomData<fn() -> (E, H)>,
+}
+
+impl<E: KernelEncoding, H: KernelHash> KernelSigner<E, H> {
+    /// Use a key for signing.
+    ///
+    /// Fails with `EOPNOTSUPP` if the kernel does not support signing with the key using the
+    /// encoding and hash.
+    pub fn new(key: Key) -> Result<Self> {
+        let info = query_support::<E, H>(&key, KeyctlSupportFlag::SUPPORTS_SIGN)?;
+
+        Ok(KernelSigner {
+            key,
+            info,
+            marker: PhantomData,
+        })
+    }
+}
+
+impl<E, H> KernelSigner<E, H> {
+    /// The key used for signing.
+    pub fn key(&self) -> &Key {
+        &self.key
+    }
+
+    /// The support information reported by the kernel when the signer was created.
+    pub fn support_info(&self) -> &KeySupportInfo {
+        &self.info
+    }
+
+    /// Extract the key from the signer.
+    pub fn into_key(self) -> Key {
+        self.key
+    }
+}
+
+impl<E, H> fmt::Debug for KernelSigner<E, H> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("KernelSigner")
+            .field("key", &self.key)
+            .field("info", &self.info)
+            .finish()
+    }
+}
+
+impl<E: KernelEncoding, H: KernelHash> Signer<Signature<E>> for KernelSigner<E, H> {
+    fn try_sign(&self, msg: &[u8]) -> result::Result<Signature<E>, Error> {
+        let digest = H::digest(msg);
+        self.key
+            .sign(&options::<E, H>(), &digest)
+            .map(Signature::new)
+            .map_err(Error::from_source)
+    }
+}
+
+impl<E: KernelEncoding, H: KernelHash> Verifier<Signature<E>> for KernelSigner<E, H> {
+    fn verify(&self, msg: &[u8], signature: &Signature<E>) -> result::Result<(), Error> {
+        verify::<E, H>(&self.key, msg, signature)
+    }
+}
+
+/// A verifier using an asymmetric key held by the kernel.
+///
+/// Unlike `KernelSigner`, this only requires the public part of the key (e.g., from a
+/// certificate).
+pub struct KernelVerifier<E, H> {
+    key: Key,
+    info: KeySupportInfo,
+    marker: PhantomData<fn() -> (E, H)>,
+}
+
+impl<E: KernelEncoding, H: KernelHash> KernelVerifier<E, H> {
+    /// Use a key for verification.
+    ///
+    /// Fails with `EOPNOTSUPP` if the kernel does not support verifying with the key using the
+    /// encoding and hash.
+    pub fn new(key: Key) -> Result<Self> {
+        let info = query_support::<E, H>(&key, KeyctlSupportFlag::SUPPORTS_VERIFY)?;
+
+        Ok(KernelVerifier {
+            key,
+            info,
+            marker: PhantomData,
+        })
+    }
+}
+
+impl<E, H> KernelVerifier<E, H> {
+    /// The key used for verification.
+    pub fn key(&self) -> &Key {
+        &self.key
+    }
+
+    /// The support information reported by the kernel when the verifier was created.
+    pub fn support_info(&self) -> &KeySupportInfo {
+        &self.info
+    }
+
+    /// Extract the key from the verifier.
+    pub fn into_key(self) -> Key {
+        self.key
+    }
+}
+
+impl<E, H> fmt::Debug for KernelVerifier<E, H> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("KernelVerifier")
+            .field("key", &self.key)
+            .field("info", &self.info)
+            .finish()
+    }
+}
+
+impl<E: KernelEncoding, H: KernelHash> Verifier<Signature<E>> for KernelVerifier<E, H> {
+    fn verify(&self, msg: &[u8], signature: &Signature<E>) -> result::Result<(), Error> {
+        verify::<E, H>(&self.key, msg, signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use signature::SignatureEncoding;
+
+    use super::{Pkcs1v15, Signature};
+
+    #[test]
+    fn test_signature_encoding() {
+        let signature = Signature::<Pkcs1v15>::try_from(&[1, 2, 3][..]).unwrap();
+        assert_eq!(signature.as_bytes(), [1, 2, 3]);
+        assert_eq!(&*signature.to_bytes(), [1, 2, 3]);
+        assert_eq!(signature.encoded_len(), 3);
+
+        assert!(Signature::<Pkcs1v15>::try_from(&[][..]).is_err());
+    }
+}
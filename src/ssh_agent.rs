@@ -0,0 +1,566 @@
+// Copyright (c) 2026, Ben Boeckel
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of this project nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! An ssh-agent backed by kernel asymmetric keys
+//!
+//! Identities are RSA private keys loaded into a keyring as `asymmetric` keys, so the agent
+//! process never holds the private key material. The kernel does not expose the public part of
+//! an asymmetric key, so each key is paired with its OpenSSH public key; the comment of the
+//! public key is used as the description of the kernel key.
+//!
+//! Only `SSH_AGENTC_REQUEST_IDENTITIES` and `SSH_AGENTC_SIGN_REQUEST` are supported. All other
+//! requests are answered with `SSH_AGENT_FAILURE`.
+
+use std::error;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::os::unix::net::UnixListener;
+use std::result;
+
+use log::warn;
+use sha2::Digest;
+
+use crate::keytypes::Asymmetric;
+use crate::pem;
+use crate::{Key, KeyctlEncoding, KeyctlHash, Keyring, PublicKeyOptions, Result};
+
+const SSH_AGENT_FAILURE: u8 = 5;
+const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+const SSH_AGENTC_SIGN_REQUEST: u8 = 13;
+const SSH_AGENT_SIGN_RESPONSE: u8 = 14;
+
+const SSH_AGENT_RSA_SHA2_256: u32 = 0x02;
+const SSH_AGENT_RSA_SHA2_512: u32 = 0x04;
+
+/// The largest request accepted from a client.
+const MAX_MESSAGE_SIZE: usize = 256 * 1024;
+
+const SSH_RSA: &str = "ssh-rsa";
+
+/// Reads SSH wire format values.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader {
+            data,
+        }
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.data.len() < len {
+            return None;
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|bytes| bytes[0])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.bytes(4)
+            .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn string(&mut self) -> Option<&'a [u8]> {
+        let len = self.u32()?;
+        self.bytes(len as usize)
+    }
+}
+
+fn put_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend_from_slice(&value.to_be_bytes());
+}
+
+fn put_string(buffer: &mut Vec<u8>, value: &[u8]) {
+    put_u32(buffer, value.len() as u32);
+    buffer.extend_from_slice(value);
+}
+
+/// Errors which may occur when parsing an OpenSSH public key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+// #[non_exhaustive]
+pub enum PublicKeyError {
+    /// The line is not of the form `<type> <base64> [comment]`.
+    InvalidFormat,
+    /// The key is not valid base64.
+    InvalidBase64,
+    /// The key blob is malformed.
+    InvalidKey,
+    /// The key type is not supported by the agent.
+    UnsupportedKeyType(String),
+    /// The key type of the line does not match the key blob.
+    MismatchedKeyType {
+        /// The key type given on the line.
+        declared: String,
+        /// The key type in the key blob.
+        actual: String,
+    },
+}
+
+impl fmt::Display for PublicKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PublicKeyError::InvalidFormat => write!(f, "invalid public key line"),
+            PublicKeyError::InvalidBase64 => write!(f, "invalid base64 in public key"),
+            PublicKeyError::InvalidKey => write!(f, "invalid public key blob"),
+            PublicKeyError::UnsupportedKeyType(key_type) => {
+                write!(f, "unsupported key type: {}", key_type)
+            },
+            PublicKeyError::MismatchedKeyType {
+                declared,
+                actual,
+            } => {
+                write!(
+                    f,
+                    "key type {} does not match the key ({})",
+                    declared, actual
+                )
+            },
+        }
+    }
+}
+
+impl error::Error for PublicKeyError {}
+
+/// An OpenSSH public key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicKey {
+    blob: Vec<u8>,
+    comment: String,
+}
+
+impl PublicKey {
+    /// Parse a public key in the format used by `.pub` files and `authorized_keys`.
+    ///
+    /// Only `ssh-rsa` keys are supported.
+    pub fn parse(line: &str) -> result::Result<Self, PublicKeyError> {
+        let mut fields = line.trim().splitn(3, char::is_whitespace);
+        let declared = fields.next().ok_or(PublicKeyError::InvalidFormat)?;
+        let encoded = fields.next().ok_or(PublicKeyError::InvalidFormat)?;
+        let comment = fields.next().unwrap_or("").trim();
+
+        if declared != SSH_RSA {
+            return Err(PublicKeyError::UnsupportedKeyType(declared.into()));
+        }
+        let blob = pem::decode_base64(encoded.as_bytes()).ok_or(PublicKeyError::InvalidBase64)?;
+
+        let mut reader = Reader::new(&blob);
+        let actual = reader.string().ok_or(PublicKeyError::InvalidKey)?;
+        if actual != declared.as_bytes() {
+            return Err(PublicKeyError::MismatchedKeyType {
+                declared: declared.into(),
+                actual: String::from_utf8_lossy(actual).into(),
+            });
+        }
+        // The public exponent and modulus.
+        reader.string().ok_or(PublicKeyError::InvalidKey)?;
+        reader.string().ok_or(PublicKeyError::InvalidKey)?;
+        if !reader.data.is_empty() {
+            return Err(PublicKeyError::InvalidKey);
+        }
+
+        Ok(PublicKey {
+            blob,
+            comment: comment.into(),
+        })
+    }
+
+    /// The public key in SSH wire format.
+    pub fn blob(&self) -> &[u8] {
+        &self.blob
+    }
+
+    /// The comment of the key.
+    pub fn comment(&self) -> &str {
+        &self.comment
+    }
+}
+
+/// A key which may be used by the agent.
+#[derive(Debug, Clone)]
+pub struct Identity {
+    key: Key,
+    public_key: PublicKey,
+}
+
+impl Identity {
+    /// The kernel key holding the private key.
+    pub fn key(&self) -> &Key {
+        &self.key
+    }
+
+    /// The public key of the identity.
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    fn sign(&self, data: &[u8], flags: u32) -> Result<Vec<u8>> {
+        let (algorithm, hash, digest) = if flags & SSH_AGENT_RSA_SHA2_512 != 0 {
+            (
+                "rsa-sha2-512",
+                KeyctlHash::Sha512,
+                sha2::Sha512::digest(data).to_vec(),
+            )
+        } else if flags & SSH_AGENT_RSA_SHA2_256 != 0 {
+            (
+                "rsa-sha2-256",
+                KeyctlHash::Sha256,
+                sha2::Sha256::digest(data).to_vec(),
+            )
+        } else {
+            (SSH_RSA, KeyctlHash::Sha1, sha1::Sha1::digest(data).to_vec())
+        };
+        let options = PublicKeyOptions {
+            encoding: Some(KeyctlEncoding::RsassaPkcs1V15),
+            hash: Some(hash),
+        };
+        let raw = self.key.sign(&options, &digest)?;
+
+        let mut signature = Vec::new();
+        put_string(&mut signature, algorithm.as_bytes());
+        put_string(&mut signature, &raw);
+        Ok(signature)
+    }
+}
+
+/// An ssh-agent serving identities held by the kernel.
+#[derive(Debug, Default, Clone)]
+pub struct Agent {
+    identities: Vec<Identity>,
+}
+
+impl Agent {
+    /// An agent without any identities.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Find the kernel keys for the given public keys.
+    ///
+    /// Each public key is looked up as an `asymmetric` key described by its comment. Public keys
+    /// without a matching kernel key are skipped.
+    pub fn from_keyring<I>(keyring: &Keyring, public_keys: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = PublicKey>,
+    {
+        let mut agent = Self::new();
+        for public_key in public_keys {
+            match keyring.search_for_key::<Asymmetric, _, _>(public_key.comment(), None) {
+                Ok(key) => agent.add_identity(key, public_key),
+                Err(errno::Errno(libc::ENOKEY)) => {
+                    warn!("no asymmetric key found for {}", public_key.comment());
+                },
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(agent)
+    }
+
+    /// Add an identity to the agent.
+    pub fn add_identity(&mut self, key: Key, public_key: PublicKey) {
+        self.identities.push(Identity {
+            key,
+            public_key,
+        })
+    }
+
+    /// The identities served by the agent.
+    pub fn identities(&self) -> &[Identity] {
+        &self.identities
+    }
+
+    /// Handle a single request.
+    ///
+    /// The request and response are message bodies (the type byte and its contents) without the
+    /// length prefix.
+    pub fn handle(&self, request: &[u8]) -> Vec<u8> {
+        let mut reader = Reader::new(request);
+        let response = match reader.u8() {
+            Some(SSH_AGENTC_REQUEST_IDENTITIES) => Some(self.identities_answer()),
+            Some(SSH_AGENTC_SIGN_REQUEST) => self.sign_response(reader),
+            _ => None,
+        };
+        response.unwrap_or_else(|| vec![SSH_AGENT_FAILURE])
+    }
+
+    fn identities_answer(&self) -> Vec<u8> {
+        let mut response = vec![SSH_AGENT_IDENTITIES_ANSWER];
+        put_u32(&mut response, self.identities.len() as u32);
+        for identity in &self.identities {
+            put_string(&mut response, identity.public_key.blob());
+            put_string(&mut response, identity.public_key.comment().as_bytes());
+        }
+        response
+    }
+
+    fn sign_response(&self, mut reader: Reader) -> Option<Vec<u8>> {
+        let blob = reader.string()?;
+        let data = reader.string()?;
+        let flags = reader.u32()?;
+
+        let identity = self
+            .identities
+            .iter()
+            .find(|identity| identity.public_key.blob() == blob)?;
+        let signature = identity
+            .sign(data, flags)
+            .map_err(|err| {
+                warn!(
+                    "failed to sign with {}: {}",
+                    identity.public_key.comment(),
+                    err,
+                )
+            })
+            .ok()?;
+
+        let mut response = vec![SSH_AGENT_SIGN_RESPONSE];
+        put_string(&mut response, &signature);
+        Some(response)
+    }
+
+    /// Serve requests from a client until it disconnects.
+    pub fn serve<S>(&self, mut stream: S) -> io::Result<()>
+    where
+        S: Read + Write,
+    {
+        loop {
+            let mut len = [0; 4];
+            match stream.read_exact(&mut len) {
+                Ok(()) => (),
+                Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                Err(err) => return Err(err),
+            }
+
+            let len = u32::from_be_bytes(len) as usize;
+            if len == 0 || len > MAX_MESSAGE_SIZE {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid request length: {}", len),
+                ));
+            }
+            let mut request = vec![0; len];
+            stream.read_exact(&mut request)?;
+
+            let response = self.handle(&request);
+            let mut message = Vec::with_capacity(4 + response.len());
+            put_string(&mut message, &response);
+            stream.write_all(&message)?;
+        }
+    }
+
+    /// Accept and serve clients on a socket.
+    ///
+    /// Clients are served one at a time. Errors from individual clients are logged and do not
+    /// stop the agent.
+    pub fn listen(&self, listener: &UnixListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            if let Err(err) = self.serve(stream?) {
+                warn!("error serving ssh-agent client: {}", err);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{Key, KeyringSerial};
+
+    use super::*;
+
+    // ssh-rsa with e = 65537 and a tiny modulus.
+    const PUBLIC_KEY: &str = "ssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAAAAwDerQ== test@example";
+
+    struct Stream {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Stream {
+        fn new(input: Vec<u8>) -> Self {
+            Stream {
+                input: Cursor::new(input),
+                output: Vec::new(),
+            }
+        }
+    }
+
+    impl Read for Stream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Stream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn agent() -> Agent {
+        let mut agent = Agent::new();
+        // The key is never used in these tests.
+        let key = unsafe { Key::new(KeyringSerial::new(1).unwrap()) };
+        agent.add_identity(key, PublicKey::parse(PUBLIC_KEY).unwrap());
+        agent
+    }
+
+    #[test]
+    fn test_public_key_parse() {
+        let key = PublicKey::parse(PUBLIC_KEY).unwrap();
+        assert_eq!(key.comment(), "test@example");
+        assert_eq!(
+            key.blob(),
+            [
+                0, 0, 0, 7, b's', b's', b'h', b'-', b'r', b's', b'a', 0, 0, 0, 3, 1, 0, 1, 0, 0, 0,
+                3, 0, 0xde, 0xad,
+            ],
+        );
+
+        let key = PublicKey::parse("ssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAAAAwDerQ==\n").unwrap();
+        assert_eq!(key.comment(), "");
+        let key = PublicKey::parse("ssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAAAAwDerQ== a b  ").unwrap();
+        assert_eq!(key.comment(), "a b");
+    }
+
+    #[test]
+    fn test_public_key_parse_invalid() {
+        assert_eq!(PublicKey::parse(""), Err(PublicKeyError::InvalidFormat));
+        assert_eq!(
+            PublicKey::parse("ssh-rsa"),
+            Err(PublicKeyError::InvalidFormat),
+        );
+        assert_eq!(
+            PublicKey::parse("ssh-ed25519 AAAA"),
+            Err(PublicKeyError::UnsupportedKeyType("ssh-ed25519".into())),
+        );
+        assert_eq!(
+            PublicKey::parse("ssh-rsa !!!!"),
+            Err(PublicKeyError::InvalidBase64),
+        );
+        // A truncated blob.
+        assert_eq!(
+            PublicKey::parse("ssh-rsa AAAAB3NzaC1yc2EAAAADAQAB"),
+            Err(PublicKeyError::InvalidKey),
+        );
+        // A blob for `ssh-dss`.
+        assert_eq!(
+            PublicKey::parse("ssh-rsa AAAAB3NzaC1kc3M="),
+            Err(PublicKeyError::MismatchedKeyType {
+                declared: "ssh-rsa".into(),
+                actual: "ssh-dss".into(),
+            }),
+        );
+    }
+
+    #[test]
+    fn test_request_identities() {
+        let response = Agent::new().handle(&[SSH_AGENTC_REQUEST_IDENTITIES]);
+        assert_eq!(response, [SSH_AGENT_IDENTITIES_ANSWER, 0, 0, 0, 0]);
+
+        let agent = agent();
+        let response = agent.handle(&[SSH_AGENTC_REQUEST_IDENTITIES]);
+        let mut reader = Reader::new(&response);
+        assert_eq!(reader.u8(), Some(SSH_AGENT_IDENTITIES_ANSWER));
+        assert_eq!(reader.u32(), Some(1));
+        assert_eq!(
+            reader.string(),
+            Some(agent.identities()[0].public_key().blob()),
+        );
+        assert_eq!(reader.string(), Some(&b"test@example"[..]));
+        assert!(reader.data.is_empty());
+    }
+
+    #[test]
+    fn test_failures() {
+        let agent = agent();
+        assert_eq!(agent.handle(&[]), [SSH_AGENT_FAILURE]);
+        // SSH_AGENTC_ADD_IDENTITY
+        assert_eq!(agent.handle(&[17]), [SSH_AGENT_FAILURE]);
+        // A truncated sign request.
+        assert_eq!(
+            agent.handle(&[SSH_AGENTC_SIGN_REQUEST, 0, 0]),
+            [SSH_AGENT_FAILURE],
+        );
+
+        // A sign request for an unknown key.
+        let mut request = vec![SSH_AGENTC_SIGN_REQUEST];
+        put_string(&mut request, b"unknown");
+        put_string(&mut request, b"data");
+        put_u32(&mut request, 0);
+        assert_eq!(agent.handle(&request), [SSH_AGENT_FAILURE]);
+    }
+
+    #[test]
+    fn test_serve() {
+        let mut input = Vec::new();
+        put_string(&mut input, &[SSH_AGENTC_REQUEST_IDENTITIES]);
+        put_string(&mut input, &[17]);
+        let mut stream = Stream::new(input);
+
+        Agent::new().serve(&mut stream).unwrap();
+
+        assert_eq!(
+            stream.output,
+            [
+                0,
+                0,
+                0,
+                5,
+                SSH_AGENT_IDENTITIES_ANSWER,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                1,
+                SSH_AGENT_FAILURE
+            ],
+        );
+    }
+
+    #[test]
+    fn test_serve_invalid_length() {
+        let mut stream = Stream::new(vec![0, 0, 0, 0]);
+        let err = Agent::new().serve(&mut stream).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
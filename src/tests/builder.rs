@@ -0,0 +1,173 @@
+// Copyright (c) 2026, Ben Boeckel
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of this project nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use std::thread;
+use std::time::Duration;
+
+use crate::keytypes::User;
+use crate::{Keyring, Permission, SpecialKeyring};
+
+use super::utils;
+use super::utils::kernel::*;
+
+fn staging_keyrings() -> usize {
+    let thread_keyring = Keyring::attach_or_create(SpecialKeyring::Thread).unwrap();
+    let (_, keyrings) = thread_keyring.read().unwrap();
+    keyrings
+        .iter()
+        .filter(|keyring| {
+            keyring
+                .description()
+                .map(|desc| desc.description.starts_with("keyutils:staging:"))
+                .unwrap_or(false)
+        })
+        .count()
+}
+
+#[test]
+fn build_key() {
+    let mut keyring = utils::new_test_keyring();
+    let payload = &b"payload"[..];
+    let key = keyring
+        .build_key::<User, _, _>("build_key", payload)
+        .create()
+        .unwrap();
+
+    let (keys, keyrings) = keyring.read().unwrap();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0], key);
+    assert!(keyrings.is_empty());
+
+    let desc = key.description().unwrap();
+    assert_eq!(desc.uid, *UID);
+    assert_eq!(desc.gid, *GID);
+    assert_eq!(desc.perms, Permission::PRIVATE);
+    assert_eq!(desc.description, "build_key");
+
+    assert_eq!(key.read().unwrap(), payload);
+    assert_eq!(staging_keyrings(), 0);
+}
+
+#[test]
+fn build_key_configured() {
+    let mut keyring = utils::new_test_keyring();
+    let payload = &b"payload"[..];
+    let perms = Permission::USER_READABLE | Permission::GROUP_VIEW;
+    let key = keyring
+        .build_key::<User, _, _>("build_key_configured", payload)
+        .permissions(perms)
+        .timeout(Duration::from_secs(1024))
+        .owner(*UID)
+        .group(*GID)
+        .create()
+        .unwrap();
+
+    let (keys, _) = keyring.read().unwrap();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0], key);
+
+    let desc = key.description().unwrap();
+    assert_eq!(desc.uid, *UID);
+    assert_eq!(desc.gid, *GID);
+    assert_eq!(desc.perms, perms);
+
+    assert_eq!(key.read().unwrap(), payload);
+    assert_eq!(staging_keyrings(), 0);
+}
+
+#[test]
+fn build_key_subsecond_timeout() {
+    let mut keyring = utils::new_test_keyring();
+    let key = keyring
+        .build_key::<User, _, _>("build_key_subsecond_timeout", &b"payload"[..])
+        .timeout(Duration::from_millis(100))
+        .create()
+        .unwrap();
+
+    // The timeout is rounded up rather than truncated to "no expiration".
+    thread::sleep(Duration::from_secs(2));
+
+    let err = key.read().unwrap_err();
+    assert_eq!(err, errno::Errno(libc::EKEYEXPIRED));
+}
+
+#[test]
+fn build_key_without_link_permission() {
+    let mut keyring = utils::new_test_keyring();
+    let payload = &b"payload"[..];
+    let perms = Permission::POSSESSOR_VIEW | Permission::POSSESSOR_READ | Permission::USER_VIEW;
+    let key = keyring
+        .build_key::<User, _, _>("build_key_without_link_permission", payload)
+        .permissions(perms)
+        .create()
+        .unwrap();
+
+    let (keys, _) = keyring.read().unwrap();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0], key);
+
+    let desc = key.description().unwrap();
+    assert_eq!(desc.perms, perms);
+}
+
+#[test]
+fn build_key_replace() {
+    let mut keyring = utils::new_test_keyring();
+    let old = keyring
+        .add_key::<User, _, _>("build_key_replace", &b"old"[..])
+        .unwrap();
+    let new = keyring
+        .build_key::<User, _, _>("build_key_replace", &b"new"[..])
+        .create()
+        .unwrap();
+
+    assert_ne!(old, new);
+
+    let (keys, _) = keyring.read().unwrap();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0], new);
+    assert_eq!(new.read().unwrap(), b"new");
+}
+
+#[test]
+fn build_key_rollback() {
+    let mut keyring = utils::new_test_keyring();
+    let not_a_keyring = keyring
+        .add_key::<User, _, _>("build_key_rollback_target", &b"payload"[..])
+        .unwrap();
+    let mut target = utils::key_as_keyring(&not_a_keyring);
+
+    let err = target
+        .build_key::<User, _, _>("build_key_rollback", &b"payload"[..])
+        .timeout(Duration::from_secs(1024))
+        .create()
+        .unwrap_err();
+    assert_eq!(err, errno::Errno(libc::ENOTDIR));
+
+    let (keys, _) = keyring.read().unwrap();
+    assert_eq!(keys, [not_a_keyring]);
+    assert_eq!(staging_keyrings(), 0);
+}
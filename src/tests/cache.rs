@@ -0,0 +1,230 @@
+// Copyright (c) 2026, Ben Boeckel
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of this project nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use std::cell::Cell;
+use std::thread;
+use std::time::Duration;
+
+use crate::cache::{CacheError, SecretCache};
+use crate::keytypes::User;
+
+use super::utils;
+
+fn refresh_with<'a>(
+    count: &'a Cell<usize>,
+    value: &'a [u8],
+) -> impl FnOnce() -> Result<Vec<u8>, ()> + 'a {
+    move || {
+        count.set(count.get() + 1);
+        Ok(value.into())
+    }
+}
+
+#[test]
+fn cache_miss() {
+    let keyring = utils::new_test_keyring();
+    let mut cache = SecretCache::new((*keyring).clone(), "cache_miss", Duration::from_secs(1024));
+    let count = Cell::new(0);
+
+    assert_eq!(cache.get("token").unwrap(), None);
+
+    let value = cache
+        .get_or_refresh("token", refresh_with(&count, b"value"))
+        .unwrap();
+    assert_eq!(value, b"value");
+    assert_eq!(count.get(), 1);
+
+    assert_eq!(cache.get("token").unwrap().unwrap(), b"value");
+
+    let key = keyring
+        .search_for_key::<User, _, _>("cache_miss:token", None)
+        .unwrap();
+    assert_eq!(key.read().unwrap(), b"value");
+
+    // The lock is released after refreshing.
+    let (keys, _) = keyring.read().unwrap();
+    assert_eq!(keys.len(), 1);
+}
+
+#[test]
+fn cache_hit() {
+    let keyring = utils::new_test_keyring();
+    let mut cache = SecretCache::new((*keyring).clone(), "cache_hit", Duration::from_secs(1024));
+    let count = Cell::new(0);
+
+    cache.insert("token", b"cached").unwrap();
+
+    let value = cache
+        .get_or_refresh("token", refresh_with(&count, b"value"))
+        .unwrap();
+    assert_eq!(value, b"cached");
+    assert_eq!(count.get(), 0);
+}
+
+#[test]
+fn cache_revoked() {
+    let keyring = utils::new_test_keyring();
+    let mut cache = SecretCache::new(
+        (*keyring).clone(),
+        "cache_revoked",
+        Duration::from_secs(1024),
+    );
+    let count = Cell::new(0);
+
+    let key = cache.insert("token", b"revoked").unwrap();
+    key.revoke().unwrap();
+
+    assert_eq!(cache.get("token").unwrap(), None);
+
+    let value = cache
+        .get_or_refresh("token", refresh_with(&count, b"value"))
+        .unwrap();
+    assert_eq!(value, b"value");
+    assert_eq!(count.get(), 1);
+}
+
+#[test]
+fn cache_expired() {
+    let keyring = utils::new_test_keyring();
+    let duration = Duration::from_secs(1);
+    let mut cache = SecretCache::new((*keyring).clone(), "cache_expired", duration);
+    let count = Cell::new(0);
+
+    cache.insert("token", b"expired").unwrap();
+
+    thread::sleep(duration);
+    thread::sleep(duration);
+
+    assert_eq!(cache.get("token").unwrap(), None);
+
+    let value = cache
+        .get_or_refresh("token", refresh_with(&count, b"value"))
+        .unwrap();
+    assert_eq!(value, b"value");
+    assert_eq!(count.get(), 1);
+}
+
+#[test]
+fn cache_subsecond_timeouts() {
+    let keyring = utils::new_test_keyring();
+    let duration = Duration::from_secs(1);
+    // Timeouts which truncate to zero seconds must not create keys which never expire.
+    let mut cache = SecretCache::new(
+        (*keyring).clone(),
+        "cache_subsecond_timeouts",
+        Duration::from_millis(100),
+    )
+    .lock_timeout(Duration::from_millis(100));
+    let count = Cell::new(0);
+
+    let value = cache
+        .get_or_refresh("token", || {
+            let lock = keyring
+                .search_for_key::<User, _, _>("cache_subsecond_timeouts.lock:token", None)
+                .unwrap();
+
+            thread::sleep(duration);
+            thread::sleep(duration);
+
+            // The lock expires even though it is still held.
+            assert!(lock.read().is_err());
+            refresh_with(&count, b"value")()
+        })
+        .unwrap();
+    assert_eq!(value, b"value");
+    assert_eq!(cache.get("token").unwrap().unwrap(), b"value");
+
+    thread::sleep(duration);
+    thread::sleep(duration);
+
+    assert_eq!(cache.get("token").unwrap(), None);
+}
+
+#[test]
+fn cache_invalidate() {
+    let keyring = utils::new_test_keyring();
+    let mut cache = SecretCache::new(
+        (*keyring).clone(),
+        "cache_invalidate",
+        Duration::from_secs(1024),
+    );
+
+    cache.insert("token", b"value").unwrap();
+    cache.invalidate("token").unwrap();
+    assert_eq!(cache.get("token").unwrap(), None);
+
+    // Invalidating a missing value is fine.
+    cache.invalidate("token").unwrap();
+}
+
+#[test]
+fn cache_refresh_error() {
+    let keyring = utils::new_test_keyring();
+    let mut cache = SecretCache::new(
+        (*keyring).clone(),
+        "cache_refresh_error",
+        Duration::from_secs(1024),
+    );
+
+    let err = cache
+        .get_or_refresh("token", || Err::<Vec<u8>, _>("failed"))
+        .unwrap_err();
+    match err {
+        CacheError::Refresh("failed") => (),
+        err => panic!("unexpected error: {:?}", err),
+    }
+
+    assert_eq!(cache.get("token").unwrap(), None);
+
+    // The lock is released on failure.
+    let (keys, _) = keyring.read().unwrap();
+    assert!(keys.is_empty());
+}
+
+#[test]
+fn cache_locked() {
+    let mut keyring = utils::new_test_keyring();
+    let mut cache = SecretCache::new(
+        (*keyring).clone(),
+        "cache_locked",
+        Duration::from_secs(1024),
+    )
+    .lock_timeout(Duration::from_secs(1))
+    .poll_interval(Duration::from_millis(10));
+    let count = Cell::new(0);
+
+    // Simulate another process refreshing the value.
+    keyring
+        .add_key::<User, _, _>("cache_locked.lock:token", &b"other"[..])
+        .unwrap();
+
+    // The lock holder never stores a value, so the value is refreshed after the timeout.
+    let value = cache
+        .get_or_refresh("token", refresh_with(&count, b"value"))
+        .unwrap();
+    assert_eq!(value, b"value");
+    assert_eq!(count.get(), 1);
+}
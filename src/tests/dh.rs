@@ -0,0 +1,141 @@
+// Copyright (c) 2026, Ben Boeckel
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of this project nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use crate::dh::{self, DhPrivateKey, OtherInfo};
+use crate::keytypes::User;
+use crate::{Key, KeyctlHash};
+
+use super::utils;
+
+#[test]
+fn compute_dh_kdf() {
+    let mut keyring = utils::new_test_keyring();
+    let params = dh::MODP_2048.load(&mut keyring).unwrap();
+    // With a private exponent of 16, the shared secret with the generator is 2^16.
+    let private = keyring
+        .add_key::<User, _, _>("compute_dh_kdf:private", &[16][..])
+        .unwrap();
+    let mut secret = vec![0; dh::MODP_2048.bits() / 8];
+    let len = secret.len();
+    secret[len - 3] = 1;
+
+    let info = OtherInfo::new("AES-256")
+        .party_u_info("alice")
+        .party_v_info("bob")
+        .length_prefixed();
+    let hashes = [
+        KeyctlHash::Sha1,
+        KeyctlHash::Sha224,
+        KeyctlHash::Sha256,
+        KeyctlHash::Sha384,
+        KeyctlHash::Sha512,
+    ];
+
+    for hash in hashes.iter() {
+        let res = Key::compute_dh_kdf(
+            &private,
+            params.prime(),
+            params.base(),
+            hash.clone(),
+            Some(&info),
+        );
+        let derived = match res {
+            Err(errno::Errno(libc::EOPNOTSUPP)) => {
+                eprintln!("This kernel does not support Diffie-Hellman operations; skipping.");
+                return;
+            },
+            res => res.unwrap(),
+        };
+        assert!(!derived.is_empty());
+        assert_eq!(
+            derived,
+            dh::kdf(hash, &secret, &info, derived.len()).unwrap(),
+            "{:?}",
+            hash,
+        );
+    }
+}
+
+#[test]
+fn exchange() {
+    let mut keyring = utils::new_test_keyring();
+    let params = dh::FFDHE_2048.load(&mut keyring).unwrap();
+    let alice = DhPrivateKey::generate(&params, &mut keyring, "exchange:alice").unwrap();
+    let bob = DhPrivateKey::generate(&params, &mut keyring, "exchange:bob").unwrap();
+
+    let alice_public = match alice.public_value() {
+        Err(errno::Errno(libc::EOPNOTSUPP)) => {
+            eprintln!("This kernel does not support Diffie-Hellman operations; skipping.");
+            return;
+        },
+        res => res.unwrap(),
+    };
+    let bob_public = bob.public_value().unwrap();
+    assert_ne!(alice_public, bob_public);
+
+    let alice_secret = alice.shared_secret(&mut keyring, &bob_public).unwrap();
+    let bob_secret = bob.shared_secret(&mut keyring, &alice_public).unwrap();
+    assert_eq!(alice_secret.as_bytes(), bob_secret.as_bytes());
+
+    // The peer values are removed after use.
+    let (keys, _) = keyring.read().unwrap();
+    assert_eq!(keys.len(), 4);
+
+    let info = OtherInfo::new("AES-256")
+        .party_u_info("alice")
+        .party_v_info("bob")
+        .length_prefixed();
+    let alice_derived =
+        match alice.derive(&mut keyring, &bob_public, KeyctlHash::Sha256, Some(&info)) {
+            Err(errno::Errno(libc::EOPNOTSUPP)) => {
+                eprintln!("This kernel does not support Diffie-Hellman KDF operations; skipping.");
+                return;
+            },
+            res => res.unwrap(),
+        };
+    let bob_derived = bob
+        .derive(&mut keyring, &alice_public, KeyctlHash::Sha256, Some(&info))
+        .unwrap();
+    assert_eq!(alice_derived.as_bytes(), bob_derived.as_bytes());
+    assert_ne!(alice_derived.as_bytes(), alice_secret.as_bytes());
+}
+
+#[test]
+fn generate_replaces() {
+    let mut keyring = utils::new_test_keyring();
+    let params = dh::FFDHE_2048.load(&mut keyring).unwrap();
+    let first = DhPrivateKey::generate(&params, &mut keyring, "generate_replaces").unwrap();
+    let exponent = first.key().read().unwrap();
+    let second = DhPrivateKey::generate(&params, &mut keyring, "generate_replaces").unwrap();
+
+    // The existing key is not updated with the new exponent.
+    assert_ne!(first.key(), second.key());
+    assert_ne!(second.key().read().unwrap(), exponent);
+    let found = keyring
+        .search_for_key::<User, _, _>("generate_replaces", None)
+        .unwrap();
+    assert_eq!(&found, second.key());
+}
@@ -29,8 +29,11 @@
 pub(crate) mod utils;
 
 mod add;
+mod builder;
+mod cache;
 mod clear;
 mod describe;
+mod dh;
 mod instantiate;
 mod invalidate;
 mod keytype;
@@ -39,6 +42,7 @@ mod newring;
 mod permitting;
 mod reading;
 mod revoke;
+mod rotation;
 mod search;
 mod timeout;
 mod unlink;
@@ -0,0 +1,145 @@
+// Copyright (c) 2026, Ben Boeckel
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of this project nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use std::thread;
+use std::time::Duration;
+
+use crate::keytypes::{logon, Logon, User};
+
+use super::utils;
+
+#[test]
+fn rotate_empty() {
+    let mut keyring = utils::new_test_keyring();
+    let rotation = keyring.rotation::<User, _>("rotate_empty", Duration::from_secs(1024));
+
+    assert!(rotation.generations().unwrap().is_empty());
+    assert_eq!(rotation.current().unwrap(), None);
+    assert_eq!(rotation.previous().unwrap(), None);
+}
+
+#[test]
+fn rotate_user() {
+    let mut keyring = utils::new_test_keyring();
+    let mut rotation = keyring.rotation::<User, _>("rotate_user", Duration::from_secs(1024));
+
+    let v1 = rotation.rotate(&b"v1"[..]).unwrap();
+    assert_eq!(rotation.current().unwrap(), Some(v1.clone()));
+    assert_eq!(rotation.previous().unwrap(), None);
+    assert_eq!(v1.description().unwrap().description, "rotate_user@v1");
+
+    let v2 = rotation.rotate(&b"v2"[..]).unwrap();
+    assert_eq!(rotation.current().unwrap(), Some(v2.clone()));
+    assert_eq!(rotation.previous().unwrap(), Some(v1.clone()));
+    assert_eq!(v2.description().unwrap().description, "rotate_user@v2");
+    assert_eq!(v1.read().unwrap(), b"v1");
+    assert_eq!(v2.read().unwrap(), b"v2");
+
+    // Older generations are removed.
+    let v3 = rotation.rotate(&b"v3"[..]).unwrap();
+    assert_eq!(rotation.current().unwrap(), Some(v3));
+    assert_eq!(rotation.previous().unwrap(), Some(v2));
+
+    let versions = rotation
+        .generations()
+        .unwrap()
+        .into_iter()
+        .map(|generation| generation.version)
+        .collect::<Vec<_>>();
+    assert_eq!(versions, [3, 2]);
+
+    utils::wait_for_key_gc(&v1);
+}
+
+#[test]
+fn rotate_grace_period() {
+    let mut keyring = utils::new_test_keyring();
+    let duration = Duration::from_secs(1);
+    let mut rotation = keyring.rotation::<User, _>("rotate_grace_period", duration);
+
+    let v1 = rotation.rotate(&b"v1"[..]).unwrap();
+    let v2 = rotation.rotate(&b"v2"[..]).unwrap();
+    assert_eq!(rotation.previous().unwrap(), Some(v1.clone()));
+
+    thread::sleep(duration);
+    thread::sleep(duration);
+
+    assert_eq!(rotation.current().unwrap(), Some(v2));
+    assert_eq!(rotation.previous().unwrap(), None);
+
+    let err = v1.read().unwrap_err();
+    assert_eq!(err, errno::Errno(libc::EKEYEXPIRED));
+}
+
+#[test]
+fn rotate_no_grace_period() {
+    let mut keyring = utils::new_test_keyring();
+    let mut rotation =
+        keyring.rotation::<User, _>("rotate_no_grace_period", Duration::from_secs(0));
+
+    let v1 = rotation.rotate(&b"v1"[..]).unwrap();
+    let v2 = rotation.rotate(&b"v2"[..]).unwrap();
+
+    assert_eq!(rotation.current().unwrap(), Some(v2));
+    assert_eq!(rotation.previous().unwrap(), None);
+
+    utils::wait_for_key_gc(&v1);
+}
+
+#[test]
+fn rotate_logon() {
+    let mut keyring = utils::new_test_keyring();
+    let name = logon::Description {
+        subtype: "rotate".into(),
+        description: "logon".into(),
+    };
+    let mut rotation = keyring.rotation::<Logon, _>(name, Duration::from_secs(1024));
+
+    let v1 = rotation.rotate(&b"v1"[..]).unwrap();
+    let v2 = rotation.rotate(&b"v2"[..]).unwrap();
+
+    assert_eq!(rotation.current().unwrap(), Some(v2.clone()));
+    assert_eq!(rotation.previous().unwrap(), Some(v1));
+    assert_eq!(v2.description().unwrap().description, "rotate:logon@v2");
+}
+
+#[test]
+fn rotate_ignores_other_keys() {
+    let mut keyring = utils::new_test_keyring();
+    keyring
+        .add_key::<User, _, _>("rotate_ignores_other_keys@vx", &b"invalid"[..])
+        .unwrap();
+    keyring
+        .add_key::<User, _, _>("rotate_ignores_other_keys", &b"unversioned"[..])
+        .unwrap();
+
+    let mut rotation =
+        keyring.rotation::<User, _>("rotate_ignores_other_keys", Duration::from_secs(1024));
+    assert!(rotation.generations().unwrap().is_empty());
+
+    let v1 = rotation.rotate(&b"v1"[..]).unwrap();
+    assert_eq!(rotation.current().unwrap(), Some(v1));
+}
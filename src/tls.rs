@@ -0,0 +1,211 @@
+// Copyright (c) 2026, Ben Boeckel
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of this project nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+// WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR CONTRIBUTORS BE LIABLE FOR
+// ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON
+// ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! rustls support for asymmetric keys
+//!
+//! A TLS server may keep its private key in the kernel as an `asymmetric` key which the process
+//! cannot read. The handshake signatures are then made by the kernel.
+//!
+//! Requires the `rustls` feature.
+
+use rustls::sign::{Signer, SigningKey};
+use rustls::{Error, SignatureAlgorithm, SignatureScheme};
+use sha2::Digest;
+
+use crate::{Key, KeyctlEncoding, KeyctlHash, KeyctlSupportFlag, PublicKeyOptions, Result};
+
+/// Schemes which may be used with kernel keys in order of preference.
+const SCHEMES: &[SignatureScheme] = &[
+    SignatureScheme::RSA_PSS_SHA512,
+    SignatureScheme::RSA_PSS_SHA384,
+    SignatureScheme::RSA_PSS_SHA256,
+    SignatureScheme::RSA_PKCS1_SHA512,
+    SignatureScheme::RSA_PKCS1_SHA384,
+    SignatureScheme::RSA_PKCS1_SHA256,
+];
+
+/// The kernel options for a signature scheme.
+fn scheme_options(scheme: SignatureScheme) -> Option<PublicKeyOptions> {
+    let (encoding, hash) = match scheme {
+        SignatureScheme::RSA_PKCS1_SHA256 => (KeyctlEncoding::RsassaPkcs1V15, KeyctlHash::Sha256),
+        SignatureScheme::RSA_PKCS1_SHA384 => (KeyctlEncoding::RsassaPkcs1V15, KeyctlHash::Sha384),
+        SignatureScheme::RSA_PKCS1_SHA512 => (KeyctlEncoding::RsassaPkcs1V15, KeyctlHash::Sha512),
+        SignatureScheme::RSA_PSS_SHA256 => (KeyctlEncoding::RsassaPss, KeyctlHash::Sha256),
+        SignatureScheme::RSA_PSS_SHA384 => (KeyctlEncoding::RsassaPss, KeyctlHash::Sha384),
+        SignatureScheme::RSA_PSS_SHA512 => (KeyctlEncoding::RsassaPss, KeyctlHash::Sha512),
+        _ => return None,
+    };
+
+    Some(PublicKeyOptions {
+        encoding: Some(encoding),
+        hash: Some(hash),
+    })
+}
+
+/// Hash a message for a scheme.
+///
+/// The kernel signs digests, so the message must be hashed in userspace.
+fn digest(hash: &KeyctlHash, message: &[u8]) -> Option<Vec<u8>> {
+    Some(match hash {
+        KeyctlHash::Sha256 => sha2::Sha256::digest(message).to_vec(),
+        KeyctlHash::Sha384 => sha2::Sha384::digest(message).to_vec(),
+        KeyctlHash::Sha512 => sha2::Sha512::digest(message).to_vec(),
+        _ => return None,
+    })
+}
+
+/// A rustls signing key backed by an asymmetric key held by the kernel.
+#[derive(Debug, Clone)]
+pub struct KernelSigningKey {
+    key: Key,
+    schemes: Vec<SignatureScheme>,
+}
+
+impl KernelSigningKey {
+    /// Use a key for TLS signatures.
+    ///
+    /// The kernel is asked which of the RSA signature schemes it can sign with using the key.
+    /// Fails with `EOPNOTSUPP` if it supports none of them. Any other error from the query is
+    /// returned as-is.
+    pub fn new(key: Key) -> Result<Self> {
+        let mut schemes = Vec::new();
+        for &scheme in SCHEMES {
+            let options = scheme_options(scheme).expect("all schemes have options");
+            // The kernel rejects queries for combinations it does not support.
+            let supported = match key.pkey_query_support(&options) {
+                Ok(info) => info.supported().contains(KeyctlSupportFlag::SUPPORTS_SIGN),
+                Err(errno::Errno(libc::EOPNOTSUPP))
+                | Err(errno::Errno(libc::EINVAL))
+                | Err(errno::Errno(libc::ENOPKG)) => false,
+                Err(err) => return Err(err),
+            };
+            if supported {
+                schemes.push(scheme);
+            }
+        }
+
+        if schemes.is_empty() {
+            return Err(errno::Errno(libc::EOPNOTSUPP));
+        }
+
+        Ok(KernelSigningKey {
+            key,
+            schemes,
+        })
+    }
+
+    /// The key used for signing.
+    pub fn key(&self) -> &Key {
+        &self.key
+    }
+
+    /// The signature schemes the kernel supports for the key.
+    pub fn schemes(&self) -> &[SignatureScheme] {
+        &self.schemes
+    }
+}
+
+impl SigningKey for KernelSigningKey {
+    fn choose_scheme(&self, offered: &[SignatureScheme]) -> Option<Box<dyn Signer>> {
+        self.schemes
+            .iter()
+            .find(|scheme| offered.contains(scheme))
+            .map(|&scheme| {
+                Box::new(KernelSigner {
+                    key: self.key.clone(),
+                    scheme,
+                }) as Box<dyn Signer>
+            })
+    }
+
+    fn algorithm(&self) -> SignatureAlgorithm {
+        SignatureAlgorithm::RSA
+    }
+}
+
+/// A signer for a single signature scheme.
+#[derive(Debug)]
+struct KernelSigner {
+    key: Key,
+    scheme: SignatureScheme,
+}
+
+impl Signer for KernelSigner {
+    fn sign(&self, message: &[u8]) -> std::result::Result<Vec<u8>, Error> {
+        let options = scheme_options(self.scheme)
+            .ok_or_else(|| Error::General(format!("unsupported scheme {:?}", self.scheme)))?;
+        let hash = options.hash.as_ref().expect("all schemes use a hash");
+        let digest = digest(hash, message)
+            .ok_or_else(|| Error::General(format!("unsupported hash {:?}", hash)))?;
+
+        self.key
+            .sign(&options, &digest)
+            .map_err(|err| Error::General(format!("kernel signing failed: {}", err)))
+    }
+
+    fn scheme(&self) -> SignatureScheme {
+        self.scheme
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustls::SignatureScheme;
+
+    use crate::{KeyctlEncoding, KeyctlHash};
+
+    use super::{digest, scheme_options, SCHEMES};
+
+    #[test]
+    fn test_scheme_options() {
+        for &scheme in SCHEMES {
+            let options = scheme_options(scheme).unwrap();
+            let hash = options.hash.unwrap();
+            let len = digest(&hash, b"").unwrap().len();
+            let expected = match hash {
+                KeyctlHash::Sha256 => 32,
+                KeyctlHash::Sha384 => 48,
+                KeyctlHash::Sha512 => 64,
+                _ => panic!("unexpected hash for {:?}", scheme),
+            };
+            assert_eq!(len, expected);
+        }
+
+        let options = scheme_options(SignatureScheme::RSA_PKCS1_SHA256).unwrap();
+        assert_eq!(options.info(), "enc=pkcs1 hash=sha256");
+        let options = scheme_options(SignatureScheme::RSA_PSS_SHA384).unwrap();
+        assert_eq!(options.info(), "enc=pss hash=sha384");
+        let options = scheme_options(SignatureScheme::RSA_PSS_SHA512).unwrap();
+        match options.encoding {
+            Some(KeyctlEncoding::RsassaPss) => (),
+            encoding => panic!("unexpected encoding: {:?}", encoding),
+        }
+
+        assert!(scheme_options(SignatureScheme::ECDSA_NISTP256_SHA256).is_none());
+        assert!(scheme_options(SignatureScheme::ED25519).is_none());
+        assert!(scheme_options(SignatureScheme::RSA_PKCS1_SHA1).is_none());
+    }
+}